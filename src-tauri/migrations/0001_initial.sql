@@ -0,0 +1,34 @@
+-- Initial local schema. Ids are ObjectId hex strings so records keep the same ids as in MongoDB.
+
+CREATE TABLE IF NOT EXISTS accounts (
+    id TEXT PRIMARY KEY NOT NULL,
+    email TEXT NOT NULL,
+    hashed_password TEXT NOT NULL,
+    created_at TEXT NOT NULL -- RFC 3339 timestamp
+);
+
+CREATE TABLE IF NOT EXISTS projects (
+    id TEXT PRIMARY KEY NOT NULL,
+    account_id TEXT NOT NULL,
+    date_time INTEGER NOT NULL, -- UNIX timestamp
+    sent_date INTEGER,
+    image_path TEXT NOT NULL,
+    is_sent INTEGER NOT NULL DEFAULT 0,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    grade TEXT NOT NULL,
+    is_active INTEGER NOT NULL DEFAULT 1,
+    style TEXT, -- JSON array of style tags
+    holds TEXT  -- JSON array of hold tags
+);
+
+CREATE INDEX IF NOT EXISTS idx_projects_account_active ON projects (account_id, is_active);
+
+-- Annotation markers on a project's image, kept in their original order
+CREATE TABLE IF NOT EXISTS coordinates (
+    project_id TEXT NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    lat REAL NOT NULL,
+    lng REAL NOT NULL,
+    note TEXT NOT NULL, -- JSON array of notes
+    PRIMARY KEY (project_id, position)
+);
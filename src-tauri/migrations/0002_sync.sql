@@ -0,0 +1,22 @@
+-- Change tracking for the MongoDB sync (see src/sync.rs)
+
+ALTER TABLE projects ADD COLUMN updated_at INTEGER; -- UNIX ms of the last change
+ALTER TABLE projects ADD COLUMN field_versions TEXT NOT NULL DEFAULT '{}'; -- JSON object: field -> UNIX ms
+ALTER TABLE projects ADD COLUMN synced_at INTEGER; -- UNIX ms of the last successful sync, NULL if never synced
+
+-- Local changes waiting to be pushed
+CREATE TABLE IF NOT EXISTS sync_queue (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    project_id TEXT NOT NULL,
+    account_id TEXT NOT NULL,
+    operation TEXT NOT NULL, -- 'upsert' or 'delete'
+    queued_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_sync_queue_account ON sync_queue (account_id);
+
+-- Newest remote updated_at already pulled, per account
+CREATE TABLE IF NOT EXISTS sync_state (
+    account_id TEXT PRIMARY KEY NOT NULL,
+    last_pulled_at INTEGER NOT NULL
+);
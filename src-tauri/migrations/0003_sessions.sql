@@ -0,0 +1,16 @@
+-- Logged-in devices and their refresh tokens (see src/session_store.rs)
+-- Sessions are per device, so they are not synced with MongoDB.
+
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT PRIMARY KEY NOT NULL, -- ObjectId hex
+    account_id TEXT NOT NULL,
+    device TEXT NOT NULL,
+    refresh_token_hash TEXT NOT NULL UNIQUE, -- SHA-256 of the current refresh token
+    previous_token_hash TEXT, -- SHA-256 of the token it replaced, to detect reuse
+    created_at TEXT NOT NULL, -- RFC 3339
+    last_used_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_sessions_account ON sessions (account_id);
+CREATE INDEX IF NOT EXISTS idx_sessions_previous_token ON sessions (previous_token_hash);
@@ -0,0 +1,4 @@
+-- Pending password reset per account (see DatabaseHelper::request_password_reset)
+
+ALTER TABLE accounts ADD COLUMN reset_token_hash TEXT; -- SHA-256 of the emailed token, NULL when no reset is pending
+ALTER TABLE accounts ADD COLUMN reset_expires_at TEXT; -- RFC 3339
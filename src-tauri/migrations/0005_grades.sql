@@ -0,0 +1,16 @@
+-- Grade systems (see src/grade.rs)
+
+ALTER TABLE projects ADD COLUMN grade_system TEXT; -- 'v_scale', 'font' or the id of a custom scale
+ALTER TABLE projects ADD COLUMN difficulty INTEGER; -- Grade on the shared difficulty index (10 per V grade)
+
+CREATE INDEX IF NOT EXISTS idx_projects_account_difficulty ON projects (account_id, difficulty);
+
+-- Gym colour-circuit scales, one row per scale
+CREATE TABLE IF NOT EXISTS grade_scales (
+    id TEXT PRIMARY KEY NOT NULL,
+    account_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    circuits TEXT NOT NULL -- JSON array of { name, colour, lowest, highest }
+);
+
+CREATE INDEX IF NOT EXISTS idx_grade_scales_account ON grade_scales (account_id);
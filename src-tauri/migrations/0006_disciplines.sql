@@ -0,0 +1,6 @@
+-- Rope routes next to boulders (see Discipline and SendStyle in src/database_helper.rs)
+
+ALTER TABLE projects ADD COLUMN discipline TEXT NOT NULL DEFAULT 'boulder'; -- 'boulder', 'sport', 'trad' or 'top_rope'
+ALTER TABLE projects ADD COLUMN send_style TEXT; -- 'lead', 'top_rope', 'flash' or 'onsight', only on sent projects
+
+CREATE INDEX IF NOT EXISTS idx_projects_account_discipline ON projects (account_id, discipline);
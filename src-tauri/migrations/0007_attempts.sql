@@ -0,0 +1,14 @@
+-- Attempt log of each project (see Attempt in src/database_helper.rs)
+
+CREATE TABLE IF NOT EXISTS attempts (
+    id TEXT PRIMARY KEY NOT NULL,
+    account_id TEXT NOT NULL,
+    project_id TEXT NOT NULL,
+    timestamp INTEGER NOT NULL, -- UNIX timestamp
+    high_point_annotation INTEGER, -- Index into the project's coordinates
+    high_point_percent REAL, -- 0 to 100, only set when high_point_annotation isn't
+    outcome TEXT NOT NULL, -- 'fall', 'send' or 'flash'
+    notes TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_attempts_account_project ON attempts (account_id, project_id, timestamp);
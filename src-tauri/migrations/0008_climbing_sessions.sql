@@ -0,0 +1,17 @@
+-- Gym or crag visits (see ClimbingSession in src/database_helper.rs)
+
+CREATE TABLE IF NOT EXISTS climbing_sessions (
+    id TEXT PRIMARY KEY NOT NULL,
+    account_id TEXT NOT NULL,
+    started_at INTEGER NOT NULL, -- UNIX timestamp
+    ended_at INTEGER, -- NULL while the session is running
+    location TEXT,
+    effort INTEGER, -- Perceived effort from 1 to 10
+    notes TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_climbing_sessions_account_started ON climbing_sessions (account_id, started_at);
+
+ALTER TABLE attempts ADD COLUMN session_id TEXT; -- The climbing session the attempt was part of
+
+CREATE INDEX IF NOT EXISTS idx_attempts_account_session ON attempts (account_id, session_id);
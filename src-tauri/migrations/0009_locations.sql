@@ -0,0 +1,27 @@
+-- Gyms, crags and their walls (see src/location.rs)
+
+CREATE TABLE IF NOT EXISTS locations (
+    id TEXT PRIMARY KEY NOT NULL,
+    account_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    lat REAL, -- Both set or both NULL
+    lng REAL,
+    environment TEXT NOT NULL DEFAULT 'indoor' -- 'indoor' or 'outdoor'
+);
+
+CREATE INDEX IF NOT EXISTS idx_locations_account ON locations (account_id);
+
+CREATE TABLE IF NOT EXISTS walls (
+    id TEXT PRIMARY KEY NOT NULL,
+    account_id TEXT NOT NULL,
+    location_id TEXT NOT NULL REFERENCES locations (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    angle INTEGER -- Degrees from vertical, negative for slabs
+);
+
+CREATE INDEX IF NOT EXISTS idx_walls_account_location ON walls (account_id, location_id);
+
+ALTER TABLE projects ADD COLUMN location_id TEXT;
+ALTER TABLE projects ADD COLUMN wall_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_projects_account_location ON projects (account_id, location_id, wall_id);
@@ -0,0 +1,10 @@
+-- Wall reset schedules and projects archived by them (see src/reset.rs)
+
+ALTER TABLE walls ADD COLUMN reset_date INTEGER; -- UNIX timestamp of the next reset
+ALTER TABLE walls ADD COLUMN reset_interval_days INTEGER; -- NULL for a one-off reset
+ALTER TABLE walls ADD COLUMN last_reset_at INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_walls_reset_date ON walls (reset_date);
+
+ALTER TABLE projects ADD COLUMN inactive_reason TEXT; -- 'wall_reset', NULL when archived by hand
+ALTER TABLE projects ADD COLUMN inactive_since INTEGER; -- UNIX timestamp
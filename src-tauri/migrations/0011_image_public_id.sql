@@ -0,0 +1,3 @@
+-- Key of each project's image in the image store (Cloudinary's public_id), see src/image_store.rs
+
+ALTER TABLE projects ADD COLUMN image_public_id TEXT; -- NULL for images uploaded before it was stored
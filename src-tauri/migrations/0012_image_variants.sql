@@ -0,0 +1,3 @@
+-- Sizes each project image is stored in (see src/image_pipeline.rs)
+
+ALTER TABLE projects ADD COLUMN image_variants TEXT NOT NULL DEFAULT '[]'; -- JSON array of { variant, url, public_id, width, height }
@@ -0,0 +1,14 @@
+-- At most one running climbing session per account, so two quick start_session calls can't both succeed
+
+-- Older versions could start several: end all but the latest at their start time
+UPDATE climbing_sessions SET ended_at = started_at
+WHERE ended_at IS NULL
+  AND EXISTS (
+    SELECT 1 FROM climbing_sessions AS newer
+    WHERE newer.account_id = climbing_sessions.account_id
+      AND newer.ended_at IS NULL
+      AND (newer.started_at > climbing_sessions.started_at
+           OR (newer.started_at = climbing_sessions.started_at AND newer.id > climbing_sessions.id))
+  );
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_climbing_sessions_running ON climbing_sessions (account_id) WHERE ended_at IS NULL;
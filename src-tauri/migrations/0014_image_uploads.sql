@@ -0,0 +1,11 @@
+-- Images each account uploaded (see src/upload_store.rs), what a project's image fields are checked against
+
+CREATE TABLE IF NOT EXISTS image_uploads (
+    id TEXT PRIMARY KEY NOT NULL,
+    account_id TEXT NOT NULL,
+    public_id TEXT NOT NULL, -- Key in the image store
+    url TEXT NOT NULL,
+    uploaded_at INTEGER NOT NULL -- UNIX timestamp
+);
+
+CREATE INDEX IF NOT EXISTS idx_image_uploads_account_key ON image_uploads (account_id, public_id);
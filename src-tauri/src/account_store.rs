@@ -0,0 +1,151 @@
+// src-tauri/src/account_store.rs
+
+// Storage abstraction for user accounts.
+// DatabaseHelper keeps the hashing/JWT logic and only uses an AccountStore to persist accounts,
+// so registering and logging in work the same on every backend.
+
+
+// IMPORTS
+use async_trait::async_trait;
+use std::sync::Arc;
+
+// MongoDB & BSON
+use mongodb::{Client, Collection, IndexModel, bson::{self, doc, Bson, oid::ObjectId}};
+use mongodb::error::{ErrorKind, WriteFailure};
+use mongodb::options::IndexOptions;
+use futures_util::stream::TryStreamExt;
+
+use crate::database_helper::{normalize_email, Account, PasswordReset};
+use crate::project_store::DATABASE_NAME;
+use crate::error::{HookedError, HookedResult};
+
+pub const ACCOUNTS_COLLECTION: &str = "accounts";
+
+// MongoDB's error code for a unique index violation
+pub const DUPLICATE_KEY_CODE: i32 = 11000;
+
+// The store shared with DatabaseHelper
+pub type SharedAccountStore = Arc<dyn AccountStore>;
+
+// STORE TRAIT
+#[async_trait]
+pub trait AccountStore: Send + Sync {
+    // Creates the unique index on email (and normalises emails stored before it existed). Run at startup.
+    async fn ensure_indexes(&self) -> HookedResult<()>;
+    // Inserts a new account and returns its generated id.
+    // Fails with EmailAlreadyRegistered when the unique email index rejects it.
+    async fn insert(&self, account: Account) -> HookedResult<ObjectId>;
+    // Looks an account up by its email address
+    async fn find_by_email(&self, email: &str) -> HookedResult<Option<Account>>;
+    // Looks an account up by its id
+    async fn find_by_id(&self, account_id: &ObjectId) -> HookedResult<Option<Account>>;
+    // Replaces the password hash (and drops any pending reset, the old token was for the old password)
+    async fn update_password(&self, account_id: &ObjectId, hashed_password: &str) -> HookedResult<()>;
+    // Removes the account itself. Its projects and sessions are deleted by the caller.
+    async fn delete(&self, account_id: &ObjectId) -> HookedResult<()>;
+    // Stores (or clears, with None) the pending password reset of an account
+    async fn set_password_reset(&self, account_id: &ObjectId, reset: Option<PasswordReset>) -> HookedResult<()>;
+    // Looks an account up by the hash of its pending reset token
+    async fn find_by_reset_token(&self, token_hash: &str) -> HookedResult<Option<Account>>;
+    // Sets a new password hash and clears the pending reset, only if `token_hash` is still the pending one.
+    // Returns false when the token was already used.
+    async fn reset_password(&self, account_id: &ObjectId, token_hash: &str, hashed_password: &str) -> HookedResult<bool>;
+}
+
+// MONGODB IMPLEMENTATION
+pub struct MongoAccountStore {
+    collection: Collection<Account>,
+}
+
+impl MongoAccountStore {
+    pub fn new(client: &Client) -> Self {
+        let collection = client.database(DATABASE_NAME).collection::<Account>(ACCOUNTS_COLLECTION);
+        MongoAccountStore { collection }
+    }
+}
+
+#[async_trait]
+impl AccountStore for MongoAccountStore {
+    async fn ensure_indexes(&self) -> HookedResult<()> {
+        // Accounts registered before emails were normalised, one at a time so an account whose normalised email
+        // is already taken can be left alone (like the SQLite store does). Those have to be merged by hand.
+        let stale = doc! { "$expr": { "$ne": ["$email", { "$toLower": { "$trim": { "input": "$email" } } }] } };
+        let accounts: Vec<Account> = self.collection.find(stale, None).await.map_err(HookedError::storage)?
+            .try_collect().await.map_err(HookedError::storage)?;
+
+        for account in accounts {
+            let Some(account_id) = account._id else { continue };
+            let email = normalize_email(&account.email);
+            if email == account.email {
+                continue;
+            }
+            if self.collection.find_one(doc! { "email": &email }, None).await.map_err(HookedError::storage)?.is_some() {
+                eprintln!("Account {} ({}) wasn't normalised, {} is already registered", account_id, account.email, email);
+                continue;
+            }
+            let update = doc! { "$set": { "email": &email } };
+            self.collection.update_one(doc! { "_id": account_id }, update, None).await.map_err(HookedError::storage)?;
+        }
+
+        let index = IndexModel::builder()
+            .keys(doc! { "email": 1 })
+            .options(IndexOptions::builder().unique(true).name("email_unique".to_string()).build())
+            .build();
+        self.collection.create_index(index, None).await.map_err(HookedError::storage)?;
+        Ok(())
+    }
+
+    async fn insert(&self, account: Account) -> HookedResult<ObjectId> {
+        let insert_result = self.collection.insert_one(account, None).await.map_err(|e| match *e.kind {
+            ErrorKind::Write(WriteFailure::WriteError(ref write_error)) if write_error.code == DUPLICATE_KEY_CODE => HookedError::EmailAlreadyRegistered,
+            _ => HookedError::Storage(e.to_string()),
+        })?;
+        insert_result.inserted_id.as_object_id().ok_or_else(|| HookedError::Storage("Inserted id is not an ObjectId".to_string()))
+    }
+
+    async fn find_by_email(&self, email: &str) -> HookedResult<Option<Account>> {
+        self.collection.find_one(doc! { "email": email }, None).await.map_err(HookedError::storage)
+    }
+
+    async fn find_by_id(&self, account_id: &ObjectId) -> HookedResult<Option<Account>> {
+        self.collection.find_one(doc! { "_id": account_id }, None).await.map_err(HookedError::storage)
+    }
+
+    async fn update_password(&self, account_id: &ObjectId, hashed_password: &str) -> HookedResult<()> {
+        let update = doc! { "$set": { "hashed_password": hashed_password }, "$unset": { "password_reset": "" } };
+        let result = self.collection.update_one(doc! { "_id": account_id }, update, None).await.map_err(HookedError::storage)?;
+        if result.matched_count == 0 {
+            return Err(HookedError::NotFound("Account not found".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, account_id: &ObjectId) -> HookedResult<()> {
+        self.collection.delete_one(doc! { "_id": account_id }, None).await.map_err(HookedError::storage)?;
+        Ok(())
+    }
+
+    async fn set_password_reset(&self, account_id: &ObjectId, reset: Option<PasswordReset>) -> HookedResult<()> {
+        let reset = match reset {
+            Some(reset) => bson::to_bson(&reset).map_err(HookedError::storage)?,
+            None => Bson::Null,
+        };
+
+        let update = doc! { "$set": { "password_reset": reset } };
+        self.collection.update_one(doc! { "_id": account_id }, update, None).await.map_err(HookedError::storage)?;
+        Ok(())
+    }
+
+    async fn find_by_reset_token(&self, token_hash: &str) -> HookedResult<Option<Account>> {
+        self.collection.find_one(doc! { "password_reset.token_hash": token_hash }, None).await.map_err(HookedError::storage)
+    }
+
+    async fn reset_password(&self, account_id: &ObjectId, token_hash: &str, hashed_password: &str) -> HookedResult<bool> {
+        // Matching on the token hash makes the token single use, even with two requests at once
+        let filter = doc! { "_id": account_id, "password_reset.token_hash": token_hash };
+        let update = doc! { "$set": { "hashed_password": hashed_password }, "$unset": { "password_reset": "" } };
+
+        let result = self.collection.update_one(filter, update, None).await.map_err(HookedError::storage)?;
+        Ok(result.matched_count == 1)
+    }
+}
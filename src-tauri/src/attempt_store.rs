@@ -0,0 +1,91 @@
+// src-tauri/src/attempt_store.rs
+
+// Storage abstraction for the attempt log of each project (see Attempt in database_helper.rs).
+// A project's attempts counter and sent_date are derived from this log.
+
+
+// IMPORTS
+use async_trait::async_trait;
+use std::sync::Arc;
+
+// MongoDB & BSON
+use mongodb::{Client, Collection, bson::{doc, oid::ObjectId}, options::FindOptions};
+use futures_util::stream::TryStreamExt;
+
+use crate::database_helper::Attempt;
+use crate::project_store::DATABASE_NAME;
+use crate::error::{HookedError, HookedResult};
+
+pub const ATTEMPTS_COLLECTION: &str = "attempts";
+
+// The store shared through Tauri's state
+pub type SharedAttemptStore = Arc<dyn AttemptStore>;
+
+// STORE TRAIT
+#[async_trait]
+pub trait AttemptStore: Send + Sync {
+    // Inserts an attempt, returning its id
+    async fn insert(&self, attempt: Attempt) -> HookedResult<ObjectId>;
+    // Every attempt on one project, oldest first
+    async fn list_for_project(&self, account_id: &ObjectId, project_id: &ObjectId) -> HookedResult<Vec<Attempt>>;
+    // Every attempt made during any of the given climbing sessions, oldest first
+    async fn list_for_sessions(&self, account_id: &ObjectId, session_ids: &[ObjectId]) -> HookedResult<Vec<Attempt>>;
+    // Deletes the log of one project
+    async fn delete_for_project(&self, account_id: &ObjectId, project_id: &ObjectId) -> HookedResult<u64>;
+    // Deletes every attempt of an account
+    async fn delete_for_account(&self, account_id: &ObjectId) -> HookedResult<u64>;
+}
+
+// MONGODB IMPLEMENTATION
+pub struct MongoAttemptStore {
+    collection: Collection<Attempt>,
+}
+
+impl MongoAttemptStore {
+    pub fn new(client: &Client) -> Self {
+        let collection = client.database(DATABASE_NAME).collection::<Attempt>(ATTEMPTS_COLLECTION);
+        MongoAttemptStore { collection }
+    }
+}
+
+#[async_trait]
+impl AttemptStore for MongoAttemptStore {
+    async fn insert(&self, attempt: Attempt) -> HookedResult<ObjectId> {
+        let result = self.collection.insert_one(&attempt, None).await.map_err(HookedError::storage)?;
+        result.inserted_id.as_object_id().ok_or_else(|| HookedError::Storage("Inserted id is not an ObjectId".to_string()))
+    }
+
+    async fn list_for_project(&self, account_id: &ObjectId, project_id: &ObjectId) -> HookedResult<Vec<Attempt>> {
+        let options = FindOptions::builder().sort(doc! { "timestamp": 1, "_id": 1 }).build();
+        let cursor = self.collection
+            .find(doc! { "account_id": account_id, "project_id": project_id }, options)
+            .await
+            .map_err(HookedError::storage)?;
+        cursor.try_collect().await.map_err(HookedError::storage)
+    }
+
+    async fn list_for_sessions(&self, account_id: &ObjectId, session_ids: &[ObjectId]) -> HookedResult<Vec<Attempt>> {
+        if session_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let options = FindOptions::builder().sort(doc! { "timestamp": 1, "_id": 1 }).build();
+        let cursor = self.collection
+            .find(doc! { "account_id": account_id, "session_id": { "$in": session_ids } }, options)
+            .await
+            .map_err(HookedError::storage)?;
+        cursor.try_collect().await.map_err(HookedError::storage)
+    }
+
+    async fn delete_for_project(&self, account_id: &ObjectId, project_id: &ObjectId) -> HookedResult<u64> {
+        let result = self.collection
+            .delete_many(doc! { "account_id": account_id, "project_id": project_id }, None)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(result.deleted_count)
+    }
+
+    async fn delete_for_account(&self, account_id: &ObjectId) -> HookedResult<u64> {
+        let result = self.collection.delete_many(doc! { "account_id": account_id }, None).await.map_err(HookedError::storage)?;
+        Ok(result.deleted_count)
+    }
+}
@@ -0,0 +1,167 @@
+// src-tauri/src/climbing_session_store.rs
+
+// Storage abstraction for climbing sessions (gym or crag visits, see ClimbingSession in database_helper.rs).
+// Attempts point at the session they were part of through Attempt::session_id.
+// A unique index allows one running session (ended_at not set) per account.
+
+
+// IMPORTS
+use async_trait::async_trait;
+use std::sync::Arc;
+
+// MongoDB & BSON
+use mongodb::{Client, Collection, IndexModel, bson::{self, doc, oid::ObjectId, Bson, Document}};
+use mongodb::error::{ErrorKind, WriteFailure};
+use mongodb::options::{FindOneOptions, FindOptions, IndexOptions, ReplaceOptions};
+use futures_util::stream::TryStreamExt;
+
+use crate::account_store::DUPLICATE_KEY_CODE;
+use crate::database_helper::ClimbingSession;
+use crate::project_store::DATABASE_NAME;
+use crate::error::{HookedError, HookedResult};
+
+// `sessions` already holds the login sessions
+pub const CLIMBING_SESSIONS_COLLECTION: &str = "climbing_sessions";
+
+// The store shared through Tauri's state
+pub type SharedClimbingSessionStore = Arc<dyn ClimbingSessionStore>;
+
+// STORE TRAIT
+#[async_trait]
+pub trait ClimbingSessionStore: Send + Sync {
+    // Creates the index allowing one running session per account (ending any extra ones started before it). Run at startup.
+    async fn ensure_indexes(&self) -> HookedResult<()>;
+    // Inserts a new session and returns its generated id.
+    // Fails with a Validation error when the account already has a running session and this one is running too.
+    async fn insert(&self, session: ClimbingSession) -> HookedResult<ObjectId>;
+    // One of the account's sessions
+    async fn find(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<ClimbingSession>>;
+    // The account's session that hasn't ended yet, the latest one if there are several
+    async fn running(&self, account_id: &ObjectId) -> HookedResult<Option<ClimbingSession>>;
+    // Replaces a session the account owns
+    async fn update(&self, session: &ClimbingSession) -> HookedResult<()>;
+    // Sessions started within the (inclusive) range, newest first
+    async fn list(&self, account_id: &ObjectId, from: Option<i64>, to: Option<i64>) -> HookedResult<Vec<ClimbingSession>>;
+    // Deletes every session of an account
+    async fn delete_for_account(&self, account_id: &ObjectId) -> HookedResult<u64>;
+}
+
+// The error for a second running session, whichever store rejects it
+pub fn already_running() -> HookedError {
+    HookedError::Validation("A climbing session is already running".to_string())
+}
+
+// MONGODB IMPLEMENTATION
+pub struct MongoClimbingSessionStore {
+    collection: Collection<ClimbingSession>,
+}
+
+impl MongoClimbingSessionStore {
+    pub fn new(client: &Client) -> Self {
+        let collection = client.database(DATABASE_NAME).collection::<ClimbingSession>(CLIMBING_SESSIONS_COLLECTION);
+        MongoClimbingSessionStore { collection }
+    }
+}
+
+#[async_trait]
+impl ClimbingSessionStore for MongoClimbingSessionStore {
+    async fn ensure_indexes(&self) -> HookedResult<()> {
+        let documents = self.collection.clone_with_type::<Document>();
+        // Sessions saved before running ones stored ended_at as null
+        documents.update_many(doc! { "ended_at": { "$exists": false } }, doc! { "$set": { "ended_at": Bson::Null } }, None)
+            .await
+            .map_err(HookedError::storage)?;
+
+        // Older versions could start several: end all but the latest at their start time
+        let options = FindOptions::builder().sort(doc! { "account_id": 1, "started_at": -1 }).build();
+        let running: Vec<ClimbingSession> = self.collection.find(doc! { "ended_at": null }, options).await
+            .map_err(HookedError::storage)?
+            .try_collect().await
+            .map_err(HookedError::storage)?;
+        for (index, session) in running.iter().enumerate() {
+            if index > 0 && running[index - 1].account_id == session.account_id {
+                documents.update_one(doc! { "_id": session._id }, doc! { "$set": { "ended_at": session.started_at } }, None)
+                    .await
+                    .map_err(HookedError::storage)?;
+            }
+        }
+
+        let index = IndexModel::builder()
+            .keys(doc! { "account_id": 1 })
+            .options(IndexOptions::builder()
+                .unique(true)
+                .partial_filter_expression(doc! { "ended_at": { "$type": "null" } })
+                .name("running_session_unique".to_string())
+                .build())
+            .build();
+        self.collection.create_index(index, None).await.map_err(HookedError::storage)?;
+        Ok(())
+    }
+
+    async fn insert(&self, session: ClimbingSession) -> HookedResult<ObjectId> {
+        let result = self.collection.clone_with_type::<Document>()
+            .insert_one(running_document(&session)?, None)
+            .await
+            .map_err(|e| match *e.kind {
+                ErrorKind::Write(WriteFailure::WriteError(ref write_error)) if write_error.code == DUPLICATE_KEY_CODE => already_running(),
+                _ => HookedError::Storage(e.to_string()),
+            })?;
+        result.inserted_id.as_object_id().ok_or_else(|| HookedError::Storage("Inserted id is not an ObjectId".to_string()))
+    }
+
+    async fn find(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<ClimbingSession>> {
+        self.collection.find_one(doc! { "_id": id, "account_id": account_id }, None).await.map_err(HookedError::storage)
+    }
+
+    async fn running(&self, account_id: &ObjectId) -> HookedResult<Option<ClimbingSession>> {
+        let options = FindOneOptions::builder().sort(doc! { "started_at": -1 }).build();
+        self.collection
+            .find_one(doc! { "account_id": account_id, "ended_at": null }, options)
+            .await
+            .map_err(HookedError::storage)
+    }
+
+    async fn update(&self, session: &ClimbingSession) -> HookedResult<()> {
+        let id = session._id.ok_or_else(|| HookedError::Validation("Session ID is required for update".to_string()))?;
+        let options = ReplaceOptions::builder().upsert(false).build();
+        let result = self.collection.clone_with_type::<Document>()
+            .replace_one(doc! { "_id": id, "account_id": session.account_id }, running_document(session)?, options)
+            .await
+            .map_err(HookedError::storage)?;
+        if result.matched_count == 0 {
+            return Err(HookedError::NotFound("Climbing session not found".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn list(&self, account_id: &ObjectId, from: Option<i64>, to: Option<i64>) -> HookedResult<Vec<ClimbingSession>> {
+        let mut filter = doc! { "account_id": account_id };
+        let mut range = Document::new();
+        if let Some(from) = from {
+            range.insert("$gte", from);
+        }
+        if let Some(to) = to {
+            range.insert("$lte", to);
+        }
+        if !range.is_empty() {
+            filter.insert("started_at", range);
+        }
+
+        let options = FindOptions::builder().sort(doc! { "started_at": -1 }).build();
+        let cursor = self.collection.find(filter, options).await.map_err(HookedError::storage)?;
+        cursor.try_collect().await.map_err(HookedError::storage)
+    }
+
+    async fn delete_for_account(&self, account_id: &ObjectId) -> HookedResult<u64> {
+        let result = self.collection.delete_many(doc! { "account_id": account_id }, None).await.map_err(HookedError::storage)?;
+        Ok(result.deleted_count)
+    }
+}
+
+// A session as stored in MongoDB. Running sessions keep ended_at as null instead of leaving it out,
+// since the unique index only covers documents where it is null.
+fn running_document(session: &ClimbingSession) -> HookedResult<Document> {
+    let mut document = bson::to_document(session).map_err(HookedError::storage)?;
+    document.entry("ended_at".to_string()).or_insert(Bson::Null);
+    Ok(document)
+}
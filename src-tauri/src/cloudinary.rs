@@ -0,0 +1,222 @@
+// src-tauri/src/cloudinary.rs
+
+// Cloudinary delivery URLs. They look like
+// https://res.cloudinary.com/<cloud>/<resource type>/<delivery type>/[s--<signature>--/][<transformations>/][v<version>/]<public id>[.<format>]
+// where the transformations are any number of segments such as `c_fill,w_300` and the public id can contain
+// folders and dots. New projects store the public_id returned by the upload, this is for the older ones.
+
+
+// IMPORTS
+use reqwest::Url;
+use sha1::{Digest, Sha1};
+
+const CLOUDINARY_HOST: &str = "res.cloudinary.com";
+
+// Transformation parameters (https://cloudinary.com/documentation/transformation_reference)
+const TRANSFORMATION_KEYS: [&str; 41] = [
+    "a", "ac", "af", "ar", "b", "bo", "br", "c", "co", "cs", "d", "dl", "dn", "dpr", "du", "e", "eo", "f", "fl", "fn", "fps",
+    "g", "h", "if", "ki", "l", "o", "p", "pg", "q", "r", "so", "sp", "t", "u", "vc", "vs", "w", "x", "y", "z",
+];
+
+// Formats Cloudinary delivers images in. Only these are taken as an extension, other dots belong to the public id.
+const IMAGE_FORMATS: [&str; 16] = [
+    "jpg", "jpeg", "jpe", "png", "gif", "webp", "avif", "heic", "heif", "bmp", "tif", "tiff", "svg", "ico", "jxl", "pdf",
+];
+
+// The parts of a Cloudinary delivery URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloudinaryUrl {
+    pub cloud_name: String,
+    pub resource_type: String, // image, video or raw
+    pub delivery_type: String, // upload, private, authenticated, ...
+    pub transformations: Vec<String>, // One entry per segment, e.g. "c_fill,w_300"
+    pub version: Option<u64>,
+    pub public_id: String, // With its folders, without the format
+    pub format: Option<String>,
+}
+
+impl CloudinaryUrl {
+    // Parses a delivery URL, None if it isn't one
+    pub fn parse(url: &str) -> Option<Self> {
+        let url = Url::parse(url).ok()?;
+        if url.host_str()? != CLOUDINARY_HOST {
+            return None;
+        }
+
+        let segments: Vec<String> = url.path_segments()?.map(percent_decode).collect();
+        let [cloud_name, resource_type, delivery_type, rest @ ..] = segments.as_slice() else { return None };
+        if cloud_name.is_empty() || rest.is_empty() {
+            return None;
+        }
+
+        let mut rest = rest;
+        // URL signature of signed delivery URLs
+        if rest.len() > 1 && rest[0].starts_with("s--") && rest[0].ends_with("--") {
+            rest = &rest[1..];
+        }
+
+        // Transformations come first, then the version, and everything after that is the public id.
+        // Without a version the public id starts at the first segment that isn't a transformation.
+        let count = rest.iter().take(rest.len() - 1).take_while(|segment| is_transformation(segment)).count();
+        let (transformations, rest) = rest.split_at(count);
+        let (version, id_segments) = match rest.split_first() {
+            Some((segment, id_segments)) if !id_segments.is_empty() => match parse_version(segment) {
+                Some(version) => (Some(version), id_segments),
+                None => (None, rest),
+            },
+            _ => (None, rest),
+        };
+
+        let path = id_segments.join("/");
+        let (public_id, format) = match path.rsplit_once('.') {
+            Some((id, extension)) if !id.is_empty() && IMAGE_FORMATS.contains(&extension.to_ascii_lowercase().as_str()) => {
+                (id.to_string(), Some(extension.to_string()))
+            }
+            _ => (path, None),
+        };
+        if public_id.is_empty() || public_id.ends_with('/') {
+            return None;
+        }
+
+        Some(CloudinaryUrl {
+            cloud_name: cloud_name.clone(),
+            resource_type: resource_type.clone(),
+            delivery_type: delivery_type.clone(),
+            transformations: transformations.to_vec(),
+            version,
+            public_id,
+            format,
+        })
+    }
+}
+
+// Signature of an API request: SHA-1 of the parameters sorted by name, as `name=value` joined with `&`,
+// followed by the API secret. file, api_key, resource_type and cloud_name aren't signed.
+pub fn sign(params: &[(&str, String)], api_secret: &str) -> String {
+    let mut params: Vec<&(&str, String)> = params.iter().filter(|(_, value)| !value.is_empty()).collect();
+    params.sort_by_key(|(name, _)| *name);
+    let joined = params.iter().map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<_>>().join("&");
+
+    let mut hasher = Sha1::new();
+    hasher.update(format!("{}{}", joined, api_secret));
+    format!("{:x}", hasher.finalize())
+}
+
+// `v1712345678` -> 1712345678
+fn parse_version(segment: &str) -> Option<u64> {
+    let digits = segment.strip_prefix('v')?;
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+// A transformation segment is comma-separated parameters such as `w_300` or `c_fill`
+fn is_transformation(segment: &str) -> bool {
+    !segment.is_empty() && segment.split(',').all(|parameter| {
+        parameter.split_once('_').is_some_and(|(key, value)| TRANSFORMATION_KEYS.contains(&key) && !value.is_empty())
+    })
+}
+
+// Decodes %XX escapes, keeping malformed ones as they are
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn public_id(url: &str) -> Option<String> {
+        CloudinaryUrl::parse(url).map(|parsed| parsed.public_id)
+    }
+
+    #[test]
+    fn parses_a_plain_upload_url() {
+        let parsed = CloudinaryUrl::parse("https://res.cloudinary.com/demo/image/upload/v1712345678/sample.jpg").unwrap();
+        assert_eq!(parsed, CloudinaryUrl {
+            cloud_name: "demo".to_string(),
+            resource_type: "image".to_string(),
+            delivery_type: "upload".to_string(),
+            transformations: Vec::new(),
+            version: Some(1712345678),
+            public_id: "sample".to_string(),
+            format: Some("jpg".to_string()),
+        });
+    }
+
+    #[test]
+    fn keeps_folders_in_the_public_id() {
+        assert_eq!(public_id("https://res.cloudinary.com/demo/image/upload/v1/hooked/projects/boulder.png").as_deref(), Some("hooked/projects/boulder"));
+        // Without a version the folders still aren't taken for transformations
+        assert_eq!(public_id("https://res.cloudinary.com/demo/image/upload/hooked/boulder.png").as_deref(), Some("hooked/boulder"));
+    }
+
+    #[test]
+    fn skips_chained_transformations_and_signatures() {
+        let parsed = CloudinaryUrl::parse(
+            "https://res.cloudinary.com/demo/image/upload/s--abc123--/c_fill,w_300,h_200/e_sepia/v42/hooked/boulder.webp",
+        ).unwrap();
+        assert_eq!(parsed.transformations, vec!["c_fill,w_300,h_200".to_string(), "e_sepia".to_string()]);
+        assert_eq!(parsed.version, Some(42));
+        assert_eq!(parsed.public_id, "hooked/boulder");
+    }
+
+    #[test]
+    fn only_known_parameters_make_a_transformation() {
+        assert!(is_transformation("c_fill,w_300"));
+        assert!(is_transformation("q_auto"));
+        assert!(!is_transformation("my_folder"));
+        assert!(!is_transformation("w_"));
+        assert!(!is_transformation("w_300,folder"));
+        assert!(!is_transformation(""));
+    }
+
+    #[test]
+    fn keeps_dots_that_arent_an_image_format() {
+        assert_eq!(public_id("https://res.cloudinary.com/demo/image/upload/v1/route.v2.final.jpeg").as_deref(), Some("route.v2.final"));
+        assert_eq!(public_id("https://res.cloudinary.com/demo/image/upload/v1/route.v2").as_deref(), Some("route.v2"));
+    }
+
+    #[test]
+    fn decodes_percent_escapes() {
+        assert_eq!(public_id("https://res.cloudinary.com/demo/image/upload/v1/my%20crag/%C3%A9t%C3%A9.jpg").as_deref(), Some("my crag/été"));
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn rejects_urls_from_elsewhere() {
+        assert_eq!(public_id("https://example.com/demo/image/upload/v1/sample.jpg"), None);
+        assert_eq!(public_id("https://res.cloudinary.com/demo/image/upload"), None);
+        assert_eq!(public_id("https://res.cloudinary.com/demo/image/upload/"), None);
+        assert_eq!(public_id("/local/path/sample.jpg"), None);
+        assert_eq!(public_id("not a url"), None);
+    }
+
+    #[test]
+    fn signs_sorted_parameters() {
+        // Example from Cloudinary's signature docs
+        let params = [("timestamp", "1315060510".to_string()), ("public_id", "sample_image".to_string()), ("eager", "w_400,h_300,c_pad|w_260,h_200,c_crop".to_string())];
+        assert_eq!(sign(&params, "abcd"), "bfd09f95f331f558cbd1320e67aa8d488770583e");
+    }
+}
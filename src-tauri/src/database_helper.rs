@@ -1,32 +1,39 @@
 // src-tauri/src/database_helper.rs
 
-// Initialises the MongoDB database
+// Account logic (password hashing, login sessions) on top of the AccountStore and SessionStore
 
 
 // IMPORTS
 // Serde for JSON serialization
 use serde::{Deserialize, Serialize};
 
-// MongoDB & BSON
-use mongodb::{Client, bson::{self, doc}, Collection};
-use bson::oid::ObjectId;
+// BSON
+use mongodb::bson::oid::ObjectId;
 
 // Date handling
-use chrono::Utc;
+use chrono::{Duration, Utc};
 
-// Error types
-use mongodb::error::Error;
+use std::collections::HashMap;
+
+// Account and session persistence
+use crate::account_store::SharedAccountStore;
+use crate::session_store::SharedSessionStore;
+
+// Outgoing email
+use crate::mailer::{Email, SharedMailer};
+
+use crate::error::{HookedError, HookedResult};
 
 // Secure password hashing & verifying
 use argon2::{Argon2, PasswordHasher, PasswordVerifier, password_hash::{PasswordHash, SaltString, rand_core::OsRng}};
 
-// JWT tokens
-use jsonwebtoken::{encode, EncodingKey, Header};
+// Login tokens
+use crate::session;
 
 // DATA STRUCTS
 
 // Stores a coordinate marker on the image
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")] // Ensures field names in MongoDB match JSON camelCase
 pub struct Coordinate {
     pub lat: f64,
@@ -34,25 +41,185 @@ pub struct Coordinate {
     pub note: Vec<String>,
 }
 
-// Represents a bouldering project
-#[derive(Debug, Serialize, Deserialize)]
+// What kind of climb a project is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Discipline {
+    #[default]
+    Boulder, // Projects saved before disciplines existed are boulders
+    Sport,
+    Trad,
+    TopRope,
+}
+
+impl Discipline {
+    pub const ALL: [Discipline; 4] = [Discipline::Boulder, Discipline::Sport, Discipline::Trad, Discipline::TopRope];
+
+    // Stored name, same as the serde one
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Discipline::Boulder => "boulder",
+            Discipline::Sport => "sport",
+            Discipline::Trad => "trad",
+            Discipline::TopRope => "top_rope",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|discipline| discipline.as_str() == value)
+    }
+
+    pub fn is_route(self) -> bool {
+        self != Discipline::Boulder
+    }
+}
+
+// How a project was sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SendStyle {
+    Lead,    // Sport or trad, after earlier attempts (redpoint)
+    TopRope, // Any rope discipline
+    Flash,   // First try, with beta
+    Onsight, // First try, without beta
+}
+
+impl SendStyle {
+    pub const ALL: [SendStyle; 4] = [SendStyle::Lead, SendStyle::TopRope, SendStyle::Flash, SendStyle::Onsight];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SendStyle::Lead => "lead",
+            SendStyle::TopRope => "top_rope",
+            SendStyle::Flash => "flash",
+            SendStyle::Onsight => "onsight",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|style| style.as_str() == value)
+    }
+
+    // Lead only makes sense on sport and trad routes, top-rope only on routes
+    pub fn fits(self, discipline: Discipline) -> bool {
+        match self {
+            SendStyle::Lead => matches!(discipline, Discipline::Sport | Discipline::Trad),
+            SendStyle::TopRope => discipline.is_route(),
+            SendStyle::Flash | SendStyle::Onsight => true,
+        }
+    }
+}
+
+// Why a project was made inactive, None when the user archived it by hand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InactiveReason {
+    WallReset, // Its wall was reset by the setters (shown as "expired by reset")
+}
+
+impl InactiveReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InactiveReason::WallReset => "wall_reset",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "wall_reset" => Some(InactiveReason::WallReset),
+            _ => None,
+        }
+    }
+}
+
+// The sizes each project image is stored in (see image_pipeline.rs)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageVariant {
+    Thumbnail, // For project cards
+    Medium,
+    Original, // What was uploaded, after processing
+}
+
+impl ImageVariant {
+    pub const ALL: [ImageVariant; 3] = [ImageVariant::Thumbnail, ImageVariant::Medium, ImageVariant::Original];
+
+    // Added to the original's key to get the variant's, so variants can be found from the original
+    pub fn key_suffix(self) -> &'static str {
+        match self {
+            ImageVariant::Thumbnail => "_thumb",
+            ImageVariant::Medium => "_medium",
+            ImageVariant::Original => "",
+        }
+    }
+}
+
+// One stored size of a project's image
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredVariant {
+    pub variant: ImageVariant,
+    pub url: String,
+    pub public_id: String, // Key in the image store
+    pub width: u32,
+    pub height: u32,
+}
+
+// An image stored by upload_image for an account (every variant has its own). Stored in the `image_uploads` collection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageUpload {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    pub account_id: ObjectId,
+    pub public_id: String, // Key in the image store
+    pub url: String,
+    pub uploaded_at: i64, // UNIX timestamp
+}
+
+// Represents a climbing project (a boulder or a route)
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Project {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub _id: Option<ObjectId>, // MongoDB uses _id
+    #[serde(default)] // Set from the caller's session, the frontend doesn't need to send it
     pub account_id: ObjectId,  // 🔥 foreign key to Account
     pub date_time: i64, // UNIX timestamp
     pub sent_date: Option<i64>, // Optional UNIX timestamp
     pub image_path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_public_id: Option<String>, // Key of the image in the image store, so deleting it doesn't depend on parsing image_path
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub image_variants: Vec<StoredVariant>, // Sizes made at upload, empty for images uploaded before them
     pub is_sent: i32, // Use 1 or 0 to match the JS client
     pub attempts: i32,
+    #[serde(default)]
+    pub discipline: Discipline,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub send_style: Option<SendStyle>, // Only set on sent projects
     pub grade: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location_id: Option<ObjectId>, // The gym or crag (see location.rs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wall_id: Option<ObjectId>, // A wall of that location
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grade_system: Option<String>, // Id of the grade's system (see grade.rs), checked when the project is saved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<i32>, // The grade on the shared difficulty index, used for sorting and stats
     pub is_active: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inactive_reason: Option<InactiveReason>, // Only set on inactive projects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inactive_since: Option<i64>, // UNIX timestamp, only set on inactive projects
     pub coordinates: Vec<Coordinate>,
     pub style: Option<Vec<String>>,
     pub holds: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<i64>, // UNIX timestamp (ms) of the last change, set by the stores for sync
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub field_versions: HashMap<String, i64>, // Last change (ms) per field, used to resolve sync conflicts
 }
 
-// Normalization to ensure sent_date is only set when is_sent = 1
+// Normalization to ensure sent_date and send_style are only set when is_sent = 1,
+// and inactive_reason and inactive_since only when is_active = 0
 impl Project {
     pub fn normalize(&mut self) {
         if self.is_sent != 0 {
@@ -61,15 +228,175 @@ impl Project {
             }
         } else {
             self.sent_date = None;
+            self.send_style = None;
+        }
+
+        if self.is_active != 0 {
+            self.inactive_reason = None;
+            self.inactive_since = None;
+        } else if self.inactive_since.is_none() {
+            self.inactive_since = Some(Utc::now().timestamp());
+        }
+    }
+
+    // Sets attempts, is_sent and sent_date from the attempt log (oldest first).
+    // Projects without a logged attempt keep their hand-entered counter and send.
+    pub fn apply_attempts(&mut self, log: &[Attempt]) {
+        if log.is_empty() {
+            return;
+        }
+        self.attempts = log.len() as i32;
+
+        match log.iter().find(|attempt| attempt.outcome.is_send()) {
+            Some(first_send) => {
+                self.is_sent = 1;
+                self.sent_date = Some(first_send.timestamp);
+                if first_send.outcome == AttemptOutcome::Flash && self.send_style.is_none() {
+                    self.send_style = Some(SendStyle::Flash);
+                }
+            }
+            None => {
+                self.is_sent = 0;
+                self.sent_date = None;
+                self.send_style = None;
+            }
+        }
+    }
+
+    // Checks the send style fits the discipline (e.g. no lead sends on a boulder)
+    pub fn validate_send_style(&self) -> HookedResult<()> {
+        match self.send_style {
+            Some(style) if !style.fits(self.discipline) => Err(HookedError::Validation(format!(
+                "A {} send isn't possible on a {} project", style.as_str().replace('_', "-"), self.discipline.as_str().replace('_', "-"),
+            ))),
+            _ => Ok(()),
         }
     }
+
+    // The smallest stored variant at least `width` pixels wide, or the widest one when none is
+    pub fn image_for_width(&self, width: u32) -> Option<&StoredVariant> {
+        let widest = self.image_variants.iter().max_by_key(|variant| variant.width);
+        self.image_variants.iter()
+            .filter(|variant| variant.width >= width)
+            .min_by_key(|variant| variant.width)
+            .or(widest)
+    }
 }
 
-// Represents a JWT claim
-#[derive(Debug, Serialize, Deserialize)]
-struct Claims {
-    sub: String, // Subject (usually the user identifier)
-    exp: usize,  // Expiration time
+// How an attempt ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttemptOutcome {
+    Fall,
+    Send,
+    Flash, // Sent on the first attempt
+}
+
+impl AttemptOutcome {
+    pub const ALL: [AttemptOutcome; 3] = [AttemptOutcome::Fall, AttemptOutcome::Send, AttemptOutcome::Flash];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AttemptOutcome::Fall => "fall",
+            AttemptOutcome::Send => "send",
+            AttemptOutcome::Flash => "flash",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|outcome| outcome.as_str() == value)
+    }
+
+    pub fn is_send(self) -> bool {
+        self != AttemptOutcome::Fall
+    }
+}
+
+// How far an attempt got: the last annotation marker reached, or a share of the climb
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HighPoint {
+    Annotation(u32), // Index into the project's coordinates
+    Percent(f64),    // 0 to 100
+}
+
+// One go on a project. Stored in the `attempts` collection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attempt {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    #[serde(default)] // Set from the caller's session
+    pub account_id: ObjectId,
+    pub project_id: ObjectId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<ObjectId>, // The climbing session it was part of, the running one when left out
+    #[serde(default)]
+    pub timestamp: i64, // UNIX timestamp, the time it was logged when left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub high_point: Option<HighPoint>,
+    pub outcome: AttemptOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+impl Attempt {
+    // Checks the attempt fits its project and the attempts logged before it (oldest first)
+    pub fn validate(&self, project: &Project, earlier: &[Attempt]) -> HookedResult<()> {
+        match self.high_point {
+            Some(HighPoint::Annotation(index)) if index as usize >= project.coordinates.len() => {
+                return Err(HookedError::Validation(format!("The project has no annotation {}", index)));
+            }
+            Some(HighPoint::Percent(percent)) if !(0.0..=100.0).contains(&percent) => {
+                return Err(HookedError::Validation("The high point has to be between 0 and 100 percent".to_string()));
+            }
+            _ => {}
+        }
+        if self.outcome == AttemptOutcome::Flash && earlier.iter().any(|attempt| attempt.timestamp <= self.timestamp) {
+            return Err(HookedError::Validation("Only the first attempt can be a flash".to_string()));
+        }
+        Ok(())
+    }
+}
+
+// Lowest and highest perceived effort (RPE) of a climbing session
+pub const MIN_EFFORT: u8 = 1;
+pub const MAX_EFFORT: u8 = 10;
+
+// One visit to the gym or crag. Stored in the `climbing_sessions` collection.
+// Not to be confused with session::Session (the logged-in caller) or DeviceSession.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClimbingSession {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    pub account_id: ObjectId,
+    pub started_at: i64, // UNIX timestamp
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<i64>, // None while the session is running
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effort: Option<u8>, // Perceived effort from MIN_EFFORT to MAX_EFFORT, usually given when the session ends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+impl ClimbingSession {
+    pub fn is_running(&self) -> bool {
+        self.ended_at.is_none()
+    }
+
+    // Checks the effort is on the scale and the session doesn't end before it started
+    pub fn validate(&self) -> HookedResult<()> {
+        if let Some(effort) = self.effort {
+            if !(MIN_EFFORT..=MAX_EFFORT).contains(&effort) {
+                return Err(HookedError::Validation(format!("Effort has to be between {} and {}", MIN_EFFORT, MAX_EFFORT)));
+            }
+        }
+        if self.ended_at.is_some_and(|ended_at| ended_at < self.started_at) {
+            return Err(HookedError::Validation("A session can't end before it started".to_string()));
+        }
+        Ok(())
+    }
 }
 
 // User accounts stored in the DB
@@ -80,96 +407,461 @@ pub struct Account {
     pub email: String,         // User email (unique)
     pub hashed_password: String, // Securely stored password
     pub created_at: chrono::DateTime<chrono::Utc>, // Timestamp
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_reset: Option<PasswordReset>, // Set while a reset email is outstanding
 }
 
-// DATABASE HELPER
-// Wraps a MongoDB client to share across calls
-pub struct DatabaseHelper {
-    client: Client, // Holds a MongoDB client to interact with the database.
+// A requested password reset. Only the hash of the emailed token is stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordReset {
+    pub token_hash: String,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
 }
 
-// Implement all the logic on it
-impl DatabaseHelper {
-    // NEW INSTANCE
-    // Connect to MongoDB, do a test ping
-    pub async fn new() -> Result<Self, mongodb::error::Error> { // Connect to MongoDB.
-        let mongo_uri = std::env::var("MONGODB_URI").expect("MONGODB_URI must be set");
-
-        let client = Client::with_uri_str(mongo_uri).await?; // Asynchronously connects to MongoDB using the URI.
+// One logged-in device. Stored in the `sessions` collection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceSession {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    pub account_id: ObjectId,
+    pub device: String,                      // Name the frontend gave the device at login
+    pub refresh_token_hash: String,          // SHA-256 of the current refresh token, never the token itself
+    pub previous_token_hash: Option<String>, // The token it replaced, presenting it again means it was stolen
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
 
-        client.database("admin").run_command(doc! {"ping": 1}, None).await?; // client.database("admin"): Selects the admin database. .run_command(doc! {"ping": 1}, None).await?: Executes a ping command to check the database connection.
-        Ok(DatabaseHelper { client }) // Returns an instance of DatabaseHelper with the connected client.
-    }
+// What login and refresh_session hand back to the frontend
+#[derive(Debug, Serialize)]
+pub struct SessionTokens {
+    pub token: String,           // Short-lived JWT sent with every command
+    pub expires_at: i64,         // When `token` expires (UNIX seconds)
+    pub refresh_token: String,   // Trades for a new pair of tokens, only valid once
+    pub account_id: String,
+}
 
-    // PROJECTS
+// DATABASE HELPER
+// Wraps the account and session stores to share across calls
+pub struct DatabaseHelper {
+    accounts: SharedAccountStore, // Whichever backend (MongoDB or SQLite) was picked at startup.
+    sessions: SharedSessionStore,
+    mailer: Option<SharedMailer>, // None when no email can be sent, password resets are refused
+}
 
-    // Fetch a single project by its ObjectId
-    pub async fn get_project_by_id(&self, id: &ObjectId) -> Result<Option<Project>, mongodb::error::Error> {
-        let database = self.client.database("hooked_db");
-        let collection: Collection<Project> = database.collection("projects");
+// Password policy
+const MIN_PASSWORD_LENGTH: usize = 8;
+const MAX_PASSWORD_LENGTH: usize = 128; // Argon2 accepts more, but nobody types this much
 
-        let filter = doc! { "_id": id };
-        let project = collection.find_one(filter, None).await?;
+// How long an emailed password reset token can be used
+const RESET_TOKEN_LIFETIME_MINUTES: i64 = 30;
 
-        Ok(project)
+// Implement all the logic on it
+impl DatabaseHelper {
+    // NEW INSTANCE
+    pub fn new(accounts: SharedAccountStore, sessions: SharedSessionStore, mailer: Option<SharedMailer>) -> Self {
+        DatabaseHelper { accounts, sessions, mailer }
     }
 
     // ACCOUNTS
 
     // Create a new user account (register)
-    pub async fn create_account(&self, email: &str, password: &str) -> Result<ObjectId, Error> {
-        let database = self.client.database("hooked_db");
-        let collection: Collection<Account> = database.collection("accounts");
+    pub async fn create_account(&self, email: &str, password: &str) -> HookedResult<ObjectId> {
+        let email = normalize_email(email);
+        validate_email(&email)?;
+        validate_password(password)?;
+
+        // Friendlier than waiting for the unique index to reject the insert
+        if self.accounts.find_by_email(&email).await?.is_some() {
+            return Err(HookedError::EmailAlreadyRegistered);
+        }
 
         // Hash the password securely
-        let salt = SaltString::generate(&mut OsRng); // Generates a secure random salt. (A salt is a random string of data that is generated each time a user creates (or changes) their password.It is then combined (concatenated) with the user's password before hashing.)
-        let argon2 = Argon2::default(); // Uses secure default Argon2 parameters
-        let hashed_password = argon2.hash_password(password.as_bytes(), &salt)
-            .map_err(|_| Error::from(std::io::Error::new(std::io::ErrorKind::Other, "Hashing error")))?
-            .to_string();
+        let hashed_password = Self::hash_password(password)?;
 
         // Insert into database
         let new_account = Account {
             _id: None,
-            email: email.to_string(),
+            email,
             hashed_password,
             created_at: Utc::now(),
+            password_reset: None,
         };
 
-        let insert_result = collection.insert_one(new_account, None).await?;
-        Ok(insert_result.inserted_id.as_object_id().unwrap()) // Return inserted ID
+        self.accounts.insert(new_account).await // Return inserted ID
     }
 
     // LOGIN
-    // Verifies password + returns a JWT token
-    pub async fn login(&self, email: &str, password: &str) -> Result<(String, ObjectId), Error> {
-        let database = self.client.database("hooked_db");
-        let collection: Collection<Account> = database.collection("accounts");
-
-        let filter = doc! { "email": email };
-        let user = collection.find_one(filter, None).await?;
+    // Verifies password + starts a session for this device
+    pub async fn login(&self, email: &str, password: &str, device: &str) -> HookedResult<SessionTokens> {
+        let user = self.accounts.find_by_email(&normalize_email(email)).await?;
 
         if let Some(user) = user {
-            let parsed_hash = PasswordHash::new(&user.hashed_password).unwrap();
-            let argon2 = Argon2::default();
+            if Self::password_matches(&user, password)? {
+                // Password correct. Store the session and generate the tokens
+                let account_id = user._id.ok_or_else(|| HookedError::Internal("Account ID missing".to_string()))?;
+                let refresh_token = session::generate_token();
+                let now = Utc::now();
+
+                self.sessions.insert(DeviceSession {
+                    _id: None,
+                    account_id,
+                    device: device.to_string(),
+                    refresh_token_hash: session::hash_token(&refresh_token),
+                    previous_token_hash: None,
+                    created_at: now,
+                    last_used_at: now,
+                    expires_at: now + Duration::days(session::REFRESH_TOKEN_LIFETIME_DAYS),
+                }).await?;
+
+                return Self::tokens(account_id, refresh_token);
+            }
+        }
+
+        Err(HookedError::Unauthorized("Invalid credentials".to_string()))
+    }
+
+    // Checks a password against the account's stored Argon2 hash
+    fn password_matches(account: &Account, password: &str) -> HookedResult<bool> {
+        let parsed_hash = PasswordHash::new(&account.hashed_password).map_err(|_| HookedError::Internal("Stored password hash is invalid".to_string()))?;
+        let argon2 = Argon2::default();
+        Ok(argon2.verify_password(password.as_bytes(), &parsed_hash).is_ok())
+    }
+
+    // Loads an account and makes sure `password` is its current password.
+    // Used to confirm sensitive changes from an already logged-in user.
+    pub async fn verify_password(&self, account_id: &ObjectId, password: &str) -> HookedResult<Account> {
+        let account = self.accounts.find_by_id(account_id).await?.ok_or_else(|| HookedError::NotFound("Account not found".to_string()))?;
+
+        if !Self::password_matches(&account, password)? {
+            return Err(HookedError::Unauthorized("Incorrect password".to_string()));
+        }
+        Ok(account)
+    }
+
+    // Replaces the password after checking the current one
+    pub async fn change_password(&self, account_id: &ObjectId, old_password: &str, new_password: &str) -> HookedResult<()> {
+        self.verify_password(account_id, old_password).await?;
+        validate_password(new_password)?;
+
+        let hashed_password = Self::hash_password(new_password)?;
+        self.accounts.update_password(account_id, &hashed_password).await
+    }
+
+    // Removes the account and logs out all its devices.
+    // Its projects (and their images) must be deleted first, see the delete_account command.
+    pub async fn delete_account(&self, account_id: &ObjectId) -> HookedResult<()> {
+        self.sessions.delete_for_account(account_id).await?;
+        self.accounts.delete(account_id).await
+    }
+
+    // Hashes a password with Argon2 for storage
+    fn hash_password(password: &str) -> HookedResult<String> {
+        let salt = SaltString::generate(&mut OsRng); // Generates a secure random salt. (A salt is a random string of data that is generated each time a user creates (or changes) their password.It is then combined (concatenated) with the user's password before hashing.)
+        let argon2 = Argon2::default(); // Uses secure default Argon2 parameters
+        argon2.hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|_| HookedError::Internal("Hashing error".to_string()))
+    }
+
+    // PASSWORD RESET
+
+    // Emails a single-use reset token to the account's address.
+    // Succeeds whether or not the email is registered, so it can't be used to find out who has an account.
+    pub async fn request_password_reset(&self, email: &str) -> HookedResult<()> {
+        let Some(mailer) = &self.mailer else {
+            return Err(HookedError::Internal("Password reset is unavailable, no email provider is configured".to_string()));
+        };
+        let Some(account) = self.accounts.find_by_email(&normalize_email(email)).await? else {
+            return Ok(());
+        };
+        let account_id = account._id.ok_or_else(|| HookedError::Internal("Account ID missing".to_string()))?;
+
+        // A new request replaces any earlier token
+        let token = session::generate_token();
+        let reset = PasswordReset {
+            token_hash: session::hash_token(&token),
+            expires_at: Utc::now() + Duration::minutes(RESET_TOKEN_LIFETIME_MINUTES),
+        };
+        self.accounts.set_password_reset(&account_id, Some(reset)).await?;
+
+        mailer.send(Email {
+            to: account.email,
+            subject: "Reset your Hooked password".to_string(),
+            body: format!(
+                "Someone asked to reset the password of your Hooked account.\n\n\
+                 Enter this code in the app to choose a new password:\n\n{}\n\n\
+                 The code expires in {} minutes and can only be used once. If you didn't ask for this, you can ignore this email.",
+                token, RESET_TOKEN_LIFETIME_MINUTES,
+            ),
+        }).await.map_err(HookedError::Internal)
+    }
+
+    // Sets a new password if the reset token is valid, then logs every device out
+    pub async fn confirm_password_reset(&self, token: &str, new_password: &str) -> HookedResult<()> {
+        let invalid = || HookedError::Validation("Invalid or expired reset code".to_string());
+        let token_hash = session::hash_token(token.trim());
+
+        let account = self.accounts.find_by_reset_token(&token_hash).await?.ok_or_else(invalid)?;
+        let account_id = account._id.ok_or_else(|| HookedError::Internal("Account ID missing".to_string()))?;
+
+        let expired = account.password_reset.as_ref().is_none_or(|reset| reset.expires_at <= Utc::now());
+        if expired {
+            self.accounts.set_password_reset(&account_id, None).await?;
+            return Err(invalid());
+        }
+
+        validate_password(new_password)?;
+        let hashed_password = Self::hash_password(new_password)?;
+        if !self.accounts.reset_password(&account_id, &token_hash, &hashed_password).await? {
+            return Err(invalid()); // Used by another request in the meantime
+        }
+
+        // Whoever had the old password shouldn't stay logged in
+        self.sessions.delete_for_account(&account_id).await?;
+        Ok(())
+    }
+
+    // SESSIONS
+
+    // Trades a refresh token for a new access token and a new refresh token.
+    // The old refresh token stops working, so each one can only be used once.
+    pub async fn refresh_session(&self, refresh_token: &str) -> HookedResult<SessionTokens> {
+        let token_hash = session::hash_token(refresh_token);
+        let stored = self.sessions.find_by_token_hash(&token_hash).await?
+            .ok_or_else(|| HookedError::Unauthorized("Unauthorized: session not found".to_string()))?;
+        let session_id = stored._id.ok_or_else(|| HookedError::Internal("Session ID missing".to_string()))?;
+
+        // An already rotated token was used again: someone else has a copy, end the session for both
+        if stored.refresh_token_hash != token_hash {
+            self.sessions.delete(&session_id).await?;
+            return Err(HookedError::Unauthorized("Unauthorized: refresh token reuse detected, please log in again".to_string()));
+        }
+
+        if stored.expires_at <= Utc::now() {
+            self.sessions.delete(&session_id).await?;
+            return Err(HookedError::Unauthorized("Unauthorized: session expired".to_string()));
+        }
+
+        let new_token = session::generate_token();
+        let expires_at = Utc::now() + Duration::days(session::REFRESH_TOKEN_LIFETIME_DAYS);
+        let rotated = self.sessions
+            .rotate(&session_id, &token_hash, &session::hash_token(&new_token), expires_at)
+            .await?;
 
-            if argon2.verify_password(password.as_bytes(), &parsed_hash).is_ok() {
-                // Password correct. Generate JWT Token
-                let expiration = Utc::now().timestamp() as usize + 3600; // 1-hour expiry
-                let claims = Claims { sub: email.to_string(), exp: expiration };
-                let secret = "my_secret_key"; // WARNING: should be ENV VAR in production!
+        // Another refresh with the same token got there first
+        if !rotated {
+            return Err(HookedError::Unauthorized("Unauthorized: refresh token already used".to_string()));
+        }
 
-                let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_ref()))
-                    .map_err(|_| Error::from(std::io::Error::new(std::io::ErrorKind::Other, "JWT Error")))?;
+        Self::tokens(stored.account_id, new_token)
+    }
 
-                let account_id = user._id.ok_or_else(|| 
-                    Error::from(std::io::Error::new(std::io::ErrorKind::Other, "Account ID missing"))
-                )?;
+    // Ends the session a refresh token belongs to. Unknown tokens are ignored, the device is logged out either way.
+    pub async fn logout(&self, refresh_token: &str) -> HookedResult<()> {
+        let token_hash = session::hash_token(refresh_token);
 
-                return Ok((token, account_id));
+        if let Some(stored) = self.sessions.find_by_token_hash(&token_hash).await? {
+            if let Some(session_id) = stored._id {
+                self.sessions.delete(&session_id).await?;
             }
         }
+        Ok(())
+    }
+
+    // Ends every session of an account, returning how many devices were logged out
+    pub async fn logout_all_devices(&self, account_id: &ObjectId) -> HookedResult<u64> {
+        self.sessions.delete_for_account(account_id).await
+    }
+
+    // Pairs a fresh access token with a refresh token
+    fn tokens(account_id: ObjectId, refresh_token: String) -> HookedResult<SessionTokens> {
+        let (token, expires_at) = session::issue_token(&account_id)?;
+        Ok(SessionTokens { token, expires_at, refresh_token, account_id: account_id.to_hex() })
+    }
+}
+
+// VALIDATION
+
+// Emails are stored trimmed and lowercased, so "Me@Mail.com " and "me@mail.com" are the same account
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+fn invalid_email() -> HookedError {
+    HookedError::Validation("Invalid email address".to_string())
+}
+
+// A light format check (something@domain.tld). Whether the address exists is only known by emailing it.
+pub fn validate_email(email: &str) -> HookedResult<()> {
+    let Some((local, domain)) = email.split_once('@') else {
+        return Err(invalid_email());
+    };
+
+    let valid = email.len() <= 254
+        && !local.is_empty()
+        && !domain.contains('@')
+        && !email.chars().any(char::is_whitespace)
+        && domain.split('.').count() >= 2
+        && domain.split('.').all(|label| !label.is_empty());
+
+    if valid { Ok(()) } else { Err(invalid_email()) }
+}
+
+// Password policy: 8 to 128 characters, with at least one letter and one digit
+pub fn validate_password(password: &str) -> HookedResult<()> {
+    let length = password.chars().count();
+
+    if length < MIN_PASSWORD_LENGTH {
+        return Err(HookedError::Validation(format!("Password too weak: use at least {} characters", MIN_PASSWORD_LENGTH)));
+    }
+    if length > MAX_PASSWORD_LENGTH {
+        return Err(HookedError::Validation(format!("Password too weak: use at most {} characters", MAX_PASSWORD_LENGTH)));
+    }
+    if !password.chars().any(char::is_alphabetic) || !password.chars().any(|c| c.is_ascii_digit()) {
+        return Err(HookedError::Validation("Password too weak: use both letters and numbers".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // A minimal active, unsent project, shared by the tests of other modules
+    pub(crate) fn project() -> Project {
+        Project {
+            _id: Some(ObjectId::new()),
+            account_id: ObjectId::new(),
+            date_time: 1_700_000_000,
+            sent_date: None,
+            image_path: String::new(),
+            image_public_id: None,
+            image_variants: Vec::new(),
+            is_sent: 0,
+            attempts: 0,
+            discipline: Discipline::default(),
+            send_style: None,
+            grade: "V3".to_string(),
+            location_id: None,
+            wall_id: None,
+            grade_system: None,
+            difficulty: None,
+            is_active: 1,
+            inactive_reason: None,
+            inactive_since: None,
+            coordinates: Vec::new(),
+            style: None,
+            holds: None,
+            updated_at: None,
+            field_versions: HashMap::new(),
+        }
+    }
+
+    fn attempt(outcome: AttemptOutcome, timestamp: i64) -> Attempt {
+        Attempt {
+            _id: Some(ObjectId::new()),
+            account_id: ObjectId::new(),
+            project_id: ObjectId::new(),
+            session_id: None,
+            timestamp,
+            high_point: None,
+            outcome,
+            notes: None,
+        }
+    }
+
+    #[test]
+    fn first_logged_send_sets_the_send() {
+        let mut sent = project();
+        sent.apply_attempts(&[attempt(AttemptOutcome::Fall, 10), attempt(AttemptOutcome::Send, 20), attempt(AttemptOutcome::Send, 30)]);
+        assert_eq!((sent.attempts, sent.is_sent, sent.sent_date), (3, 1, Some(20)));
+        assert_eq!(sent.send_style, None);
+
+        let mut flashed = project();
+        flashed.apply_attempts(&[attempt(AttemptOutcome::Flash, 10)]);
+        assert_eq!(flashed.send_style, Some(SendStyle::Flash));
+    }
+
+    #[test]
+    fn log_without_a_send_clears_a_manual_send() {
+        let mut project = Project { is_sent: 1, sent_date: Some(5), send_style: Some(SendStyle::Flash), ..project() };
+        project.apply_attempts(&[attempt(AttemptOutcome::Fall, 10), attempt(AttemptOutcome::Fall, 20)]);
+        assert_eq!((project.attempts, project.is_sent, project.sent_date, project.send_style), (2, 0, None, None));
+    }
+
+    #[test]
+    fn empty_log_keeps_the_manual_values() {
+        let mut project = Project { attempts: 4, is_sent: 1, sent_date: Some(5), ..project() };
+        project.apply_attempts(&[]);
+        assert_eq!((project.attempts, project.is_sent, project.sent_date), (4, 1, Some(5)));
+    }
+
+    #[test]
+    fn accepts_plain_email_addresses() {
+        assert!(validate_email("climber@example.com").is_ok());
+        assert!(validate_email("first.last+tag@mail.example.co.uk").is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_email_addresses() {
+        for email in ["", "climber", "@example.com", "climber@", "climber@example", "climber@example.", "climber@.com",
+            "climber@a..com", "a@b@example.com", "climb er@example.com"] {
+            assert!(validate_email(email).is_err(), "{} should be rejected", email);
+        }
+    }
+
+    #[test]
+    fn email_length_boundary() {
+        let domain = "@example.com";
+        let longest = format!("{}{}", "a".repeat(254 - domain.len()), domain);
+        assert!(validate_email(&longest).is_ok());
+        assert!(validate_email(&format!("a{}", longest)).is_err());
+    }
 
-        Err(Error::from(std::io::Error::new(std::io::ErrorKind::Other, "Invalid credentials")))
+    #[test]
+    fn password_length_boundaries() {
+        assert!(validate_password(&format!("{}1", "a".repeat(MIN_PASSWORD_LENGTH - 2))).is_err());
+        assert!(validate_password(&format!("{}1", "a".repeat(MIN_PASSWORD_LENGTH - 1))).is_ok());
+        assert!(validate_password(&format!("{}1", "a".repeat(MAX_PASSWORD_LENGTH - 1))).is_ok());
+        assert!(validate_password(&format!("{}1", "a".repeat(MAX_PASSWORD_LENGTH))).is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn password_needs_letters_and_digits() {
+        assert!(validate_password("abcdefgh").is_err());
+        assert!(validate_password("12345678").is_err());
+        assert!(validate_password("abcdefg1").is_ok());
+        // Length counts characters, not bytes
+        assert!(validate_password("ééééééé1").is_ok());
+    }
+
+    fn with_variants(widths: &[(ImageVariant, u32)]) -> Project {
+        let mut project = project();
+        project.image_variants = widths.iter().map(|&(variant, width)| StoredVariant {
+            variant,
+            url: format!("https://example.com/{}.webp", width),
+            public_id: width.to_string(),
+            width,
+            height: width,
+        }).collect();
+        project
+    }
+
+    #[test]
+    fn image_for_width_picks_the_smallest_wide_enough_variant() {
+        let project = with_variants(&[(ImageVariant::Original, 2048), (ImageVariant::Medium, 1024), (ImageVariant::Thumbnail, 320)]);
+        let width_for = |width| project.image_for_width(width).map(|variant| variant.width);
+        assert_eq!(width_for(1), Some(320));
+        assert_eq!(width_for(320), Some(320));
+        assert_eq!(width_for(321), Some(1024));
+        assert_eq!(width_for(1500), Some(2048));
+        // Wider than every variant gets the widest
+        assert_eq!(width_for(4000), Some(2048));
+    }
+
+    #[test]
+    fn image_for_width_without_variants() {
+        assert!(project().image_for_width(320).is_none());
+    }
+}
@@ -0,0 +1,83 @@
+// src-tauri/src/error.rs
+
+// The error type every command, store and helper returns.
+// It reaches the frontend as `{ code, message }`, so the UI can branch on `code`
+// (e.g. send the user back to login on "Unauthorized") instead of matching message text.
+
+
+// IMPORTS
+use std::fmt;
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use crate::session::SessionError;
+
+pub type HookedResult<T> = Result<T, HookedError>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookedError {
+    NotFound(String),       // The record doesn't exist, or belongs to another account
+    Unauthorized(String),   // Missing, expired or invalid session, or wrong password
+    Validation(String),     // Bad input: malformed id, invalid email, weak password, ...
+    EmailAlreadyRegistered, // create_account with an email that already has an account
+    Storage(String),        // MongoDB / SQLite failures
+    ImageService(String),   // Cloudinary (or another image backend) failures
+    Internal(String),       // Anything else: hashing, signing, misconfiguration
+}
+
+impl HookedError {
+    // Wraps a driver or (de)serialisation error, e.g. `.map_err(HookedError::storage)`
+    pub fn storage(error: impl fmt::Display) -> Self {
+        HookedError::Storage(error.to_string())
+    }
+
+    // Stable identifier sent to the frontend
+    pub fn code(&self) -> &'static str {
+        match self {
+            HookedError::NotFound(_) => "NotFound",
+            HookedError::Unauthorized(_) => "Unauthorized",
+            HookedError::Validation(_) => "Validation",
+            HookedError::EmailAlreadyRegistered => "EmailAlreadyRegistered",
+            HookedError::Storage(_) => "Storage",
+            HookedError::ImageService(_) => "ImageService",
+            HookedError::Internal(_) => "Internal",
+        }
+    }
+}
+
+impl fmt::Display for HookedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookedError::EmailAlreadyRegistered => write!(f, "An account with this email already exists"),
+            HookedError::NotFound(message)
+            | HookedError::Unauthorized(message)
+            | HookedError::Validation(message)
+            | HookedError::Storage(message)
+            | HookedError::ImageService(message)
+            | HookedError::Internal(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for HookedError {}
+
+// Serialised as { "code": "NotFound", "message": "Project not found" }
+impl Serialize for HookedError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("HookedError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+// CONVERSIONS
+
+impl From<SessionError> for HookedError {
+    fn from(error: SessionError) -> Self {
+        match error {
+            SessionError::NotInitialized | SessionError::Secret(_) => HookedError::Internal(error.to_string()),
+            _ => HookedError::Unauthorized(error.to_string()),
+        }
+    }
+}
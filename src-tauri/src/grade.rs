@@ -0,0 +1,531 @@
+// src-tauri/src/grade.rs
+
+// Grade systems: the built-in boulder scales (V-scale, Fontainebleau), route scales (YDS, French, UIAA),
+// and gym colour-circuit scales defined per account.
+// Every grade maps to a numeric difficulty, which is what projects are sorted and compared by, and what
+// converting a grade between scales goes through. Boulder grades share one index (10 per V grade) and
+// route grades another (10 per French grade from 3), so only grades of the same kind can be converted.
+
+
+// IMPORTS
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+use crate::database_helper::{Discipline, Project};
+use crate::error::{HookedError, HookedResult};
+
+// Position on the difficulty index. V0 (and French 3) is 0 and every V grade (French grade) above adds DIFFICULTY_STEP.
+pub type Difficulty = i32;
+pub const DIFFICULTY_STEP: Difficulty = 10;
+
+// Ids of the built-in systems, as stored in Project.grade_system
+pub const V_SCALE_ID: &str = "v_scale";
+pub const FONT_SCALE_ID: &str = "font";
+pub const YDS_ID: &str = "yds";
+pub const FRENCH_ID: &str = "french";
+pub const UIAA_ID: &str = "uiaa";
+
+// Easiest first, one step apart. Same lists as src/stores/settingsStore.ts.
+pub const V_SCALE_GRADES: [&str; 18] = ["V0", "V1", "V2", "V3", "V4", "V5", "V6", "V7", "V8", "V9", "V10", "V11", "V12", "V13", "V14", "V15", "V16", "V17"];
+pub const FONT_SCALE_GRADES: [&str; 18] = ["4", "5", "5+", "6A/6A+", "6B/6B+", "6C/6C+", "7A", "7A+", "7B/7B+", "7B+/7C", "7C+", "8A", "8A+", "8B", "8B+", "8C", "8C+", "9A"];
+
+// Route grades with their difficulty on the route index. French is one step per grade,
+// YDS and UIAA are placed against it following the usual conversion tables.
+const FRENCH_GRADES: [(&str, Difficulty); 31] = [
+    ("3", 0), ("4a", 10), ("4b", 20), ("4c", 30), ("5a", 40), ("5b", 50), ("5c", 60),
+    ("6a", 70), ("6a+", 80), ("6b", 90), ("6b+", 100), ("6c", 110), ("6c+", 120),
+    ("7a", 130), ("7a+", 140), ("7b", 150), ("7b+", 160), ("7c", 170), ("7c+", 180),
+    ("8a", 190), ("8a+", 200), ("8b", 210), ("8b+", 220), ("8c", 230), ("8c+", 240),
+    ("9a", 250), ("9a+", 260), ("9b", 270), ("9b+", 280), ("9c", 290), ("9c+", 300),
+];
+const YDS_GRADES: [(&str, Difficulty); 30] = [
+    ("5.4", 10), ("5.5", 20), ("5.6", 30), ("5.7", 40), ("5.8", 50), ("5.9", 60),
+    ("5.10a", 70), ("5.10b", 80), ("5.10c", 90), ("5.10d", 95),
+    ("5.11a", 100), ("5.11b", 110), ("5.11c", 120), ("5.11d", 130),
+    ("5.12a", 140), ("5.12b", 150), ("5.12c", 160), ("5.12d", 170),
+    ("5.13a", 180), ("5.13b", 190), ("5.13c", 200), ("5.13d", 210),
+    ("5.14a", 220), ("5.14b", 230), ("5.14c", 240), ("5.14d", 250),
+    ("5.15a", 260), ("5.15b", 270), ("5.15c", 280), ("5.15d", 290),
+];
+const UIAA_GRADES: [(&str, Difficulty); 27] = [
+    ("III", 0), ("IV", 10), ("IV+", 20), ("V-", 30), ("V", 40), ("V+", 50), ("VI-", 60),
+    ("VI", 70), ("VI+", 80), ("VII-", 90), ("VII", 100), ("VII+", 110), ("VIII-", 120),
+    ("VIII", 130), ("VIII+", 140), ("IX-", 155), ("IX", 170), ("IX+", 180), ("X-", 190),
+    ("X", 210), ("X+", 220), ("XI-", 230), ("XI", 240), ("XI+", 250), ("XII-", 260),
+    ("XII", 270), ("XII+", 280),
+];
+
+// One grade of a system
+#[derive(Debug, Clone, Serialize)]
+pub struct Grade {
+    pub name: String,
+    pub difficulty: Difficulty,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub colour: Option<String>, // Hold colour of a circuit
+}
+
+// GRADE SYSTEM TRAIT
+pub trait GradeSystem: Send + Sync {
+    fn id(&self) -> String;
+    fn name(&self) -> String;
+    // Every grade of the system, easiest first
+    fn grades(&self) -> Vec<Grade>;
+
+    // Route scales grade sport, trad and top-rope projects, the others grade boulders
+    fn for_routes(&self) -> bool {
+        false
+    }
+
+    fn fits(&self, discipline: Discipline) -> bool {
+        self.for_routes() == discipline.is_route()
+    }
+
+    // Difficulty of a grade of this system (case-insensitive), None if it isn't one
+    fn difficulty(&self, grade: &str) -> Option<Difficulty> {
+        self.find(grade).map(|grade| grade.difficulty)
+    }
+
+    // The grade as the system spells it
+    fn find(&self, grade: &str) -> Option<Grade> {
+        let grade = grade.trim();
+        self.grades().into_iter().find(|candidate| candidate.name.eq_ignore_ascii_case(grade))
+    }
+
+    // The grade closest to a difficulty. Halfway between two grades rounds down.
+    fn grade_at(&self, difficulty: Difficulty) -> Option<Grade> {
+        self.grades().into_iter().min_by_key(|grade| ((grade.difficulty - difficulty).abs(), grade.difficulty))
+    }
+}
+
+// Grades listed easiest first, one DIFFICULTY_STEP apart from V0
+fn stepped_grades(names: &[&str]) -> Vec<Grade> {
+    names.iter().enumerate().map(|(index, name)| Grade {
+        name: name.to_string(),
+        difficulty: index as Difficulty * DIFFICULTY_STEP,
+        colour: None,
+    }).collect()
+}
+
+// Grades listed easiest first with their difficulty
+fn listed_grades(grades: &[(&str, Difficulty)]) -> Vec<Grade> {
+    grades.iter().map(|(name, difficulty)| Grade {
+        name: name.to_string(),
+        difficulty: *difficulty,
+        colour: None,
+    }).collect()
+}
+
+// BUILT-IN SCALES
+pub struct VScale;
+
+impl GradeSystem for VScale {
+    fn id(&self) -> String {
+        V_SCALE_ID.to_string()
+    }
+
+    fn name(&self) -> String {
+        "V-Scale".to_string()
+    }
+
+    fn grades(&self) -> Vec<Grade> {
+        stepped_grades(&V_SCALE_GRADES)
+    }
+}
+
+// Fontainebleau, lined up one to one with the V-scale like the frontend does
+pub struct FontScale;
+
+impl GradeSystem for FontScale {
+    fn id(&self) -> String {
+        FONT_SCALE_ID.to_string()
+    }
+
+    fn name(&self) -> String {
+        "Font Scale".to_string()
+    }
+
+    fn grades(&self) -> Vec<Grade> {
+        stepped_grades(&FONT_SCALE_GRADES)
+    }
+}
+
+// Yosemite Decimal System, used in North America
+pub struct YdsScale;
+
+impl GradeSystem for YdsScale {
+    fn id(&self) -> String {
+        YDS_ID.to_string()
+    }
+
+    fn name(&self) -> String {
+        "YDS".to_string()
+    }
+
+    fn grades(&self) -> Vec<Grade> {
+        listed_grades(&YDS_GRADES)
+    }
+
+    fn for_routes(&self) -> bool {
+        true
+    }
+}
+
+// French sport grades
+pub struct FrenchScale;
+
+impl GradeSystem for FrenchScale {
+    fn id(&self) -> String {
+        FRENCH_ID.to_string()
+    }
+
+    fn name(&self) -> String {
+        "French".to_string()
+    }
+
+    fn grades(&self) -> Vec<Grade> {
+        listed_grades(&FRENCH_GRADES)
+    }
+
+    fn for_routes(&self) -> bool {
+        true
+    }
+}
+
+pub struct UiaaScale;
+
+impl GradeSystem for UiaaScale {
+    fn id(&self) -> String {
+        UIAA_ID.to_string()
+    }
+
+    fn name(&self) -> String {
+        "UIAA".to_string()
+    }
+
+    fn grades(&self) -> Vec<Grade> {
+        listed_grades(&UIAA_GRADES)
+    }
+
+    fn for_routes(&self) -> bool {
+        true
+    }
+}
+
+// The built-in systems, boulder scales first
+fn builtin_systems() -> [&'static dyn GradeSystem; 5] {
+    [&VScale, &FontScale, &YdsScale, &FrenchScale, &UiaaScale]
+}
+
+// CUSTOM SCALES
+
+// A gym's colour-circuit scale for boulders, owned by one account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomScale {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>, // None until saved
+    #[serde(default)] // Set from the caller's session
+    pub account_id: ObjectId,
+    pub name: String,
+    pub circuits: Vec<Circuit>,
+}
+
+// One circuit, covering a range of V grades (e.g. yellow = V0 to V2)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Circuit {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub colour: Option<String>, // "#rrggbb"
+    pub lowest: String,  // V grade
+    pub highest: String, // V grade
+}
+
+impl Circuit {
+    // (lowest, highest) on the difficulty index
+    fn range(&self) -> Option<(Difficulty, Difficulty)> {
+        Some((VScale.difficulty(&self.lowest)?, VScale.difficulty(&self.highest)?))
+    }
+}
+
+impl CustomScale {
+    // Checks the scale can be saved: a name, at least one circuit, unique circuit names and valid ranges
+    pub fn validate(&self) -> HookedResult<()> {
+        let invalid = |message: String| Err(HookedError::Validation(message));
+
+        if self.name.trim().is_empty() {
+            return invalid("A grade scale needs a name".to_string());
+        }
+        if self.circuits.is_empty() {
+            return invalid("A grade scale needs at least one circuit".to_string());
+        }
+
+        for (index, circuit) in self.circuits.iter().enumerate() {
+            if circuit.name.trim().is_empty() {
+                return invalid("Every circuit needs a name".to_string());
+            }
+            if self.circuits[..index].iter().any(|other| other.name.trim().eq_ignore_ascii_case(circuit.name.trim())) {
+                return invalid(format!("Circuit '{}' is listed twice", circuit.name));
+            }
+            if let Some(colour) = &circuit.colour {
+                let hex = colour.strip_prefix('#').unwrap_or_default();
+                if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return invalid(format!("Colour of circuit '{}' must look like #rrggbb", circuit.name));
+                }
+            }
+            match circuit.range() {
+                Some((lowest, highest)) if lowest <= highest => {}
+                Some(_) => return invalid(format!("Circuit '{}' has its lowest grade above its highest", circuit.name)),
+                None => return invalid(format!("Circuit '{}' needs V-scale grades as its range", circuit.name)),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl GradeSystem for CustomScale {
+    fn id(&self) -> String {
+        self._id.map(|id| id.to_hex()).unwrap_or_default()
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    // A circuit's difficulty is the middle of its range
+    fn grades(&self) -> Vec<Grade> {
+        let mut grades: Vec<Grade> = self.circuits.iter().filter_map(|circuit| {
+            let (lowest, highest) = circuit.range()?;
+            Some(Grade {
+                name: circuit.name.trim().to_string(),
+                difficulty: (lowest + highest) / 2,
+                colour: circuit.colour.clone(),
+            })
+        }).collect();
+        grades.sort_by_key(|grade| grade.difficulty);
+        grades
+    }
+
+    // Prefers a circuit whose range covers the difficulty over the nearest middle
+    fn grade_at(&self, difficulty: Difficulty) -> Option<Grade> {
+        let covering = self.circuits.iter()
+            .filter(|circuit| circuit.range().is_some_and(|(lowest, highest)| (lowest..=highest).contains(&difficulty)))
+            .min_by_key(|circuit| circuit.range().map(|(lowest, highest)| highest - lowest));
+
+        match covering {
+            Some(circuit) => self.find(&circuit.name),
+            None => self.grades().into_iter().min_by_key(|grade| ((grade.difficulty - difficulty).abs(), grade.difficulty)),
+        }
+    }
+}
+
+// REGISTRY
+
+// A grade checked against its system
+#[derive(Debug, Clone)]
+pub struct ResolvedGrade {
+    pub system: String,
+    pub grade: String, // Spelled as the system does
+    pub difficulty: Difficulty,
+}
+
+// Summary of a system sent to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct GradeSystemInfo {
+    pub id: String,
+    pub name: String,
+    pub custom: bool,
+    pub routes: bool, // Grades routes rather than boulders
+    pub grades: Vec<Grade>,
+}
+
+// The built-in systems plus one account's custom scales
+pub struct GradeSystems {
+    custom: Vec<CustomScale>,
+}
+
+impl GradeSystems {
+    pub fn new(custom: Vec<CustomScale>) -> Self {
+        GradeSystems { custom }
+    }
+
+    pub fn all(&self) -> Vec<&dyn GradeSystem> {
+        let mut systems: Vec<&dyn GradeSystem> = builtin_systems().to_vec();
+        systems.extend(self.custom.iter().map(|scale| scale as &dyn GradeSystem));
+        systems
+    }
+
+    pub fn get(&self, id: &str) -> HookedResult<&dyn GradeSystem> {
+        self.all().into_iter()
+            .find(|system| system.id() == id)
+            .ok_or_else(|| HookedError::NotFound(format!("Unknown grade system '{}'", id)))
+    }
+
+    pub fn info(&self) -> Vec<GradeSystemInfo> {
+        self.all().into_iter().map(|system| GradeSystemInfo {
+            id: system.id(),
+            name: system.name(),
+            custom: builtin_systems().iter().all(|builtin| builtin.id() != system.id()),
+            routes: system.for_routes(),
+            grades: system.grades(),
+        }).collect()
+    }
+
+    // Checks a grade against `system`, or against the built-in scales for the discipline when no system is given
+    pub fn resolve(&self, grade: &str, system: Option<&str>, discipline: Discipline) -> HookedResult<ResolvedGrade> {
+        let candidates: Vec<&dyn GradeSystem> = match system {
+            Some(id) => {
+                let system = self.get(id)?;
+                if !system.fits(discipline) {
+                    return Err(HookedError::Validation(format!(
+                        "The {} scale doesn't grade {} projects", system.name(), discipline.as_str().replace('_', "-"),
+                    )));
+                }
+                vec![system]
+            }
+            None => builtin_systems().into_iter().filter(|system| system.fits(discipline)).collect(),
+        };
+
+        candidates.into_iter()
+            .find_map(|system| system.find(grade).map(|found| ResolvedGrade {
+                system: system.id(),
+                grade: found.name,
+                difficulty: found.difficulty,
+            }))
+            .ok_or_else(|| HookedError::Validation(format!("Unknown grade '{}'", grade.trim())))
+    }
+
+    // Validates a project's grade, and stores its system and difficulty on the project.
+    // `stored` is the saved version when the project is updated. A grade no built-in scale knows that the stored
+    // project already has (free text saved before grades were checked, such as "V5+") is kept as it is when no system
+    // is given, with no system or difficulty, so those projects stay editable. New grades always have to be known.
+    pub fn apply(&self, project: &mut Project, stored: Option<&Project>) -> HookedResult<()> {
+        match self.resolve(&project.grade, project.grade_system.as_deref(), project.discipline) {
+            Ok(resolved) => {
+                project.grade = resolved.grade;
+                project.grade_system = Some(resolved.system);
+                project.difficulty = Some(resolved.difficulty);
+            }
+            Err(HookedError::Validation(_)) if project.grade_system.is_none() && stored.is_some_and(|stored| stored.grade == project.grade) => {
+                project.difficulty = None;
+            }
+            Err(e) => return Err(e),
+        }
+        Ok(())
+    }
+
+    // The closest grade of `to` to a grade of `from`. Both have to be boulder scales, or both route scales.
+    pub fn convert(&self, grade: &str, from: &str, to: &str) -> HookedResult<String> {
+        let (from, to) = (self.get(from)?, self.get(to)?);
+        if from.for_routes() != to.for_routes() {
+            return Err(HookedError::Validation("Boulder and route grades can't be converted into each other".to_string()));
+        }
+
+        let discipline = if from.for_routes() { Discipline::Sport } else { Discipline::Boulder };
+        let difficulty = self.resolve(grade, Some(&from.id()), discipline)?.difficulty;
+        to.grade_at(difficulty)
+            .map(|grade| grade.name)
+            .ok_or_else(|| HookedError::Validation(format!("Grade system '{}' has no grades", to.name())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database_helper::tests::project;
+
+    fn circuit(name: &str, lowest: &str, highest: &str) -> Circuit {
+        Circuit { name: name.to_string(), colour: None, lowest: lowest.to_string(), highest: highest.to_string() }
+    }
+
+    fn circuits() -> CustomScale {
+        CustomScale {
+            _id: Some(ObjectId::new()),
+            account_id: ObjectId::new(),
+            name: "Gym".to_string(),
+            circuits: vec![circuit("Yellow", "V0", "V2"), circuit("Blue", "V3", "V5"), circuit("Red", "V6", "V8")],
+        }
+    }
+
+    #[test]
+    fn converts_between_v_and_font() {
+        let systems = GradeSystems::new(Vec::new());
+        assert_eq!(systems.convert("V4", V_SCALE_ID, FONT_SCALE_ID).unwrap(), "6B/6B+");
+        assert_eq!(systems.convert("v0", V_SCALE_ID, FONT_SCALE_ID).unwrap(), "4");
+        assert_eq!(systems.convert("V17", V_SCALE_ID, FONT_SCALE_ID).unwrap(), "9A");
+        assert_eq!(systems.convert("6b/6b+", FONT_SCALE_ID, V_SCALE_ID).unwrap(), "V4");
+    }
+
+    #[test]
+    fn converts_between_french_and_yds_at_the_ends_of_the_range() {
+        let systems = GradeSystems::new(Vec::new());
+        assert_eq!(systems.convert("3", FRENCH_ID, YDS_ID).unwrap(), "5.4");
+        assert_eq!(systems.convert("9c+", FRENCH_ID, YDS_ID).unwrap(), "5.15d");
+        assert_eq!(systems.convert("5.4", YDS_ID, FRENCH_ID).unwrap(), "4a");
+        assert_eq!(systems.convert("5.15d", YDS_ID, FRENCH_ID).unwrap(), "9c");
+        // 5.10d sits halfway between 6b and 6b+, halfway rounds down
+        assert_eq!(systems.convert("5.10d", YDS_ID, FRENCH_ID).unwrap(), "6b");
+    }
+
+    #[test]
+    fn refuses_to_convert_boulder_grades_to_route_grades() {
+        let systems = GradeSystems::new(Vec::new());
+        assert!(matches!(systems.convert("V4", V_SCALE_ID, FRENCH_ID), Err(HookedError::Validation(_))));
+        assert!(matches!(systems.convert("V4", V_SCALE_ID, "nope"), Err(HookedError::NotFound(_))));
+    }
+
+    #[test]
+    fn converts_to_and_from_a_custom_circuit() {
+        let scale = circuits();
+        let id = scale.id();
+        let systems = GradeSystems::new(vec![scale]);
+
+        assert_eq!(systems.convert("V3", V_SCALE_ID, &id).unwrap(), "Blue");
+        assert_eq!(systems.convert("V5", V_SCALE_ID, &id).unwrap(), "Blue");
+        assert_eq!(systems.convert("blue", &id, V_SCALE_ID).unwrap(), "V4");
+        // Above every circuit: the nearest one
+        assert_eq!(systems.convert("V17", V_SCALE_ID, &id).unwrap(), "Red");
+        assert_eq!(systems.convert("6B/6B+", FONT_SCALE_ID, &id).unwrap(), "Blue");
+    }
+
+    #[test]
+    fn applies_the_grade_system_and_difficulty() {
+        let systems = GradeSystems::new(Vec::new());
+        let mut graded = Project { grade: "v5".to_string(), ..project() };
+        systems.apply(&mut graded, None).unwrap();
+        assert_eq!(graded.grade, "V5");
+        assert_eq!(graded.grade_system.as_deref(), Some(V_SCALE_ID));
+        assert_eq!(graded.difficulty, Some(50));
+    }
+
+    #[test]
+    fn keeps_stored_free_text_grades_without_a_system() {
+        let systems = GradeSystems::new(Vec::new());
+        for grade in ["V5+", "Unknown"] {
+            let stored = Project { grade: grade.to_string(), ..project() };
+            let mut legacy = Project { grade: grade.to_string(), difficulty: Some(50), ..project() };
+            systems.apply(&mut legacy, Some(&stored)).unwrap();
+            assert_eq!(legacy.grade, grade);
+            assert_eq!(legacy.grade_system, None);
+            assert_eq!(legacy.difficulty, None);
+        }
+    }
+
+    #[test]
+    fn rejects_new_free_text_grades() {
+        let systems = GradeSystems::new(Vec::new());
+        // On insert
+        let mut inserted = Project { grade: "V5+".to_string(), ..project() };
+        assert!(matches!(systems.apply(&mut inserted, None), Err(HookedError::Validation(_))));
+        // Changed on update, even from another free text grade
+        let stored = Project { grade: "V5+".to_string(), ..project() };
+        let mut changed = Project { grade: "V6+".to_string(), ..project() };
+        assert!(matches!(systems.apply(&mut changed, Some(&stored)), Err(HookedError::Validation(_))));
+    }
+
+    #[test]
+    fn rejects_unknown_grades_of_a_given_system() {
+        let systems = GradeSystems::new(Vec::new());
+        let mut project = Project { grade: "V5+".to_string(), grade_system: Some(V_SCALE_ID.to_string()), ..project() };
+        let stored = project.clone();
+        assert!(matches!(systems.apply(&mut project, Some(&stored)), Err(HookedError::Validation(_))));
+    }
+}
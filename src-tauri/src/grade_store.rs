@@ -0,0 +1,83 @@
+// src-tauri/src/grade_store.rs
+
+// Storage abstraction for the custom (gym colour-circuit) grade scales of each account.
+
+
+// IMPORTS
+use async_trait::async_trait;
+use std::sync::Arc;
+
+// MongoDB & BSON
+use mongodb::{Client, Collection, bson::{doc, oid::ObjectId}, options::ReplaceOptions};
+use futures_util::stream::TryStreamExt;
+
+use crate::grade::CustomScale;
+use crate::project_store::DATABASE_NAME;
+use crate::error::{HookedError, HookedResult};
+
+pub const GRADE_SCALES_COLLECTION: &str = "grade_scales";
+
+// The store shared through Tauri's state
+pub type SharedGradeScaleStore = Arc<dyn GradeScaleStore>;
+
+// STORE TRAIT
+#[async_trait]
+pub trait GradeScaleStore: Send + Sync {
+    // Every custom scale of an account
+    async fn list(&self, account_id: &ObjectId) -> HookedResult<Vec<CustomScale>>;
+    // Inserts the scale, or replaces it when it has an id. Returns the saved scale.
+    async fn save(&self, scale: CustomScale) -> HookedResult<CustomScale>;
+    // Deletes one scale, returning false if the account has no scale with this id
+    async fn delete(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<bool>;
+    // Deletes every scale of an account
+    async fn delete_for_account(&self, account_id: &ObjectId) -> HookedResult<u64>;
+}
+
+// MONGODB IMPLEMENTATION
+pub struct MongoGradeScaleStore {
+    collection: Collection<CustomScale>,
+}
+
+impl MongoGradeScaleStore {
+    pub fn new(client: &Client) -> Self {
+        let collection = client.database(DATABASE_NAME).collection::<CustomScale>(GRADE_SCALES_COLLECTION);
+        MongoGradeScaleStore { collection }
+    }
+}
+
+#[async_trait]
+impl GradeScaleStore for MongoGradeScaleStore {
+    async fn list(&self, account_id: &ObjectId) -> HookedResult<Vec<CustomScale>> {
+        let cursor = self.collection.find(doc! { "account_id": account_id }, None).await.map_err(HookedError::storage)?;
+        cursor.try_collect().await.map_err(HookedError::storage)
+    }
+
+    async fn save(&self, mut scale: CustomScale) -> HookedResult<CustomScale> {
+        match scale._id {
+            Some(id) => {
+                // Only replaces a scale the account owns
+                let filter = doc! { "_id": id, "account_id": scale.account_id };
+                let options = ReplaceOptions::builder().upsert(false).build();
+                let result = self.collection.replace_one(filter, &scale, options).await.map_err(HookedError::storage)?;
+                if result.matched_count == 0 {
+                    return Err(HookedError::NotFound("Grade scale not found".to_string()));
+                }
+            }
+            None => {
+                let result = self.collection.insert_one(&scale, None).await.map_err(HookedError::storage)?;
+                scale._id = result.inserted_id.as_object_id();
+            }
+        }
+        Ok(scale)
+    }
+
+    async fn delete(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<bool> {
+        let result = self.collection.delete_one(doc! { "_id": id, "account_id": account_id }, None).await.map_err(HookedError::storage)?;
+        Ok(result.deleted_count == 1)
+    }
+
+    async fn delete_for_account(&self, account_id: &ObjectId) -> HookedResult<u64> {
+        let result = self.collection.delete_many(doc! { "account_id": account_id }, None).await.map_err(HookedError::storage)?;
+        Ok(result.deleted_count)
+    }
+}
@@ -0,0 +1,414 @@
+// src-tauri/src/image_pipeline.rs
+
+// Prepares uploaded photos before they reach the image store. Phone cameras send 5-12 MB files with EXIF
+// data (GPS included), so every upload is checked by its magic bytes, decoded, turned upright, scaled down
+// to IMAGE_MAX_DIMENSION and re-encoded to IMAGE_FORMAT (webp or jpeg) at IMAGE_QUALITY. Re-encoding only
+// keeps the pixels, which drops every bit of metadata. A medium size and a thumbnail are made alongside,
+// project cards load those instead of the full image (see ImageVariant).
+
+
+// IMPORTS
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageReader, Limits};
+use serde::Serialize;
+
+use crate::database_helper::ImageVariant;
+use crate::error::{HookedError, HookedResult};
+
+// Longest side of stored images when IMAGE_MAX_DIMENSION isn't set
+const DEFAULT_MAX_DIMENSION: u32 = 2048;
+// Longest side of the medium variant when IMAGE_MEDIUM_SIZE isn't set
+const DEFAULT_MEDIUM_SIZE: u32 = 1024;
+// Longest side of thumbnails when IMAGE_THUMBNAIL_SIZE isn't set
+const DEFAULT_THUMBNAIL_SIZE: u32 = 320;
+// Encoder quality (1-100) when IMAGE_QUALITY isn't set
+const DEFAULT_QUALITY: u8 = 80;
+// Uploads above this are refused before decoding
+const MAX_UPLOAD_BYTES: usize = 25 * 1024 * 1024;
+// Decoding limits, so a small file can't claim a huge canvas
+const MAX_SOURCE_DIMENSION: u32 = 16_384;
+const MAX_DECODE_ALLOC: u64 = 512 * 1024 * 1024;
+
+// Formats accepted from the camera or the file picker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceFormat {
+    Jpeg,
+    Png,
+    Gif,
+    WebP,
+}
+
+impl SourceFormat {
+    // Recognises a format by its first bytes, whatever the file name says
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        match data {
+            [0xFF, 0xD8, 0xFF, ..] => Some(SourceFormat::Jpeg),
+            [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some(SourceFormat::Png),
+            [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some(SourceFormat::Gif),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some(SourceFormat::WebP),
+            _ => None,
+        }
+    }
+
+    fn image_format(self) -> image::ImageFormat {
+        match self {
+            SourceFormat::Jpeg => image::ImageFormat::Jpeg,
+            SourceFormat::Png => image::ImageFormat::Png,
+            SourceFormat::Gif => image::ImageFormat::Gif,
+            SourceFormat::WebP => image::ImageFormat::WebP,
+        }
+    }
+}
+
+// What uploads are re-encoded to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    WebP,
+    Jpeg,
+}
+
+impl OutputFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "webp" => Some(OutputFormat::WebP),
+            "jpeg" | "jpg" => Some(OutputFormat::Jpeg),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::WebP => "webp",
+            OutputFormat::Jpeg => "jpg",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            OutputFormat::WebP => "image/webp",
+            OutputFormat::Jpeg => "image/jpeg",
+        }
+    }
+}
+
+// Settings of the pipeline, read once at startup
+#[derive(Debug, Clone, Copy)]
+pub struct ImagePipeline {
+    pub max_dimension: u32,
+    pub medium_size: u32,
+    pub thumbnail_size: u32,
+    pub quality: u8,
+    pub format: OutputFormat,
+}
+
+impl Default for ImagePipeline {
+    fn default() -> Self {
+        ImagePipeline {
+            max_dimension: DEFAULT_MAX_DIMENSION,
+            medium_size: DEFAULT_MEDIUM_SIZE,
+            thumbnail_size: DEFAULT_THUMBNAIL_SIZE,
+            quality: DEFAULT_QUALITY,
+            format: OutputFormat::default(),
+        }
+    }
+}
+
+// One encoded image
+#[derive(Debug, Clone)]
+pub struct EncodedImage {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+// The result of processing an upload
+#[derive(Debug, Clone)]
+pub struct ProcessedImage {
+    pub image: EncodedImage,
+    pub medium: EncodedImage,
+    pub thumbnail: EncodedImage,
+    pub format: OutputFormat,
+    pub original_bytes: usize,
+}
+
+// Sizes reported back to the frontend after an upload
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ImageSizes {
+    pub original_bytes: usize,
+    pub bytes: usize,
+    pub width: u32,
+    pub height: u32,
+    pub medium_bytes: usize,
+    pub medium_width: u32,
+    pub medium_height: u32,
+    pub thumbnail_bytes: usize,
+    pub thumbnail_width: u32,
+    pub thumbnail_height: u32,
+}
+
+impl ProcessedImage {
+    pub fn sizes(&self) -> ImageSizes {
+        ImageSizes {
+            original_bytes: self.original_bytes,
+            bytes: self.image.data.len(),
+            width: self.image.width,
+            height: self.image.height,
+            medium_bytes: self.medium.data.len(),
+            medium_width: self.medium.width,
+            medium_height: self.medium.height,
+            thumbnail_bytes: self.thumbnail.data.len(),
+            thumbnail_width: self.thumbnail.width,
+            thumbnail_height: self.thumbnail.height,
+        }
+    }
+
+    // Every variant, the original first
+    pub fn into_variants(self) -> [(ImageVariant, EncodedImage); 3] {
+        [
+            (ImageVariant::Original, self.image),
+            (ImageVariant::Medium, self.medium),
+            (ImageVariant::Thumbnail, self.thumbnail),
+        ]
+    }
+}
+
+impl ImagePipeline {
+    // Reads the settings from the environment, keeping the default of any that is missing or invalid
+    pub fn from_env() -> Self {
+        let defaults = ImagePipeline::default();
+        let number = |name: &str| std::env::var(name).ok().and_then(|value| value.trim().parse::<u32>().ok()).filter(|value| *value > 0);
+
+        ImagePipeline {
+            max_dimension: number("IMAGE_MAX_DIMENSION").unwrap_or(defaults.max_dimension),
+            medium_size: number("IMAGE_MEDIUM_SIZE").unwrap_or(defaults.medium_size),
+            thumbnail_size: number("IMAGE_THUMBNAIL_SIZE").unwrap_or(defaults.thumbnail_size),
+            quality: number("IMAGE_QUALITY").map(|quality| quality.min(100) as u8).unwrap_or(defaults.quality),
+            format: std::env::var("IMAGE_FORMAT").ok().as_deref().and_then(OutputFormat::parse).unwrap_or(defaults.format),
+        }
+    }
+
+    // Validates, scales and re-encodes an upload. CPU heavy, call it from a blocking task.
+    pub fn process(&self, data: &[u8]) -> HookedResult<ProcessedImage> {
+        if data.len() > MAX_UPLOAD_BYTES {
+            return Err(HookedError::Validation(format!("Images can be at most {} MB", MAX_UPLOAD_BYTES / (1024 * 1024))));
+        }
+        let source = SourceFormat::detect(data)
+            .ok_or_else(|| HookedError::Validation("Unsupported image format, use JPEG, PNG, GIF or WebP".to_string()))?;
+
+        let image = fit(decode(data, source)?, self.max_dimension, FilterType::Lanczos3);
+        let medium = fit(image.clone(), self.medium_size, FilterType::Lanczos3);
+        let thumbnail = fit(medium.clone(), self.thumbnail_size, FilterType::Triangle);
+
+        Ok(ProcessedImage {
+            image: self.encode(&image)?,
+            medium: self.encode(&medium)?,
+            thumbnail: self.encode(&thumbnail)?,
+            format: self.format,
+            original_bytes: data.len(),
+        })
+    }
+
+    fn encode(&self, image: &DynamicImage) -> HookedResult<EncodedImage> {
+        let (width, height) = (image.width(), image.height());
+        let data = match self.format {
+            OutputFormat::WebP => {
+                let rgba = image.to_rgba8();
+                webp::Encoder::from_rgba(rgba.as_raw(), width, height).encode(f32::from(self.quality)).to_vec()
+            }
+            OutputFormat::Jpeg => {
+                // JPEG has no alpha channel
+                let mut data = Vec::new();
+                JpegEncoder::new_with_quality(&mut data, self.quality)
+                    .encode_image(&image.to_rgb8())
+                    .map_err(|e| HookedError::Internal(format!("Could not encode image: {}", e)))?;
+                data
+            }
+        };
+        Ok(EncodedImage { data, width, height })
+    }
+}
+
+// Scales an image down so its longest side is at most `size`, smaller images are kept as they are
+fn fit(image: DynamicImage, size: u32, filter: FilterType) -> DynamicImage {
+    if image.width() <= size && image.height() <= size {
+        return image;
+    }
+    image.resize(size, size, filter)
+}
+
+// Decodes with limits and applies the EXIF orientation, since the metadata holding it is dropped
+fn decode(data: &[u8], source: SourceFormat) -> HookedResult<DynamicImage> {
+    let invalid = |e: image::ImageError| HookedError::Validation(format!("Could not read the image: {}", e));
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+
+    let mut reader = ImageReader::with_format(Cursor::new(data), source.image_format());
+    reader.limits(limits);
+    let mut decoder = reader.into_decoder().map_err(invalid)?;
+    let orientation = decoder.orientation().map_err(invalid)?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(invalid)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+// File name of the processed image: the original name with the output format's extension
+pub fn output_file_name(original: &str, format: OutputFormat) -> String {
+    let stem = original.rsplit_once('.').map_or(original, |(stem, _)| stem);
+    format!("{}.{}", if stem.is_empty() { "image" } else { stem }, format.extension())
+}
+
+// Key of a variant of an image: its suffix before the extension, so variants can be found again from the original's key
+pub fn variant_key(key: &str, variant: ImageVariant) -> String {
+    let suffix = variant.key_suffix();
+    let (path, name) = key.rsplit_once('/').map_or(("", key), |(path, name)| (path, name));
+    let name = match name.rsplit_once('.') {
+        Some((stem, extension)) => format!("{}{}.{}", stem, suffix, extension),
+        None => format!("{}{}", name, suffix),
+    };
+    if path.is_empty() { name } else { format!("{}/{}", path, name) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, RgbImage};
+
+    // A gradient, so the encoders have something to compress
+    fn picture(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| image::Rgb([(x % 256) as u8, (y % 256) as u8, 128])))
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        picture(width, height).write_to(&mut Cursor::new(&mut data), ImageFormat::Png).unwrap();
+        data
+    }
+
+    // A JPEG with an EXIF segment (as phones write them) holding the orientation
+    fn jpeg_with_exif(width: u32, height: u32, orientation: u16) -> Vec<u8> {
+        let mut jpeg = Vec::new();
+        JpegEncoder::new_with_quality(&mut jpeg, 90).encode_image(&picture(width, height).to_rgb8()).unwrap();
+
+        let mut exif = b"Exif\0\0MM\0\x2A\0\0\0\x08\0\x01".to_vec();
+        exif.extend_from_slice(&[0x01, 0x12, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01]); // Orientation, one SHORT
+        exif.extend_from_slice(&orientation.to_be_bytes());
+        exif.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x00]); // Padding, no next IFD
+        let length = (exif.len() + 2) as u16;
+
+        let mut data = jpeg[..2].to_vec(); // SOI
+        data.extend_from_slice(&[0xFF, 0xE1]);
+        data.extend_from_slice(&length.to_be_bytes());
+        data.extend_from_slice(&exif);
+        data.extend_from_slice(&jpeg[2..]);
+        data
+    }
+
+    fn pipeline(format: OutputFormat) -> ImagePipeline {
+        ImagePipeline { max_dimension: 200, medium_size: 100, thumbnail_size: 40, quality: 80, format }
+    }
+
+    // Decodes an output again, returning its size and EXIF segment
+    fn reread(encoded: &EncodedImage, format: OutputFormat) -> ((u32, u32), Option<Vec<u8>>) {
+        let source = match format {
+            OutputFormat::WebP => SourceFormat::WebP,
+            OutputFormat::Jpeg => SourceFormat::Jpeg,
+        };
+        assert_eq!(SourceFormat::detect(&encoded.data), Some(source));
+        let mut decoder = ImageReader::with_format(Cursor::new(&encoded.data), source.image_format()).into_decoder().unwrap();
+        (decoder.dimensions(), decoder.exif_metadata().unwrap())
+    }
+
+    #[test]
+    fn detects_formats_by_magic_bytes() {
+        assert_eq!(SourceFormat::detect(&png(2, 2)), Some(SourceFormat::Png));
+        assert_eq!(SourceFormat::detect(&jpeg_with_exif(2, 2, 1)), Some(SourceFormat::Jpeg));
+        assert_eq!(SourceFormat::detect(b"GIF89a\x01\0"), Some(SourceFormat::Gif));
+        assert_eq!(SourceFormat::detect(b"RIFF\0\0\0\0WEBPVP8 "), Some(SourceFormat::WebP));
+        assert_eq!(SourceFormat::detect(b"RIFF\0\0\0\0WAVEfmt "), None);
+        assert_eq!(SourceFormat::detect(b"%PDF-1.7"), None);
+        assert_eq!(SourceFormat::detect(&[]), None);
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_images() {
+        let result = ImagePipeline::default().process(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>");
+        assert!(matches!(result, Err(HookedError::Validation(_))));
+
+        // The right header isn't enough
+        let mut truncated = png(8, 8);
+        truncated.truncate(40);
+        assert!(matches!(ImagePipeline::default().process(&truncated), Err(HookedError::Validation(_))));
+    }
+
+    #[test]
+    fn rejects_oversize_uploads() {
+        let mut huge = png(1, 1);
+        huge.resize(MAX_UPLOAD_BYTES + 1, 0);
+        assert!(matches!(ImagePipeline::default().process(&huge), Err(HookedError::Validation(_))));
+
+        // Small file, too large a canvas
+        let wide = png(MAX_SOURCE_DIMENSION + 1, 1);
+        assert!(matches!(ImagePipeline::default().process(&wide), Err(HookedError::Validation(_))));
+    }
+
+    #[test]
+    fn caps_the_longest_side_of_every_variant() {
+        for format in [OutputFormat::WebP, OutputFormat::Jpeg] {
+            let processed = pipeline(format).process(&png(500, 250)).unwrap();
+            assert_eq!(processed.format, format);
+
+            let sizes: Vec<_> = processed.into_variants().iter().map(|(_, encoded)| reread(encoded, format).0).collect();
+            assert_eq!(sizes, [(200, 100), (100, 50), (40, 20)]);
+        }
+    }
+
+    #[test]
+    fn never_scales_up() {
+        let processed = pipeline(OutputFormat::WebP).process(&png(30, 60)).unwrap();
+        let sizes: Vec<_> = processed.into_variants().iter().map(|(_, encoded)| (encoded.width, encoded.height)).collect();
+        assert_eq!(sizes, [(30, 60), (30, 60), (20, 40)]);
+    }
+
+    #[test]
+    fn drops_exif_and_applies_its_orientation() {
+        // 6: the camera was held upright, the pixels have to be turned a quarter clockwise
+        let upload = jpeg_with_exif(80, 40, 6);
+        let mut decoder = ImageReader::with_format(Cursor::new(&upload), ImageFormat::Jpeg).into_decoder().unwrap();
+        assert!(decoder.exif_metadata().unwrap().is_some());
+
+        for format in [OutputFormat::WebP, OutputFormat::Jpeg] {
+            for (_, encoded) in pipeline(format).process(&upload).unwrap().into_variants() {
+                let (size, exif) = reread(&encoded, format);
+                assert_eq!(size.0 * 2, size.1);
+                assert_eq!(exif, None);
+            }
+        }
+    }
+
+    #[test]
+    fn variant_key_suffixes_the_file_name() {
+        assert_eq!(variant_key("abc_boulder.webp", ImageVariant::Thumbnail), "abc_boulder_thumb.webp");
+        assert_eq!(variant_key("abc_boulder.webp", ImageVariant::Medium), "abc_boulder_medium.webp");
+        assert_eq!(variant_key("abc_boulder.webp", ImageVariant::Original), "abc_boulder.webp");
+    }
+
+    #[test]
+    fn variant_key_without_an_extension() {
+        assert_eq!(variant_key("abc_boulder", ImageVariant::Thumbnail), "abc_boulder_thumb");
+        // A dot in a folder isn't an extension
+        assert_eq!(variant_key("crag.v2/abc_boulder", ImageVariant::Medium), "crag.v2/abc_boulder_medium");
+    }
+
+    #[test]
+    fn variant_key_in_nested_folders() {
+        assert_eq!(variant_key("hooked/projects/abc.jpeg", ImageVariant::Thumbnail), "hooked/projects/abc_thumb.jpeg");
+        assert_eq!(variant_key("hooked/projects/abc.tar.jpeg", ImageVariant::Medium), "hooked/projects/abc.tar_medium.jpeg");
+    }
+}
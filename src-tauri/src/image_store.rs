@@ -0,0 +1,510 @@
+// src-tauri/src/image_store.rs
+
+// Where project images are kept. Commands only talk to an `ImageStore`, picked at startup:
+// HOOKED_IMAGE_STORE=cloudinary|local|s3 chooses explicitly. Without it Cloudinary is used when
+// CLOUDINARY_CLOUD_NAME is set, and the local app data folder otherwise, so offline installs need no account.
+// Cloudinary uploads are signed and tagged with their account and project (see ImageOwner).
+// Projects keep the image's URL in image_path and its key in image_public_id. Projects saved before
+// image_public_id have their key worked out from the URL, each store recognises its own URLs.
+
+
+// IMPORTS
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use mongodb::bson::oid::ObjectId;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::cloudinary::{self, CloudinaryUrl};
+use crate::error::{HookedError, HookedResult};
+
+// Folder inside the app data folder when HOOKED_IMAGE_DIR isn't set
+const LOCAL_IMAGE_DIR: &str = "images";
+// Cloudinary folder uploads go to when CLOUDINARY_FOLDER isn't set
+const DEFAULT_CLOUDINARY_FOLDER: &str = "hooked";
+
+// The store shared through Tauri's state
+pub type SharedImageStore = Arc<dyn ImageStore>;
+
+// A stored image, what upload_image returns
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredImage {
+    pub public_id: String, // Key it is stored under (Cloudinary's public_id, the file or object name elsewhere)
+    pub url: String, // URL the frontend loads it from
+}
+
+// Who an image belongs to, so stores that support it can trace images back to their owners
+#[derive(Debug, Clone, Copy)]
+pub struct ImageOwner {
+    pub account_id: ObjectId,
+    pub project_id: Option<ObjectId>, // None while the project is being created, see ImageStore::tag_project
+}
+
+impl ImageOwner {
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags = vec![account_tag(&self.account_id)];
+        tags.extend(self.project_id.as_ref().map(project_tag));
+        tags
+    }
+}
+
+pub fn account_tag(account_id: &ObjectId) -> String {
+    format!("account_{}", account_id.to_hex())
+}
+
+pub fn project_tag(project_id: &ObjectId) -> String {
+    format!("project_{}", project_id.to_hex())
+}
+
+// STORE TRAIT
+#[async_trait]
+pub trait ImageStore: Send + Sync {
+    // Stores an image. `key` is a suggestion, a store may pick its own (Cloudinary adds its folder).
+    async fn put(&self, key: &str, data: Vec<u8>, content_type: &str, owner: &ImageOwner) -> HookedResult<StoredImage>;
+    // Loads the bytes of a stored image
+    async fn get(&self, key: &str) -> HookedResult<Vec<u8>>;
+    // Deletes a stored image. Deleting an image that is already gone succeeds.
+    async fn delete(&self, key: &str) -> HookedResult<()>;
+    // URL the frontend loads the image from
+    fn url(&self, key: &str) -> String;
+    // Key of an image from its URL, None if the URL didn't come from this store
+    fn key_from_url(&self, url: &str) -> Option<String>;
+    // URL of an image scaled to `width` pixels on the fly, None for stores that can't (they serve the variants made at upload)
+    fn variant_url(&self, _key: &str, _width: u32) -> Option<String> {
+        None
+    }
+    // Links an image uploaded before its project existed to the project. Only Cloudinary keeps tags.
+    async fn tag_project(&self, _key: &str, _project_id: &ObjectId) -> HookedResult<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageBackend {
+    Cloudinary,
+    Local,
+    S3,
+}
+
+impl ImageBackend {
+    // Reads the backend choice from the environment
+    pub fn from_env() -> Self {
+        match std::env::var("HOOKED_IMAGE_STORE").ok().as_deref().map(str::trim) {
+            Some("cloudinary") => ImageBackend::Cloudinary,
+            Some("local") => ImageBackend::Local,
+            Some("s3") => ImageBackend::S3,
+            Some(other) => {
+                eprintln!("Unknown HOOKED_IMAGE_STORE value '{}', storing images locally", other);
+                ImageBackend::Local
+            }
+            None if std::env::var("CLOUDINARY_CLOUD_NAME").is_ok() => ImageBackend::Cloudinary,
+            None => ImageBackend::Local,
+        }
+    }
+}
+
+// Builds the configured store, falling back to local storage when its settings are incomplete
+pub fn from_env() -> SharedImageStore {
+    let configured = match ImageBackend::from_env() {
+        ImageBackend::Cloudinary => CloudinaryImageStore::from_env().map(|store| Arc::new(store) as SharedImageStore),
+        ImageBackend::S3 => S3ImageStore::from_env().map(|store| Arc::new(store) as SharedImageStore),
+        ImageBackend::Local => Ok(Arc::new(LocalImageStore::new(local_image_dir())) as SharedImageStore),
+    };
+
+    configured.unwrap_or_else(|e| {
+        eprintln!("Image storage not configured ({}), storing images locally", e);
+        Arc::new(LocalImageStore::new(local_image_dir()))
+    })
+}
+
+// Reads a required setting
+fn required_env(name: &str) -> HookedResult<String> {
+    std::env::var(name).map_err(|_| HookedError::Internal(format!("{} is not set", name)))
+}
+
+// Key for a new image: a unique prefix and the file name without anything unsafe in a path or URL
+pub fn new_key(file_name: &str) -> String {
+    let safe: String = file_name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect();
+    format!("{}_{}", ObjectId::new().to_hex(), safe)
+}
+
+// CLOUDINARY
+
+// Cloudinary's upload response, only the fields we use
+#[derive(Deserialize)]
+struct CloudinaryUploadResponse {
+    secure_url: String, // Image URL.
+    public_id: String,
+}
+
+// Every request is signed with the API secret, there is no unsigned upload preset anyone could reuse
+pub struct CloudinaryImageStore {
+    client: reqwest::Client,
+    cloud_name: String,
+    api_key: String,
+    api_secret: String,
+    folder: String,
+    moderation: Option<String>, // e.g. "manual" or an add-on such as "aws_rek", see Cloudinary's moderation docs
+}
+
+impl CloudinaryImageStore {
+    pub fn from_env() -> HookedResult<Self> {
+        Ok(CloudinaryImageStore {
+            client: reqwest::Client::new(),
+            cloud_name: required_env("CLOUDINARY_CLOUD_NAME")?,
+            api_key: required_env("CLOUDINARY_API_KEY")?,
+            api_secret: required_env("CLOUDINARY_API_SECRET")?,
+            folder: std::env::var("CLOUDINARY_FOLDER")
+                .map(|folder| folder.trim_matches('/').to_string())
+                .unwrap_or_else(|_| DEFAULT_CLOUDINARY_FOLDER.to_string()),
+            moderation: std::env::var("CLOUDINARY_MODERATION").ok().filter(|value| !value.is_empty()),
+        })
+    }
+
+    // Adds the timestamp, API key and signature to the parameters of an API call
+    fn signed(&self, mut params: Vec<(&'static str, String)>) -> Vec<(&'static str, String)> {
+        params.push(("timestamp", Utc::now().timestamp().to_string()));
+        let signature = cloudinary::sign(&params, &self.api_secret);
+        params.push(("api_key", self.api_key.clone()));
+        params.push(("signature", signature));
+        params
+    }
+
+    // Cloudinary's error message from a failed response
+    async fn error_message(res: reqwest::Response) -> String {
+        let status = res.status();
+        let body = res.text().await.unwrap_or_else(|_| "Failed to read error body".to_string());
+        let message = serde_json::from_str::<Value>(&body).ok()
+            .and_then(|val| val["error"]["message"].as_str().map(str::to_string))
+            .unwrap_or(body);
+        format!("Status: {}: {}", status, message)
+    }
+}
+
+#[async_trait]
+impl ImageStore for CloudinaryImageStore {
+    async fn put(&self, key: &str, data: Vec<u8>, _content_type: &str, owner: &ImageOwner) -> HookedResult<StoredImage> {
+        // Cloudinary adds the format itself, the public_id is the key without it. The folder goes in the
+        // public_id only (`folder` would add it a second time on fixed-folder accounts), and asset_folder
+        // files the image in the same Media Library folder on dynamic-folder accounts.
+        let stem = key.rsplit_once('.').map_or(key, |(stem, _)| stem);
+        let public_id = if self.folder.is_empty() { stem.to_string() } else { format!("{}/{}", self.folder, stem) };
+        let mut params = vec![
+            ("public_id", public_id),
+            ("asset_folder", self.folder.clone()),
+            ("tags", owner.tags().join(",")),
+        ];
+        if let Some(moderation) = &self.moderation {
+            params.push(("moderation", moderation.clone()));
+        }
+
+        let part = reqwest::multipart::Part::bytes(data).file_name(key.to_string());
+        let form = self.signed(params).into_iter()
+            .filter(|(_, value)| !value.is_empty())
+            .fold(reqwest::multipart::Form::new().part("file", part), |form, (name, value)| form.text(name, value));
+
+        let res = self.client
+            .post(format!("https://api.cloudinary.com/v1_1/{}/upload", self.cloud_name))
+            .multipart(form)
+            .send()
+            .await
+            .map_err(|e| HookedError::ImageService(format!("Request failed: {}", e)))?;
+
+        if !res.status().is_success() {
+            return Err(HookedError::ImageService(format!("Cloudinary upload failed ({})", Self::error_message(res).await)));
+        }
+        let uploaded: CloudinaryUploadResponse = res.json().await
+            .map_err(|e| HookedError::ImageService(format!("Failed to parse JSON: {}", e)))?;
+        Ok(StoredImage { public_id: uploaded.public_id, url: uploaded.secure_url })
+    }
+
+    async fn get(&self, key: &str) -> HookedResult<Vec<u8>> {
+        let res = self.client.get(self.url(key)).send().await
+            .map_err(|e| HookedError::ImageService(format!("Request failed: {}", e)))?;
+        if !res.status().is_success() {
+            return Err(HookedError::NotFound(format!("Image {} not found on Cloudinary", key)));
+        }
+        let bytes = res.bytes().await.map_err(|e| HookedError::ImageService(e.to_string()))?;
+        Ok(bytes.to_vec())
+    }
+
+    async fn delete(&self, key: &str) -> HookedResult<()> {
+        let params = self.signed(vec![("public_id", key.to_string())]);
+
+        let res = self.client
+            .post(format!("https://api.cloudinary.com/v1_1/{}/image/destroy", self.cloud_name))
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| HookedError::ImageService(format!("Request failed: {}", e)))?;
+
+        if !res.status().is_success() {
+            return Err(HookedError::ImageService(format!("Failed to delete {} from Cloudinary ({})", key, Self::error_message(res).await)));
+        }
+        Ok(())
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("https://res.cloudinary.com/{}/image/upload/{}", self.cloud_name, key)
+    }
+
+    // A transformation URL: scaled down (never up) to the width, in the best format and quality for the browser
+    fn variant_url(&self, key: &str, width: u32) -> Option<String> {
+        Some(format!("https://res.cloudinary.com/{}/image/upload/c_limit,f_auto,q_auto,w_{}/{}", self.cloud_name, width, key))
+    }
+
+    fn key_from_url(&self, url: &str) -> Option<String> {
+        CloudinaryUrl::parse(url)
+            .filter(|parsed| parsed.cloud_name == self.cloud_name)
+            .map(|parsed| parsed.public_id)
+    }
+
+    async fn tag_project(&self, key: &str, project_id: &ObjectId) -> HookedResult<()> {
+        let params = self.signed(vec![
+            ("command", "add".to_string()),
+            ("public_ids", key.to_string()),
+            ("tag", project_tag(project_id)),
+        ]);
+
+        let res = self.client
+            .post(format!("https://api.cloudinary.com/v1_1/{}/image/tags", self.cloud_name))
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| HookedError::ImageService(format!("Request failed: {}", e)))?;
+
+        if !res.status().is_success() {
+            return Err(HookedError::ImageService(format!("Failed to tag {} on Cloudinary ({})", key, Self::error_message(res).await)));
+        }
+        Ok(())
+    }
+}
+
+// LOCAL FOLDER
+
+// HOOKED_IMAGE_DIR overrides the default folder next to the SQLite database
+pub fn local_image_dir() -> PathBuf {
+    if let Ok(path) = std::env::var("HOOKED_IMAGE_DIR") {
+        return PathBuf::from(path);
+    }
+
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("hooked")
+        .join(LOCAL_IMAGE_DIR)
+}
+
+// Images as files in one folder. URLs are the file paths, the frontend turns them into asset URLs.
+pub struct LocalImageStore {
+    dir: PathBuf,
+}
+
+impl LocalImageStore {
+    pub fn new(dir: PathBuf) -> Self {
+        LocalImageStore { dir }
+    }
+
+    // Path of a key, refusing keys that would leave the folder
+    fn path(&self, key: &str) -> HookedResult<PathBuf> {
+        if key.is_empty() || key.contains(['/', '\\']) || key.starts_with('.') {
+            return Err(HookedError::Validation(format!("Invalid image key '{}'", key)));
+        }
+        Ok(self.dir.join(key))
+    }
+}
+
+#[async_trait]
+impl ImageStore for LocalImageStore {
+    async fn put(&self, key: &str, data: Vec<u8>, _content_type: &str, _owner: &ImageOwner) -> HookedResult<StoredImage> {
+        let path = self.path(key)?;
+        tokio::fs::create_dir_all(&self.dir).await
+            .map_err(|e| HookedError::ImageService(format!("Could not create image folder: {}", e)))?;
+        tokio::fs::write(&path, data).await
+            .map_err(|e| HookedError::ImageService(format!("Could not save image: {}", e)))?;
+        Ok(StoredImage { public_id: key.to_string(), url: self.url(key) })
+    }
+
+    async fn get(&self, key: &str) -> HookedResult<Vec<u8>> {
+        tokio::fs::read(self.path(key)?).await.map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => HookedError::NotFound(format!("Image {} not found", key)),
+            _ => HookedError::ImageService(format!("Could not read image: {}", e)),
+        })
+    }
+
+    async fn delete(&self, key: &str) -> HookedResult<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(HookedError::ImageService(format!("Could not delete image: {}", e))),
+            _ => Ok(()),
+        }
+    }
+
+    fn url(&self, key: &str) -> String {
+        self.dir.join(key).to_string_lossy().into_owned()
+    }
+
+    fn key_from_url(&self, url: &str) -> Option<String> {
+        let path = PathBuf::from(url);
+        if path.parent()? != self.dir {
+            return None;
+        }
+        path.file_name().map(|name| name.to_string_lossy().into_owned())
+    }
+}
+
+// S3-COMPATIBLE (AWS S3, MinIO, ...)
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Objects in one bucket, addressed path-style (endpoint/bucket/key) so MinIO works without DNS setup.
+// Requests are signed with AWS Signature Version 4.
+pub struct S3ImageStore {
+    client: reqwest::Client,
+    endpoint: Url,
+    bucket: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+    public_url: String, // Base of image URLs, endpoint/bucket unless S3_PUBLIC_URL is set (e.g. a CDN)
+}
+
+impl S3ImageStore {
+    pub fn from_env() -> HookedResult<Self> {
+        let endpoint = required_env("S3_ENDPOINT")?;
+        let endpoint = Url::parse(&endpoint).map_err(|e| HookedError::Internal(format!("Invalid S3_ENDPOINT: {}", e)))?;
+        let bucket = required_env("S3_BUCKET")?;
+        let public_url = std::env::var("S3_PUBLIC_URL")
+            .unwrap_or_else(|_| format!("{}/{}", endpoint.as_str().trim_end_matches('/'), bucket));
+
+        Ok(S3ImageStore {
+            client: reqwest::Client::new(),
+            endpoint,
+            bucket,
+            region: std::env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            access_key_id: required_env("S3_ACCESS_KEY_ID")?,
+            secret_access_key: required_env("S3_SECRET_ACCESS_KEY")?,
+            public_url: public_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    // Path of an object, each segment percent-encoded the way SigV4 expects
+    fn object_path(&self, key: &str) -> String {
+        format!("/{}/{}", uri_encode(&self.bucket), key.split('/').map(uri_encode).collect::<Vec<_>>().join("/"))
+    }
+
+    // Sends a signed request for one object
+    async fn send(&self, method: reqwest::Method, key: &str, body: Vec<u8>, content_type: Option<&str>) -> HookedResult<reqwest::Response> {
+        let path = self.object_path(key);
+        let host = match self.endpoint.port() {
+            Some(port) => format!("{}:{}", self.endpoint.host_str().unwrap_or_default(), port),
+            None => self.endpoint.host_str().unwrap_or_default().to_string(),
+        };
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex_sha256(&body);
+
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\nhost;x-amz-content-sha256;x-amz-date\n{}",
+            method.as_str(), path, host, payload_hash, amz_date, payload_hash,
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", amz_date, scope, hex_sha256(canonical_request.as_bytes()));
+
+        let mut signing_key = hmac_sha256(format!("AWS4{}", self.secret_access_key).as_bytes(), date.as_bytes());
+        for part in [self.region.as_str(), "s3", "aws4_request"] {
+            signing_key = hmac_sha256(&signing_key, part.as_bytes());
+        }
+        let signature = to_hex(&hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+            self.access_key_id, scope, signature,
+        );
+
+        let url = format!("{}{}", self.endpoint.as_str().trim_end_matches('/'), path);
+        let mut request = self.client.request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("authorization", authorization)
+            .body(body);
+        if let Some(content_type) = content_type {
+            request = request.header("content-type", content_type);
+        }
+        request.send().await.map_err(|e| HookedError::ImageService(format!("Request failed: {}", e)))
+    }
+
+    async fn error_message(res: reqwest::Response) -> String {
+        let status = res.status();
+        let body = res.text().await.unwrap_or_else(|_| "Failed to read error body".to_string());
+        format!("Status: {}: {}", status, body)
+    }
+}
+
+#[async_trait]
+impl ImageStore for S3ImageStore {
+    async fn put(&self, key: &str, data: Vec<u8>, content_type: &str, _owner: &ImageOwner) -> HookedResult<StoredImage> {
+        let res = self.send(reqwest::Method::PUT, key, data, Some(content_type)).await?;
+        if !res.status().is_success() {
+            return Err(HookedError::ImageService(format!("S3 upload failed ({})", Self::error_message(res).await)));
+        }
+        Ok(StoredImage { public_id: key.to_string(), url: self.url(key) })
+    }
+
+    async fn get(&self, key: &str) -> HookedResult<Vec<u8>> {
+        let res = self.send(reqwest::Method::GET, key, Vec::new(), None).await?;
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(HookedError::NotFound(format!("Image {} not found", key)));
+        }
+        if !res.status().is_success() {
+            return Err(HookedError::ImageService(format!("S3 download failed ({})", Self::error_message(res).await)));
+        }
+        let bytes = res.bytes().await.map_err(|e| HookedError::ImageService(e.to_string()))?;
+        Ok(bytes.to_vec())
+    }
+
+    async fn delete(&self, key: &str) -> HookedResult<()> {
+        // S3 answers 204 whether or not the object existed
+        let res = self.send(reqwest::Method::DELETE, key, Vec::new(), None).await?;
+        if !res.status().is_success() {
+            return Err(HookedError::ImageService(format!("S3 delete failed ({})", Self::error_message(res).await)));
+        }
+        Ok(())
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("{}/{}", self.public_url, key)
+    }
+
+    fn key_from_url(&self, url: &str) -> Option<String> {
+        url.strip_prefix(&self.public_url)?.strip_prefix('/').filter(|key| !key.is_empty()).map(str::to_string)
+    }
+}
+
+// Percent-encodes everything but the unreserved characters (RFC 3986), as SigV4 requires
+fn uri_encode(segment: &str) -> String {
+    segment.bytes().map(|byte| match byte {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+        _ => format!("%{:02X}", byte),
+    }).collect()
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex_sha256(data: &[u8]) -> String {
+    to_hex(&Sha256::digest(data))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
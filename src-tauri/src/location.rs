@@ -0,0 +1,194 @@
+// src-tauri/src/location.rs
+
+// Gyms and crags (locations) and the walls inside them. Projects reference both through location_id and wall_id.
+// Each account keeps its own locations, stored in the `locations` and `walls` collections (see location_store.rs).
+// Walls can have a reset date and schedule, reset.rs archives their projects once a reset is due.
+
+
+// IMPORTS
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+use crate::error::{HookedError, HookedResult};
+
+// Steepest slab and overhang a wall angle can have, in degrees from vertical
+pub const MIN_WALL_ANGLE: i32 = -90;
+pub const MAX_WALL_ANGLE: i32 = 90;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+// A point on the map
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lng: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Environment {
+    #[default]
+    Indoor,
+    Outdoor,
+}
+
+impl Environment {
+    pub const ALL: [Environment; 2] = [Environment::Indoor, Environment::Outdoor];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Environment::Indoor => "indoor",
+            Environment::Outdoor => "outdoor",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|environment| environment.as_str() == value)
+    }
+}
+
+// A gym or a crag
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Location {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    #[serde(default)] // Set from the caller's session
+    pub account_id: ObjectId,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coordinates: Option<GeoPoint>,
+    #[serde(default)]
+    pub environment: Environment,
+}
+
+// A wall (or sector, at a crag) of a location
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Wall {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub _id: Option<ObjectId>,
+    #[serde(default)] // Set from the caller's session
+    pub account_id: ObjectId,
+    pub location_id: ObjectId,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub angle: Option<i32>, // Degrees from vertical: negative for slabs, positive for overhangs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reset_date: Option<i64>, // UNIX timestamp of the next reset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reset_interval_days: Option<u32>, // Resets repeat this often after reset_date. None for a one-off reset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_reset_at: Option<i64>, // Set when a reset was applied
+}
+
+impl Location {
+    pub fn validate(&self) -> HookedResult<()> {
+        validate_name(&self.name, "location")?;
+        if let Some(point) = self.coordinates {
+            if !(-90.0..=90.0).contains(&point.lat) || !(-180.0..=180.0).contains(&point.lng) {
+                return Err(HookedError::Validation("Coordinates are out of range".to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Wall {
+    pub fn validate(&self) -> HookedResult<()> {
+        validate_name(&self.name, "wall")?;
+        if self.angle.is_some_and(|angle| !(MIN_WALL_ANGLE..=MAX_WALL_ANGLE).contains(&angle)) {
+            return Err(HookedError::Validation(format!("The wall angle has to be between {} and {} degrees", MIN_WALL_ANGLE, MAX_WALL_ANGLE)));
+        }
+        if self.reset_interval_days == Some(0) {
+            return Err(HookedError::Validation("A reset schedule needs at least one day between resets".to_string()));
+        }
+        if self.reset_interval_days.is_some() && self.reset_date.is_none() {
+            return Err(HookedError::Validation("A reset schedule needs the date of its next reset".to_string()));
+        }
+        Ok(())
+    }
+
+    // Applies the reset due at `now`: records it in last_reset_at and moves reset_date to the next scheduled reset
+    // (None for a one-off). Resets missed while the app wasn't running collapse into the latest one.
+    // Returns when the applied reset happened, None if no reset is due.
+    pub fn advance_reset(&mut self, now: i64) -> Option<i64> {
+        let reset_date = self.reset_date.filter(|date| *date <= now)?;
+
+        let (reset_at, next) = match self.reset_interval_days {
+            Some(days) => {
+                let step = i64::from(days) * SECONDS_PER_DAY;
+                let missed = (now - reset_date) / step;
+                let reset_at = reset_date + missed * step;
+                (reset_at, Some(reset_at + step))
+            }
+            None => (reset_date, None),
+        };
+        self.last_reset_at = Some(reset_at);
+        self.reset_date = next;
+        Some(reset_at)
+    }
+}
+
+fn validate_name(name: &str, kind: &str) -> HookedResult<()> {
+    if name.trim().is_empty() {
+        return Err(HookedError::Validation(format!("The {} needs a name", kind)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = SECONDS_PER_DAY;
+
+    fn wall(reset_date: Option<i64>, reset_interval_days: Option<u32>) -> Wall {
+        Wall {
+            _id: Some(ObjectId::new()),
+            account_id: ObjectId::new(),
+            location_id: ObjectId::new(),
+            name: "Cave".to_string(),
+            angle: None,
+            reset_date,
+            reset_interval_days,
+            last_reset_at: None,
+        }
+    }
+
+    #[test]
+    fn nothing_is_due_before_the_reset_date() {
+        let mut wall = wall(Some(10 * DAY), Some(7));
+        assert_eq!(wall.advance_reset(10 * DAY - 1), None);
+        assert_eq!((wall.reset_date, wall.last_reset_at), (Some(10 * DAY), None));
+    }
+
+    #[test]
+    fn one_off_reset_clears_the_reset_date() {
+        let mut wall = wall(Some(10 * DAY), None);
+        assert_eq!(wall.advance_reset(12 * DAY), Some(10 * DAY));
+        assert_eq!((wall.reset_date, wall.last_reset_at), (None, Some(10 * DAY)));
+        assert_eq!(wall.advance_reset(20 * DAY), None);
+    }
+
+    #[test]
+    fn interval_reset_on_its_date_moves_one_step() {
+        let mut wall = wall(Some(10 * DAY), Some(7));
+        assert_eq!(wall.advance_reset(10 * DAY), Some(10 * DAY));
+        assert_eq!(wall.reset_date, Some(17 * DAY));
+    }
+
+    #[test]
+    fn missed_interval_resets_collapse_into_the_latest() {
+        // Three periods passed while the app was closed: resets on days 10, 17, 24 and 31 were due
+        let mut wall = wall(Some(10 * DAY), Some(7));
+        assert_eq!(wall.advance_reset(33 * DAY), Some(31 * DAY));
+        assert_eq!((wall.reset_date, wall.last_reset_at), (Some(38 * DAY), Some(31 * DAY)));
+        assert_eq!(wall.advance_reset(33 * DAY), None);
+    }
+
+    #[test]
+    fn rejects_a_schedule_without_a_date_or_interval() {
+        assert!(wall(None, Some(7)).validate().is_err());
+        assert!(wall(Some(DAY), Some(0)).validate().is_err());
+        assert!(wall(Some(DAY), Some(7)).validate().is_ok());
+    }
+}
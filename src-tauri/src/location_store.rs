@@ -0,0 +1,160 @@
+// src-tauri/src/location_store.rs
+
+// Storage abstraction for the locations and walls of each account (see location.rs).
+
+
+// IMPORTS
+use async_trait::async_trait;
+use std::sync::Arc;
+
+// MongoDB & BSON
+use mongodb::{Client, Collection, bson::{doc, oid::ObjectId}, options::{FindOptions, ReplaceOptions}};
+use futures_util::stream::TryStreamExt;
+
+use crate::location::{Location, Wall};
+use crate::project_store::DATABASE_NAME;
+use crate::error::{HookedError, HookedResult};
+
+pub const LOCATIONS_COLLECTION: &str = "locations";
+pub const WALLS_COLLECTION: &str = "walls";
+
+// The store shared through Tauri's state
+pub type SharedLocationStore = Arc<dyn LocationStore>;
+
+// STORE TRAIT
+#[async_trait]
+pub trait LocationStore: Send + Sync {
+    // Every location of an account, by name
+    async fn list_locations(&self, account_id: &ObjectId) -> HookedResult<Vec<Location>>;
+    async fn find_location(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<Location>>;
+    // Inserts the location, or replaces it when it has an id. Returns the saved location.
+    async fn save_location(&self, location: Location) -> HookedResult<Location>;
+    // Deletes a location with its walls, returning false if the account has no location with this id
+    async fn delete_location(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<bool>;
+
+    // The walls of an account, by name, only those of one location when it is given
+    async fn list_walls(&self, account_id: &ObjectId, location_id: Option<&ObjectId>) -> HookedResult<Vec<Wall>>;
+    async fn find_wall(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<Wall>>;
+    // Inserts the wall, or replaces it when it has an id. Returns the saved wall.
+    async fn save_wall(&self, wall: Wall) -> HookedResult<Wall>;
+    // Walls whose reset_date has passed, of one account or of every account when account_id is None
+    async fn due_walls(&self, account_id: Option<&ObjectId>, now: i64) -> HookedResult<Vec<Wall>>;
+    // Deletes one wall, returning false if the account has no wall with this id
+    async fn delete_wall(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<bool>;
+
+    // Deletes every location and wall of an account
+    async fn delete_for_account(&self, account_id: &ObjectId) -> HookedResult<u64>;
+}
+
+// MONGODB IMPLEMENTATION
+pub struct MongoLocationStore {
+    locations: Collection<Location>,
+    walls: Collection<Wall>,
+}
+
+impl MongoLocationStore {
+    pub fn new(client: &Client) -> Self {
+        let database = client.database(DATABASE_NAME);
+        MongoLocationStore {
+            locations: database.collection::<Location>(LOCATIONS_COLLECTION),
+            walls: database.collection::<Wall>(WALLS_COLLECTION),
+        }
+    }
+}
+
+#[async_trait]
+impl LocationStore for MongoLocationStore {
+    async fn list_locations(&self, account_id: &ObjectId) -> HookedResult<Vec<Location>> {
+        let options = FindOptions::builder().sort(doc! { "name": 1 }).build();
+        let cursor = self.locations.find(doc! { "account_id": account_id }, options).await.map_err(HookedError::storage)?;
+        cursor.try_collect().await.map_err(HookedError::storage)
+    }
+
+    async fn find_location(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<Location>> {
+        self.locations.find_one(doc! { "_id": id, "account_id": account_id }, None).await.map_err(HookedError::storage)
+    }
+
+    async fn save_location(&self, mut location: Location) -> HookedResult<Location> {
+        match location._id {
+            Some(id) => {
+                // Only replaces a location the account owns
+                let options = ReplaceOptions::builder().upsert(false).build();
+                let result = self.locations
+                    .replace_one(doc! { "_id": id, "account_id": location.account_id }, &location, options)
+                    .await
+                    .map_err(HookedError::storage)?;
+                if result.matched_count == 0 {
+                    return Err(HookedError::NotFound("Location not found".to_string()));
+                }
+            }
+            None => {
+                let result = self.locations.insert_one(&location, None).await.map_err(HookedError::storage)?;
+                location._id = result.inserted_id.as_object_id();
+            }
+        }
+        Ok(location)
+    }
+
+    async fn delete_location(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<bool> {
+        let result = self.locations.delete_one(doc! { "_id": id, "account_id": account_id }, None).await.map_err(HookedError::storage)?;
+        if result.deleted_count == 0 {
+            return Ok(false);
+        }
+        self.walls.delete_many(doc! { "location_id": id, "account_id": account_id }, None).await.map_err(HookedError::storage)?;
+        Ok(true)
+    }
+
+    async fn list_walls(&self, account_id: &ObjectId, location_id: Option<&ObjectId>) -> HookedResult<Vec<Wall>> {
+        let mut filter = doc! { "account_id": account_id };
+        if let Some(location_id) = location_id {
+            filter.insert("location_id", location_id);
+        }
+        let options = FindOptions::builder().sort(doc! { "name": 1 }).build();
+        let cursor = self.walls.find(filter, options).await.map_err(HookedError::storage)?;
+        cursor.try_collect().await.map_err(HookedError::storage)
+    }
+
+    async fn find_wall(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<Wall>> {
+        self.walls.find_one(doc! { "_id": id, "account_id": account_id }, None).await.map_err(HookedError::storage)
+    }
+
+    async fn save_wall(&self, mut wall: Wall) -> HookedResult<Wall> {
+        match wall._id {
+            Some(id) => {
+                let options = ReplaceOptions::builder().upsert(false).build();
+                let result = self.walls
+                    .replace_one(doc! { "_id": id, "account_id": wall.account_id }, &wall, options)
+                    .await
+                    .map_err(HookedError::storage)?;
+                if result.matched_count == 0 {
+                    return Err(HookedError::NotFound("Wall not found".to_string()));
+                }
+            }
+            None => {
+                let result = self.walls.insert_one(&wall, None).await.map_err(HookedError::storage)?;
+                wall._id = result.inserted_id.as_object_id();
+            }
+        }
+        Ok(wall)
+    }
+
+    async fn due_walls(&self, account_id: Option<&ObjectId>, now: i64) -> HookedResult<Vec<Wall>> {
+        let mut filter = doc! { "reset_date": { "$lte": now } };
+        if let Some(account_id) = account_id {
+            filter.insert("account_id", account_id);
+        }
+        let cursor = self.walls.find(filter, None).await.map_err(HookedError::storage)?;
+        cursor.try_collect().await.map_err(HookedError::storage)
+    }
+
+    async fn delete_wall(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<bool> {
+        let result = self.walls.delete_one(doc! { "_id": id, "account_id": account_id }, None).await.map_err(HookedError::storage)?;
+        Ok(result.deleted_count == 1)
+    }
+
+    async fn delete_for_account(&self, account_id: &ObjectId) -> HookedResult<u64> {
+        let walls = self.walls.delete_many(doc! { "account_id": account_id }, None).await.map_err(HookedError::storage)?;
+        let locations = self.locations.delete_many(doc! { "account_id": account_id }, None).await.map_err(HookedError::storage)?;
+        Ok(walls.deleted_count + locations.deleted_count)
+    }
+}
@@ -0,0 +1,116 @@
+// src-tauri/src/mailer.rs
+
+// Outgoing email (password resets, ...) behind a `Mailer` trait, so a real provider can be plugged in later.
+// No mailer delivers email yet, so there are only development ones, and release builds have none: a reset code written
+// to the requesting device would let anyone reset any account. Debug builds pick one with MAILER:
+// MAILER=log only prints who an email went to (bodies hold reset codes), MAILER=file (the default) writes each one to the outbox folder
+// (MAIL_OUTBOX_DIR, defaults to `hooked/outbox` in the local app data folder) so they can be opened while testing.
+
+
+// IMPORTS
+use async_trait::async_trait;
+#[cfg(debug_assertions)]
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[cfg(debug_assertions)]
+use chrono::Utc;
+
+// The mailer shared with DatabaseHelper
+pub type SharedMailer = Arc<dyn Mailer>;
+
+// One outgoing email
+#[derive(Debug, Clone)]
+#[cfg_attr(not(debug_assertions), allow(dead_code))] // Only the development mailers read it so far
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String, // Plain text
+}
+
+// MAILER TRAIT
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), String>;
+}
+
+// Picks the mailer from MAILER in debug builds, None when there is no mailer that can be used
+#[cfg(debug_assertions)]
+pub fn from_env() -> Option<SharedMailer> {
+    match std::env::var("MAILER").ok().as_deref().map(str::trim) {
+        Some("log") => Some(Arc::new(LogMailer)),
+        Some("file") | None => Some(Arc::new(FileMailer::new(outbox_dir()))),
+        Some(other) => {
+            eprintln!("Unknown MAILER value '{}', writing emails to the outbox folder", other);
+            Some(Arc::new(FileMailer::new(outbox_dir())))
+        }
+    }
+}
+
+// Release builds have no mailer until a real provider is added, password resets are refused
+#[cfg(not(debug_assertions))]
+pub fn from_env() -> Option<SharedMailer> {
+    eprintln!("No email provider is configured, password resets are disabled");
+    None
+}
+
+// MAIL_OUTBOX_DIR overrides the default location in the user's data folder
+#[cfg(debug_assertions)]
+fn outbox_dir() -> PathBuf {
+    if let Ok(path) = std::env::var("MAIL_OUTBOX_DIR") {
+        return PathBuf::from(path);
+    }
+
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("hooked")
+        .join("outbox")
+}
+
+// LOG IMPLEMENTATION
+// Prints the recipient and subject of every email to stdout. The body isn't printed, anyone reading the logs
+// could use the reset code in it.
+#[cfg(debug_assertions)]
+pub struct LogMailer;
+
+#[cfg(debug_assertions)]
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: Email) -> Result<(), String> {
+        println!("Email to {}, subject: {}", email.to, email.subject);
+        Ok(())
+    }
+}
+
+// FILE IMPLEMENTATION
+// Writes every email as a .eml file, which most mail clients can open
+#[cfg(debug_assertions)]
+pub struct FileMailer {
+    dir: PathBuf,
+}
+
+#[cfg(debug_assertions)]
+impl FileMailer {
+    pub fn new(dir: PathBuf) -> Self {
+        FileMailer { dir }
+    }
+}
+
+#[cfg(debug_assertions)]
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> Result<(), String> {
+        tokio::fs::create_dir_all(&self.dir).await.map_err(|e| format!("Could not create outbox folder: {}", e))?;
+
+        let now = Utc::now();
+        let path = self.dir.join(format!("{}.eml", now.format("%Y%m%d-%H%M%S%.f")));
+        let contents = format!(
+            "To: {}\r\nSubject: {}\r\nDate: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+            email.to, email.subject, now.to_rfc2822(), email.body,
+        );
+
+        tokio::fs::write(&path, contents).await.map_err(|e| format!("Could not write email: {}", e))?;
+        println!("Email to {} written to {}", email.to, path.display());
+        Ok(())
+    }
+}
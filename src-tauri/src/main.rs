@@ -6,63 +6,107 @@
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 // Import database_helper (assuming `database_helper.rs` is in the same directory)
 mod database_helper; 
-
-use database_helper::{DatabaseHelper, Project};
+mod project_store;
+mod project_query;
+mod project_stats;
+mod account_store;
+mod sqlite_store;
+mod storage;
+mod sync;
+mod session;
+mod session_store;
+mod mailer;
+mod error;
+mod grade;
+mod grade_store;
+mod attempt_store;
+mod climbing_session_store;
+mod location;
+mod location_store;
+mod reset;
+mod image_store;
+mod cloudinary;
+mod image_pipeline;
+mod upload_store;
+
+use database_helper::{Attempt, ClimbingSession, Coordinate, DatabaseHelper, Discipline, ImageUpload, ImageVariant, Project, SessionTokens, StoredVariant};
+use session::Session;
+use error::{HookedError, HookedResult};
+use project_store::{GroupField, ProjectFilter, SharedProjectStore};
+use project_query::{PageCursor, ProjectPage, ProjectQuery};
+use project_stats::{SendPeriod, SendsSummary, SessionSummary, TagSummary};
+use grade::{CustomScale, GradeSystemInfo, GradeSystems};
+use grade_store::SharedGradeScaleStore;
+use attempt_store::SharedAttemptStore;
+use climbing_session_store::{already_running, SharedClimbingSessionStore};
+use location::{Location, Wall};
+use location_store::SharedLocationStore;
+use image_store::{ImageOwner, SharedImageStore, StoredImage};
+use image_pipeline::{ImagePipeline, ImageSizes};
+use upload_store::SharedUploadStore;
 use tauri::{Manager, State}; // Manager: Provides app management features like accessing state. State: Allows sharing state (like database connections) between Tauri commands.
-use mongodb::{Client as MongoClient, options::ClientOptions}; // MongoClient: The main MongoDB client for database interactions. ClientOptions: For configuring MongoDB connection options.
-use mongodb::bson::{self, Document, oid::ObjectId}; // bson: MongoDB’s binary JSON format. doc: Macro for creating BSON documents. 
-use bson::{Bson, doc};
-use futures_util::stream::TryStreamExt; // Provides asynchronous streaming methods.
+use mongodb::bson::oid::ObjectId; // ObjectId: MongoDB's unique document id, also used as the id on SQLite.
 use std::sync::Arc; // Enables thread-safe reference counting.
 use tokio::sync::Mutex; // Allows safe sharing and mutation of data in async code.
 use serde::{Serialize, Deserialize}; // Used for converting Rust structs to/from JSON.
-use serde_json::Value; // Represents arbitrary JSON data.
-use std::collections::HashMap;
-use sha1::{Sha1, Digest}; // Imports the SHA-1 hashing algorithm for generating Cloudinary signatures.
-
-#[derive(Serialize, Deserialize)]
-// Struct to deserialize Cloudinary's upload response.
-struct CloudinaryUploadResponse {
-    secure_url: String, // Image URL.
-}
 
 // Mark the main function as asynchronous using the tokio runtime.
 #[tokio::main] // Enables the async entry point for the app.
 async fn main() {
-    // Set up MongoDB client asynchronously.
-    // MongoDB connection string.
     dotenvy::dotenv().ok(); // Load .env file
-    let mongo_uri = std::env::var("MONGODB_URI").expect("MONGODB_URI must be set");
 
-    // Parse MongoDB connection options asynchronously.
-    let mut client_options = ClientOptions::parse(mongo_uri).await.unwrap();
-    // Sets the MongoDB API version to V1 for stability.
-    let server_api = mongodb::options::ServerApi::builder().version(mongodb::options::ServerApiVersion::V1).build();
-    client_options.server_api = Some(server_api);
+    // Load the secret that signs login tokens (JWT_SECRET or the OS keyring, see session.rs)
+    session::init().unwrap_or_else(|e| {
+        eprintln!("Error initializing sessions: {}", e);
+        std::process::exit(1);
+    });
+
+    // Connect the storage backend (MongoDB or local SQLite, see storage.rs)
+    let storage = storage::connect().await.unwrap_or_else(|e| {
+        eprintln!("Error initializing storage: {}", e);
+        std::process::exit(1); // Exit if no backend at all could be opened
+    });
+
+    // Local data is pushed to / pulled from MongoDB in the background whenever it is reachable
+    if let Some(local) = storage.local.clone() {
+        if std::env::var("MONGODB_URI").is_ok() {
+            sync::spawn(local);
+        }
+    }
 
-    // Initialize MongoDB client
-    let client = MongoClient::with_options(client_options).unwrap();
-    //client_options.connect_timeout = Some(std::time::Duration::from_secs(10)); // Adjust timeout
+    // Every project command goes through the ProjectStore trait.
+    let project_store: SharedProjectStore = storage.projects;
+    let grade_scale_store: SharedGradeScaleStore = storage.grade_scales;
+    let attempt_store: SharedAttemptStore = storage.attempts;
+    let climbing_session_store: SharedClimbingSessionStore = storage.climbing_sessions;
+    let location_store: SharedLocationStore = storage.locations;
+    let upload_store: SharedUploadStore = storage.uploads;
+    // Project images go to Cloudinary, S3/MinIO or the app data folder (see image_store.rs)
+    let image_store: SharedImageStore = image_store::from_env();
+    let image_pipeline = ImagePipeline::from_env();
 
-    // Ping MongoDB to verify connection
-    client.database("admin").run_command(doc! {"ping": 1}, None).await.unwrap();
-    println!("Successfully connected to MongoDB!");
+    // Projects on walls the gym reset are archived in the background (see reset.rs)
+    reset::spawn(project_store.clone(), location_store.clone());
 
-    // Initialize database helper asynchronously (wraps it in a thread-safe Arc<Mutex>, and exits on failure.)
-    let db_helper = Arc::new(Mutex::new(DatabaseHelper::new().await.unwrap_or_else(|e| {
-        eprintln!("Error initializing database: {}", e);
-        std::process::exit(1); // Exit if database initialization fails
-    })));
+    // Initialize database helper (wraps it in a thread-safe Arc<Mutex>)
+    let db_helper = Arc::new(Mutex::new(DatabaseHelper::new(storage.accounts, storage.sessions, mailer::from_env())));
 
     // Initialize the Tauri app builder
     tauri::Builder::default()
         // Add the logging and shell plugins for debugging and executing shell commands.
         .plugin(tauri_plugin_log::Builder::new().build())
         .plugin(tauri_plugin_shell::init())
-        // Add the MongoDB client and DatabaseHelper to Tauri’s state.
+        // Add the project store and DatabaseHelper to Tauri’s state.
         .setup(move |app| {
-            // Add MongoDB client and database helper to app's state
-            app.manage(client);
+            // Add project store and database helper to app's state
+            app.manage(project_store);
+            app.manage(grade_scale_store);
+            app.manage(attempt_store);
+            app.manage(climbing_session_store);
+            app.manage(location_store);
+            app.manage(image_store);
+            app.manage(upload_store);
+            app.manage(image_pipeline);
             app.manage(db_helper.clone()); // Pass the Arc<Mutex<DatabaseHelper>> to the app
 
             Ok(())
@@ -70,7 +114,7 @@ async fn main() {
         // Register the Tauri commands that the frontend can invoke.
         .invoke_handler(tauri::generate_handler![
             insert_project,
-            get_all_projects,
+            query_projects,
             update_project,
             save_annotations,
             delete_project,
@@ -78,14 +122,34 @@ async fn main() {
             get_sends_summary,
             get_styles_summary,
             get_holds_summary,
-            get_active_projects,
-            get_inactive_projects,
-            get_active_filtered_projects,
-            get_inactive_filtered_projects,
             upload_image,
+            get_image,
+            get_image_variant,
             get_project_by_id,
+            log_attempt,
+            list_attempts,
+            start_session,
+            end_session,
+            list_sessions,
+            list_locations,
+            save_location,
+            delete_location,
+            list_walls,
+            save_wall,
+            delete_wall,
+            list_grade_systems,
+            save_grade_scale,
+            delete_grade_scale,
+            convert_grade,
             create_account,
             login,
+            refresh_session,
+            logout,
+            logout_all_devices,
+            request_password_reset,
+            confirm_password_reset,
+            change_password,
+            delete_account,
         ])
         // Start the Tauri application.
         .run(tauri::generate_context!())
@@ -94,298 +158,273 @@ async fn main() {
 
 // Inserts a new Project document into the projects collection.
 #[tauri::command] // Marks the function as a Tauri command, allowing the frontend (e.g., SvelteKit) to invoke the function asynchronously.
-// client: State<'_, MongoClient>: State: This is Tauri's way of sharing state across different commands.MongoClient: The MongoDB client instance, which provides access to the database. '_': A lifetime specifier. This indicates that the MongoClient reference is tied to the application's state lifetime. 
-async fn insert_project(client: State<'_, MongoClient>, mut project: Project, account_id: String) -> Result<(), String> {
-    let collection = client.database("hooked_db").collection("projects");
-
+// store: State<'_, SharedProjectStore>: State is Tauri's way of sharing state across different commands. SharedProjectStore is whichever storage backend was set up in main. '_': A lifetime specifier tying the reference to the application's state lifetime.
+// session: The caller, resolved from the `token` argument (the JWT returned by login) before the command runs. See session.rs.
+// scales: The caller's custom grade scales, the grade has to belong to one of them or to a built-in scale.
+// locations: The caller's locations and walls, location_id and wall_id have to point to one of them.
+async fn insert_project(
+    store: State<'_, SharedProjectStore>,
+    scales: State<'_, SharedGradeScaleStore>,
+    locations: State<'_, SharedLocationStore>,
+    images: State<'_, SharedImageStore>,
+    uploads: State<'_, SharedUploadStore>,
+    mut project: Project,
+    session: Session,
+) -> HookedResult<()> {
     project.normalize();
-
-    // parse account_id to ObjectId
-    let object_id = ObjectId::parse_str(&account_id)
-        .map_err(|e| format!("Invalid account_id: {}", e))?;
-    project.account_id = object_id; // set foreign key
-
-    // Convert project to BSON document
-    let doc = match bson::to_document(&project) {
-        Ok(doc) => doc,
-        Err(e) => return Err(format!("Serialization error: {}", e)),
-    };
-
-    // Insert into MongoDB
-    match collection.insert_one(doc, None).await {
-        Ok(_) => Ok(()),
-        Err(e) => Err(e.to_string()),
+    project.validate_send_style()?;
+    grade_systems(&scales, &session.account_id).await?.apply(&mut project, None)?;
+    check_location(&locations, &session.account_id, &mut project).await?;
+    check_image(&uploads, &session.account_id, &project, None).await?;
+
+    // The project always belongs to the authenticated account
+    project.account_id = session.account_id; // set foreign key
+
+    let image_public_id = project.image_public_id.clone();
+    let project_id = store.create(project).await?;
+
+    // The image was uploaded before the project had an id
+    if let Some(key) = image_public_id {
+        if let Err(e) = images.tag_project(&key, &project_id).await {
+            // The project is saved, a missing tag isn't worth failing for
+            eprintln!("Error tagging image {}: {}", key, e);
+        }
     }
+    Ok(())
 }
 
-// Fetches all project documents from the database and converts them to Project objects.
+// Fetches one page of the caller's projects.
 #[tauri::command]
-// Returns a vector (list) of Project objects.
-async fn get_all_projects(client: State<'_, MongoClient>, account_id: String) -> Result<Vec<Project>, String> {
-    let collection = client.database("hooked_db").collection::<Document>("projects");
-
-    // Ensure users only see their projects
-    let filter = doc! {
-        "account_id": bson::oid::ObjectId::parse_str(&account_id)
-            .map_err(|e| format!("Invalid account_id: {}", e))?,
-        "is_active": 1
+// query: Filters (active, sent, discipline, location and wall, grades, styles, holds, date and attempt ranges), sort keys and the cursor of the previous page.
+async fn query_projects(store: State<'_, SharedProjectStore>, session: Session, query: Option<ProjectQuery>) -> HookedResult<ProjectPage> {
+    let query = query.unwrap_or_default();
+    let filter = query.filter(session.account_id);
+    let sort = query.sort_keys();
+    let page_size = query.page_size();
+
+    let after = query.cursor.as_deref().map(|cursor| PageCursor::decode(cursor, &sort)).transpose()?;
+
+    // One extra project tells us whether there is a next page
+    let mut projects = store.query_page(&filter, &sort, after.as_ref(), page_size + 1).await?;
+    let has_more = projects.len() as i64 > page_size;
+    projects.truncate(page_size as usize);
+
+    let next_cursor = match projects.last() {
+        Some(last) if has_more => PageCursor::after(last, &sort).map(|cursor| cursor.encode()).transpose()?,
+        _ => None,
     };
+    let total = store.count(&filter).await?;
 
-    // Performs a find query with no filter (None means "match everything"). The second None is for additional options (e.g., sorting or projection).
-    let mut cursor = collection.find(filter, None).await.map_err(|e| e.to_string())?; // The ? operator propagates the error, exiting the function early if an error occurs.
-    // Initialize an empty Vec (vector) to hold the list of Project objects.
-    let mut projects = Vec::new();
+    Ok(ProjectPage { projects, total, next_cursor })
+}
 
-    // while let Some(doc): Loops through the cursor while there are documents available. try_next(): from the futures crate and returns a Result<Option<Document>>.
-    while let Some(doc) = cursor.try_next().await.map_err(|e| e.to_string())? {
-        let project: Project = bson::from_document(doc).map_err(|e| e.to_string())?;
-        // Add the converted Project struct to the projects vector.
-        projects.push(project);
-    }
-    // Send the list of all projects to the frontend.
-    Ok(projects)
+// The built-in grade systems plus the caller's custom scales
+async fn grade_systems(scales: &SharedGradeScaleStore, account_id: &ObjectId) -> HookedResult<GradeSystems> {
+    Ok(GradeSystems::new(scales.list(account_id).await?))
 }
 
-// Fetches all project where is_active is 1.
+// Lists every grade system the caller can grade projects with, built-in scales first.
 #[tauri::command]
-async fn get_active_projects(client: State<'_, MongoClient>, account_id: String) -> Result<Vec<Project>, String> {
-    let collection = client.database("hooked_db").collection::<Document>("projects");
-
-    // Retrieve only active projects (where is_active is set to 1). doc!: This macro from the mongodb crate helps create BSON documents in a concise way. {"is_active": 1}: This is the MongoDB filter.
-    let filter = doc! {
-        "account_id": bson::oid::ObjectId::parse_str(&account_id)
-            .map_err(|e| format!("Invalid account_id: {}", e))?,
-        "is_active": 1
-    };
-    
-    // Queries the database for all documents matching the filter.
-    let mut cursor = collection.find(filter, None).await.map_err(|e| e.to_string())?;
-    let mut projects = Vec::new();
-
-    while let Some(doc) = cursor.try_next().await.map_err(|e| e.to_string())? {
-        let project: Project = bson::from_document(doc).map_err(|e| e.to_string())?;
-        projects.push(project);
-    }
-    Ok(projects)
+async fn list_grade_systems(scales: State<'_, SharedGradeScaleStore>, session: Session) -> HookedResult<Vec<GradeSystemInfo>> {
+    Ok(grade_systems(&scales, &session.account_id).await?.info())
 }
 
-// Fetches all project where is_active is 0.
+// Creates a custom colour-circuit scale, or replaces one when `scale._id` is set. Returns the saved scale.
 #[tauri::command]
-async fn get_inactive_projects(client: State<'_, MongoClient>, account_id: String) -> Result<Vec<Project>, String> {
-    let collection = client.database("hooked_db").collection::<Document>("projects");
-
-    // Retrieve only active projects (where is_active is set to 0).
-    let filter = doc! {
-        "account_id": bson::oid::ObjectId::parse_str(&account_id)
-            .map_err(|e| format!("Invalid account_id: {}", e))?,
-        "is_active": 0
-    };
-
-    let mut cursor = collection.find(filter, None).await.map_err(|e| e.to_string())?;
-    let mut projects = Vec::new();
+async fn save_grade_scale(scales: State<'_, SharedGradeScaleStore>, session: Session, mut scale: CustomScale) -> HookedResult<CustomScale> {
+    scale.validate()?;
+    scale.account_id = session.account_id;
+    scales.save(scale).await
+}
 
-    while let Some(doc) = cursor.try_next().await.map_err(|e| e.to_string())? {
-        let project: Project = bson::from_document(doc).map_err(|e| e.to_string())?;
-        projects.push(project);
+// Deletes a custom scale. Fails while projects are graded with it.
+#[tauri::command]
+async fn delete_grade_scale(store: State<'_, SharedProjectStore>, scales: State<'_, SharedGradeScaleStore>, session: Session, id: String) -> HookedResult<()> {
+    let object_id = parse_object_id(&id)?;
+    let filter = ProjectFilter { grade_system: Some(object_id.to_hex()), ..ProjectFilter::for_account(session.account_id) };
+    let used_by = store.count(&filter).await?;
+    if used_by > 0 {
+        return Err(HookedError::Validation(format!("The grade scale still grades {} project(s)", used_by)));
+    }
+    if !scales.delete(&session.account_id, &object_id).await? {
+        return Err(HookedError::NotFound("Grade scale not found".to_string()));
     }
-    Ok(projects)
+    Ok(())
 }
 
-// Fetches all projects where is_active is 1 with optional filtering
+// Converts a grade to the closest grade of another system (e.g. "V4" from "v_scale" to "font" is "6B/6B+").
 #[tauri::command]
-async fn get_active_filtered_projects(
-    client: State<'_, MongoClient>,
-    account_id: String,
-    grades: Option<Vec<String>>,
-    sent_status: Option<String>,
-    styles: Option<Vec<String>>,
-    holds: Option<Vec<String>>,
-) -> Result<Vec<Project>, String> {
-    let collection = client.database("hooked_db").collection::<Document>("projects");
-
-    // Base filter: Only return active projects
-    let mut filter = doc! {
-        "account_id": bson::oid::ObjectId::parse_str(&account_id)
-            .map_err(|e| format!("Invalid account_id: {}", e))?,
-        "is_active": 1
-    };
+async fn convert_grade(scales: State<'_, SharedGradeScaleStore>, session: Session, grade: String, from: String, to: String) -> HookedResult<String> {
+    grade_systems(&scales, &session.account_id).await?.convert(&grade, &from, &to)
+}
 
-    // Add grade filter if provided
-    if let Some(grades_list) = grades {
-        if !grades_list.is_empty() {
-            filter.insert("grade", doc! { "$in": grades_list }); // Use $in to match multiple grades
+// Checks a project's location and wall belong to the caller, and that the wall is at the location.
+// A wall without a location sets the location to the wall's.
+async fn check_location(locations: &SharedLocationStore, account_id: &ObjectId, project: &mut Project) -> HookedResult<()> {
+    if let Some(wall_id) = project.wall_id {
+        let wall = locations.find_wall(account_id, &wall_id).await?.ok_or_else(|| HookedError::NotFound("Wall not found".to_string()))?;
+        match project.location_id {
+            Some(location_id) if location_id != wall.location_id => {
+                return Err(HookedError::Validation("The wall isn't at the project's location".to_string()));
+            }
+            _ => project.location_id = Some(wall.location_id),
         }
     }
-
-    if let Some(styles_list) = styles {
-        if !styles_list.is_empty() {
-            filter.insert("style", doc! { "$in": styles_list });
-        }
+    if let Some(location_id) = project.location_id {
+        locations.find_location(account_id, &location_id).await?.ok_or_else(|| HookedError::NotFound("Location not found".to_string()))?;
     }
+    Ok(())
+}
 
-    if let Some(holds_list) = holds {
-        if !holds_list.is_empty() {
-            filter.insert("hold", doc! { "$in": holds_list });
-        }
+// Checks a project's image was uploaded by the caller (see upload_store.rs): its key, its variants and its URL.
+// An image the stored project already has is kept as it is, images from before uploads were recorded have no record.
+async fn check_image(uploads: &SharedUploadStore, account_id: &ObjectId, project: &Project, existing: Option<&Project>) -> HookedResult<()> {
+    let unchanged = existing.is_some_and(|existing| existing.image_path == project.image_path
+        && existing.image_public_id == project.image_public_id
+        && existing.image_variants == project.image_variants);
+    if unchanged || (project.image_path.is_empty() && project.image_public_id.is_none() && project.image_variants.is_empty()) {
+        return Ok(());
     }
 
-    // Add sent_status filter if provided
-    if let Some(s) = sent_status {
-        if s == "true" || s == "false" {
-            let sent_value = if s == "true" { 1 } else { 0 };
-            filter.insert("is_sent", Bson::Int32(sent_value));
-        }
-        // If s is empty or anything else, skip filtering on is_sent
+    let mut keys: Vec<String> = project.image_variants.iter().map(|variant| variant.public_id.clone()).collect();
+    keys.extend(project.image_public_id.clone());
+    let uploaded = uploads.find(account_id, &keys).await?;
+
+    let variants_uploaded = project.image_variants.iter()
+        .all(|variant| uploaded.iter().any(|upload| upload.public_id == variant.public_id && upload.url == variant.url));
+    let key_uploaded = project.image_public_id.as_ref()
+        .is_none_or(|key| uploaded.iter().any(|upload| &upload.public_id == key));
+    let path_uploaded = project.image_path.is_empty() || uploaded.iter().any(|upload| upload.url == project.image_path);
+    if !(variants_uploaded && key_uploaded && path_uploaded) {
+        return Err(HookedError::Validation("The image wasn't uploaded with this account".to_string()));
     }
+    Ok(())
+}
 
-    // Log the final MongoDB query for debugging
-    println!("MongoDB Query: {:?}", filter);
+// Lists the caller's gyms and crags by name.
+#[tauri::command]
+async fn list_locations(locations: State<'_, SharedLocationStore>, session: Session) -> HookedResult<Vec<Location>> {
+    locations.list_locations(&session.account_id).await
+}
 
-    // Execute the query
-    let mut cursor = collection.find(filter, None).await.map_err(|e| e.to_string())?;
-    let mut projects = Vec::new();
+// Creates a location, or replaces one when `location._id` is set. Returns the saved location.
+#[tauri::command]
+async fn save_location(locations: State<'_, SharedLocationStore>, session: Session, mut location: Location) -> HookedResult<Location> {
+    location.validate()?;
+    location.account_id = session.account_id;
+    locations.save_location(location).await
+}
 
-    while let Some(doc) = cursor.try_next().await.map_err(|e| e.to_string())? {
-        let project: Project = bson::from_document(doc).map_err(|e| e.to_string())?;
-        projects.push(project);
+// Deletes a location with its walls. Fails while projects are still at it.
+#[tauri::command]
+async fn delete_location(
+    store: State<'_, SharedProjectStore>,
+    locations: State<'_, SharedLocationStore>,
+    session: Session,
+    id: String,
+) -> HookedResult<()> {
+    let account_id = session.account_id;
+    let object_id = parse_object_id(&id)?;
+
+    let used_by = store.count(&ProjectFilter { location_id: Some(object_id), ..ProjectFilter::for_account(account_id) }).await?;
+    if used_by > 0 {
+        return Err(HookedError::Validation(format!("The location still has {} project(s)", used_by)));
     }
-
-    println!("Projects returned: {:?}", projects.len()); // Debugging
-    Ok(projects)
+    if !locations.delete_location(&account_id, &object_id).await? {
+        return Err(HookedError::NotFound("Location not found".to_string()));
+    }
+    Ok(())
 }
 
-// Fetches all projects where is_active is 0 with optional filtering
+// Lists the caller's walls by name, only those of one location when location_id is given.
 #[tauri::command]
-async fn get_inactive_filtered_projects(
-    client: State<'_, MongoClient>,
-    account_id: String,
-    grades: Option<Vec<String>>,
-    sent_status: Option<String>,
-    styles: Option<Vec<String>>,
-    holds: Option<Vec<String>>,
-) -> Result<Vec<Project>, String> {
-    let collection = client.database("hooked_db").collection::<Document>("projects");
-
-    // Base filter: Only return active projects
-    let mut filter = doc! {
-        "account_id": bson::oid::ObjectId::parse_str(&account_id)
-            .map_err(|e| format!("Invalid account_id: {}", e))?,
-        "is_active": 0
-    };
+async fn list_walls(locations: State<'_, SharedLocationStore>, session: Session, location_id: Option<String>) -> HookedResult<Vec<Wall>> {
+    let location_id = location_id.as_deref().map(parse_object_id).transpose()?;
+    locations.list_walls(&session.account_id, location_id.as_ref()).await
+}
 
-    // Add grade filter if provided
-    if let Some(grades_list) = grades {
-        if !grades_list.is_empty() {
-            filter.insert("grade", doc! { "$in": grades_list }); // Use $in to match multiple grades
+// Creates a wall at one of the caller's locations, or replaces one when `wall._id` is set. Returns the saved wall.
+// reset_date / reset_interval_days: When the setters reset the wall next and how often. A reset date in the past is applied right away.
+// A wall with projects can't move to another location.
+#[tauri::command]
+async fn save_wall(store: State<'_, SharedProjectStore>, locations: State<'_, SharedLocationStore>, session: Session, mut wall: Wall) -> HookedResult<Wall> {
+    wall.validate()?;
+    wall.account_id = session.account_id;
+    locations.find_location(&wall.account_id, &wall.location_id).await?
+        .ok_or_else(|| HookedError::NotFound("Location not found".to_string()))?;
+
+    // Projects on the wall would be left at the old location
+    if let Some(id) = wall._id {
+        let moved = locations.find_wall(&wall.account_id, &id).await?.is_some_and(|existing| existing.location_id != wall.location_id);
+        let used_by = if moved { store.count(&ProjectFilter { wall_id: Some(id), ..ProjectFilter::for_account(wall.account_id) }).await? } else { 0 };
+        if used_by > 0 {
+            return Err(HookedError::Validation(format!("The wall still has {} project(s), it can't move to another location", used_by)));
         }
     }
+    let mut saved = locations.save_wall(wall).await?;
 
-    if let Some(styles_list) = styles {
-        if !styles_list.is_empty() {
-            filter.insert("style", doc! { "$in": styles_list });
+    // Only the caller's walls, the background task in reset.rs handles every account
+    if saved.reset_date.is_some_and(|date| date <= chrono::Utc::now().timestamp()) {
+        reset::apply_due_resets(&store, &locations, Some(&session.account_id)).await?;
+        if let Some(id) = saved._id {
+            saved = locations.find_wall(&session.account_id, &id).await?.unwrap_or(saved);
         }
     }
+    Ok(saved)
+}
 
-    if let Some(holds_list) = holds {
-        if !holds_list.is_empty() {
-            filter.insert("hold", doc! { "$in": holds_list });
-        }
-    }
-    
-    // Add sent_status filter if provided
-    if let Some(s) = sent_status {
-        if s == "true" || s == "false" {
-            let sent_value = if s == "true" { 1 } else { 0 };
-            filter.insert("is_sent", Bson::Int32(sent_value));
-        }
-        // If s is empty or anything else, skip filtering on is_sent
+// Deletes a wall. Fails while projects are still on it.
+#[tauri::command]
+async fn delete_wall(
+    store: State<'_, SharedProjectStore>,
+    locations: State<'_, SharedLocationStore>,
+    session: Session,
+    id: String,
+) -> HookedResult<()> {
+    let account_id = session.account_id;
+    let object_id = parse_object_id(&id)?;
+
+    let used_by = store.count(&ProjectFilter { wall_id: Some(object_id), ..ProjectFilter::for_account(account_id) }).await?;
+    if used_by > 0 {
+        return Err(HookedError::Validation(format!("The wall still has {} project(s)", used_by)));
     }
-
-    // Log the final MongoDB query for debugging
-    println!("MongoDB Query: {:?}", filter);
-
-    // Execute the query
-    let mut cursor = collection.find(filter, None).await.map_err(|e| e.to_string())?;
-    let mut projects = Vec::new();
-
-    while let Some(doc) = cursor.try_next().await.map_err(|e| e.to_string())? {
-        let project: Project = bson::from_document(doc).map_err(|e| e.to_string())?;
-        projects.push(project);
+    if !locations.delete_wall(&account_id, &object_id).await? {
+        return Err(HookedError::NotFound("Wall not found".to_string()));
     }
+    Ok(())
+}
 
-    println!("Projects returned: {:?}", projects.len()); // Debugging
-    Ok(projects)
+// Parses an id sent by the frontend
+fn parse_object_id(id: &str) -> HookedResult<ObjectId> {
+    ObjectId::parse_str(id).map_err(|e| HookedError::Validation(format!("Invalid ObjectId: {}", e)))
 }
 
 // Updates a project by _id if it exists.
+// attempts, is_sent and sent_date come from the attempt log once the project has one, the values sent are ignored.
 #[tauri::command]
-async fn update_project(client: State<'_, MongoClient>, mut project: Project) -> Result<(), String> {
-    let collection = client.database("hooked_db").collection::<Document>("projects");
-
+async fn update_project(
+    store: State<'_, SharedProjectStore>,
+    scales: State<'_, SharedGradeScaleStore>,
+    attempts: State<'_, SharedAttemptStore>,
+    locations: State<'_, SharedLocationStore>,
+    uploads: State<'_, SharedUploadStore>,
+    mut project: Project,
+    session: Session,
+) -> HookedResult<()> {
+    let account_id = session.account_id;
+    let mut existing = None;
+    if let Some(project_id) = project._id {
+        project.apply_attempts(&attempts.list_for_project(&account_id, &project_id).await?);
+        existing = store.get(&account_id, &project_id).await?;
+    }
     project.normalize();
+    project.validate_send_style()?;
+    grade_systems(&scales, &account_id).await?.apply(&mut project, existing.as_ref())?;
+    check_location(&locations, &account_id, &mut project).await?;
+    check_image(&uploads, &account_id, &project, existing.as_ref()).await?;
 
-    // Log received project data
-    println!("Received project data: {:?}", project);
-
-    if let Some(_id) = project._id {
-        let filter = doc! {"_id": _id};
-
-        // Convert Rust struct into a BSON document
-        let mut update_doc = bson::to_document(&project).map_err(|e| e.to_string())?;
-
-        // Ensure `date_time` is a proper integer
-        if let Some(bson::Bson::Int64(date_time)) = update_doc.get("date_time") {
-            update_doc.insert("date_time", bson::Bson::Int64(*date_time));
-        } else {
-            return Err("Invalid date_time format".to_string());
-        }
-
-        // Ensure boolean fields (`is_active` and `is_sent`) are stored as `i32`
-        if let Some(bson::Bson::Boolean(is_active)) = update_doc.get("is_active") {
-            update_doc.insert("is_active", bson::Bson::Int32(if *is_active { 1 } else { 0 }));
-        }
-
-        if let Some(bson::Bson::Boolean(is_sent)) = update_doc.get("is_sent") {
-            update_doc.insert("is_sent", bson::Bson::Int32(if *is_sent { 1 } else { 0 }));
-        }
-
-        if let Some(account_id_str) = update_doc.get_str("account_id").ok() {
-        let object_id = ObjectId::parse_str(account_id_str)
-            .map_err(|e| format!("Invalid account_id: {}", e))?;
-        update_doc.insert("account_id", bson::Bson::ObjectId(object_id));
-}
-
-        // Handle `coordinates` only if valid array of objects
-        let valid_coordinates = match update_doc.get("coordinates") {
-            Some(bson::Bson::Array(array)) if !array.is_empty() => {
-                // Log and preserve array as-is
-                println!("Valid coordinates received: {:?}", array);
-                true
-            }
-            _ => false,
-        };
-
-        // If not valid, fetch existing coordinates and use them
-        if !valid_coordinates {
-            if let Ok(existing_doc) = collection.find_one(filter.clone(), None).await {
-                if let Some(existing_coordinates) = existing_doc
-                    .and_then(|doc| doc.get("coordinates").cloned())
-                {
-                    println!("Reusing existing coordinates: {:?}", existing_coordinates);
-                    update_doc.insert("coordinates", existing_coordinates);
-                }
-            }
-        }
-
-        let update = doc! {"$set": update_doc};
-
-        match collection.update_one(filter, update, None).await {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e.to_string()),
-        }
-    } else {
-        Err("Project ID is required for update".to_string())
-    }
+    // Only matches if the project belongs to the caller
+    store.update(&account_id, project).await
 }
 
 // Define the SaveAnnotationsRequest struct
@@ -404,389 +443,517 @@ pub struct Annotation {
 
 // Updates the annotations for a project by _id if it exists.
 #[tauri::command]
-async fn save_annotations(client: State<'_, MongoClient>, request: SaveAnnotationsRequest) -> Result<(), String> {
-    let collection = client.database("hooked_db").collection::<Document>("projects");
-
-    // Log received project annotations data
-    println!("Received project annotations: {:?}", request);
+async fn save_annotations(store: State<'_, SharedProjectStore>, request: SaveAnnotationsRequest, session: Session) -> HookedResult<()> {
+    let account_id = session.account_id;
 
     let project_id = &request.project_id;
     if !project_id.is_empty() {
-        let filter = doc! { "_id": bson::oid::ObjectId::parse_str(project_id).map_err(|e| e.to_string())? };
-
-        // Convert annotations to BSON based on their number type
-        let annotations: Vec<Bson> = request.annotations.iter().map(|annotation| {
-            Bson::Document(doc! {
-                "lat": annotation.lat,
-                "lng": annotation.lng,
-                "note": &annotation.note
-            })
+        let object_id = parse_object_id(project_id)?;
+
+        let coordinates: Vec<Coordinate> = request.annotations.into_iter().map(|annotation| Coordinate {
+            lat: annotation.lat,
+            lng: annotation.lng,
+            note: annotation.note,
         }).collect();
 
-        let update_doc = doc! {
-            "$set": {
-                "coordinates": annotations,
-            },
-        };
-
-        match collection.update_one(filter, update_doc, None).await {
-            Ok(_) => {
-                println!("Annotations for project {} saved successfully!", project_id);
-                Ok(())
-            },
-            Err(e) => {
-                println!("Failed to save annotations for project {}: {}", project_id, e);
-                Err(e.to_string())
-            }
-        }
+        store.update_coordinates(&account_id, &object_id, coordinates).await.map_err(|e| {
+            eprintln!("Failed to save annotations for project {}: {}", project_id, e);
+            e
+        })
     } else {
-        Err("Project ID is required".to_string())
+        Err(HookedError::Validation("Project ID is required".to_string()))
     }
 }
 
-// Deletes a project and associated Cloudinary image.
+// Deletes a project with its attempt log and associated image.
 #[tauri::command]
-async fn delete_project(client: State<'_, MongoClient>, _id: String) -> Result<(), String> {
-    let collection = client.database("hooked_db").collection::<Document>("projects");
-    // Parse the _id to ObjectId with 'bson::oid::ObjectId::parse_str(&_id)'.
-    let object_id = bson::oid::ObjectId::parse_str(&_id).map_err(|e| e.to_string())?;
-    let filter = doc! {"_id": object_id};
-
-    // Get the image_path before deleting from MongoDB
-    let project_result = collection.find_one(filter.clone(), None).await.map_err(|e| e.to_string())?; // .clone(): Creates a copy of the filter so we can reuse it later.
-    // Extract the image path
-    let image_path = match project_result { // match project_result: If a document is found (Some(doc)), attempt to extract the "image_path" field.
-        Some(doc) => {
-            match doc.get_str("image_path") {  // Retrieves the "image_path" as a string.
-                Ok(path) => Some(path.to_string()), // If successful, wrap it in Some(path.to_string()).
-                Err(_) => None, // If the field does not exist or cannot be read, return None.
-            }
-        },
-        None => None,
-    };
-
-    // Delete from MongoDB
-    collection.delete_one(filter, None).await.map_err(|e| e.to_string())?;
+async fn delete_project(
+    store: State<'_, SharedProjectStore>,
+    attempts: State<'_, SharedAttemptStore>,
+    images: State<'_, SharedImageStore>,
+    uploads: State<'_, SharedUploadStore>,
+    _id: String,
+    session: Session,
+) -> HookedResult<()> {
+    let account_id = session.account_id;
+
+    // Parse the _id to ObjectId with 'ObjectId::parse_str(&_id)'.
+    let object_id = parse_object_id(&_id)?;
+
+    // Delete from the store, which hands back the removed project so we still know its image_path
+    let deleted = store.delete(&account_id, &object_id).await?
+        .ok_or_else(|| HookedError::NotFound("Project not found".to_string()))?;
+
+    attempts.delete_for_project(&account_id, &object_id).await?;
+    delete_project_image(images.inner(), uploads.inner(), deleted).await;
+    Ok(())
+}
 
-    // Delete from Cloudinary (if image_path exists)
-    if let Some(path) = image_path { // if let Some(path): Checks if an image_path was found.
-        if let Err(e) = delete_from_cloudinary(path).await {
-            // Log the error but don't return it to avoid blocking the MongoDB delete
-            eprintln!("Error deleting from Cloudinary: {}", e); 
+// Deletes a removed project's image (if it had one) from the image store
+async fn delete_project_image(images: &SharedImageStore, uploads: &SharedUploadStore, project: Project) {
+    // Images kept elsewhere (another backend, an old URL) are left alone
+    let Some(parsed_key) = images.key_from_url(&project.image_path) else { return };
+    let mut keys: Vec<String> = project.image_variants.into_iter().map(|variant| variant.public_id).collect();
+    if keys.is_empty() {
+        // The key saved at upload, projects from before it was saved fall back to the one in the URL.
+        // Their variants are found from it, deleting one that was never made succeeds.
+        let key = project.image_public_id.unwrap_or(parsed_key);
+        keys = ImageVariant::ALL.into_iter().map(|variant| image_pipeline::variant_key(&key, variant)).collect();
+    }
+    for key in &keys {
+        if let Err(e) = images.delete(key).await {
+            // Log the error but don't return it to avoid blocking the project delete
+            eprintln!("Error deleting image {}: {}", key, e);
         }
     }
-
-    Ok(())
+    if let Err(e) = uploads.delete(&project.account_id, &keys).await {
+        eprintln!("Error forgetting the uploads of project {:?}: {}", project._id, e);
+    }
 }
 
-// Extracts the public_id and deletes the image using a signed request.
-async fn delete_from_cloudinary(image_url: String) -> Result<(), String> {
-    // Extract the public_id (necessary to identify and delete the image from Cloudinary) from the image URL.
-    let public_id = extract_cloudinary_public_id(&image_url)?;
-    let cloud_name = std::env::var("CLOUDINARY_CLOUD_NAME").expect("CLOUDINARY_CLOUD_NAME must be set");
-    let api_key = std::env::var("CLOUDINARY_API_KEY").expect("CLOUDINARY_API_KEY must be set");
-    let api_secret = std::env::var("CLOUDINARY_API_SECRET").expect("CLOUDINARY_API_SECRET must be set");
-
-
-    // Generate a secure signature using the current time.
-    let timestamp = chrono::Utc::now().timestamp().to_string(); // For signing the request
-    // Construct the signature base string required by Cloudinary’s API for request validation.
-    let signature_string = format!("public_id={}&timestamp={}{}", public_id, timestamp, api_secret);
-    
-    // Create a Sha1 hasher
-    let mut hasher = Sha1::new();
-    hasher.update(signature_string);
-    let result = hasher.finalize();
-    let signature = format!("{:x}", result); // Convert to hex string
-
-    // Prepare the request data.
-    let client = reqwest::Client::new(); // Creates a new HTTP client to send the POST request.
-    let mut params = HashMap::new(); // HashMap::new(): Creates a new map to store the form data.
-    // Add the required parameters.
-    params.insert("public_id", public_id);
-    params.insert("api_key", api_key.to_string());
-    params.insert("timestamp", timestamp);
-    params.insert("signature", signature);
-
-    // Send the request to delete the image from Cloudinary.
-    let res = client.post(format!("https://api.cloudinary.com/v1_1/{}/image/destroy", cloud_name))
-        .form(&params) 
-        .send()
-        .await
-        .map_err(|err| format!("Error sending request: {}", err))?;
-
-    // Handle the response
-    if res.status().is_success() {
-        println!("Successfully deleted image from Cloudinary: {}", image_url);
-        Ok(())
-    } else {
-        let error_body = res.text().await.unwrap_or("Failed to get error body".to_string());
-        let val: Value = serde_json::from_str(&error_body).map_err(|e| e.to_string())?;
-        let err_msg = val["error"]["message"].as_str().unwrap_or("Unknown Cloudinary Error").to_string();
-
-        Err(format!("Failed to delete image from Cloudinary: {}. Cloudinary response: {}", image_url, err_msg))
-    }
+// Returns how many projects the user has sent, optionally only at one grade or in one discipline.
+#[tauri::command]
+async fn get_sends_count(store: State<'_, SharedProjectStore>, session: Session, grade: Option<String>, discipline: Option<Discipline>) -> HookedResult<i64> {
+    let filter = ProjectFilter {
+        is_sent: Some(1),
+        discipline,
+        grades: grade.map(|grade| vec![grade]),
+        ..ProjectFilter::for_account(session.account_id)
+    };
+    store.count(&filter).await
 }
 
-// Parses the public ID from the Cloudinary URL.
-fn extract_cloudinary_public_id(image_url: &str) -> Result<String, String> {
-    // Split the URL into parts. image_url.split('/'): Splits the input URL by the / character, producing an iterator of substrings. .collect(): Collects the resulting substrings into a Vec<&str> (a vector of string slices). parts: A vector containing each section of the URL split by /.
-    let parts: Vec<&str> = image_url.split('/').collect();
-    // Validate the URL structure.
-    if parts.len() >= 8 { // Adjust as needed (if the URL structure changes, this index may need to be updated).
-        let public_id_with_ext = parts[7]; // Extract the public ID. public_id_with_ext: Holds the image's public ID with the file extension.
-        let public_id = public_id_with_ext.split('.').next().unwrap_or(public_id_with_ext); // Remove the file extension (extract up to .ext).
+// Returns the user's sends by grade (in scale order), by week, month or year (month by default), by discipline and by send style.
+// discipline: Only counts sends of this discipline. Grades of boulders and routes don't mix well in one pyramid.
+#[tauri::command]
+async fn get_sends_summary(store: State<'_, SharedProjectStore>, session: Session, period: Option<SendPeriod>, discipline: Option<Discipline>) -> HookedResult<SendsSummary> {
+    let filter = ProjectFilter {
+        is_sent: Some(1),
+        discipline,
+        ..ProjectFilter::for_account(session.account_id)
+    };
+    let sent = store.query(&filter).await?;
 
-        // Return the extracted public ID.
-        Ok(public_id.to_owned()) // .to_owned(): Converts the &str (borrowed reference) into a String (owned data).
-    } else {
-        Err("Invalid Cloudinary URL format".to_owned())
-    }
+    Ok(project_stats::summarize_sends(&sent, period.unwrap_or_default()))
 }
 
-// Returns the total number of documents in the projects collection.
+// Returns, per style, the sent and open project counts, send rate, average attempts-to-send and hardest grade sent.
 #[tauri::command]
-async fn get_sends_count(client: State<'_, MongoClient>) -> Result<i64, String> {
-    let collection = client.database("hooked_db").collection::<Document>("projects");
-    // Count all documents in the collection. 
-    let count = collection.count_documents(None, None).await.map_err(|e| e.to_string())?;
-    // Convert the count into an i64 (signed 64-bit integer).
-    Ok(count.try_into().map_err(|_| "Count exceeds i64 capacity".to_string())?)
+async fn get_styles_summary(store: State<'_, SharedProjectStore>, session: Session, discipline: Option<Discipline>) -> HookedResult<Vec<TagSummary>> {
+    let filter = ProjectFilter { discipline, ..ProjectFilter::for_account(session.account_id) };
+    let counts = store.tag_grade_counts(&filter, GroupField::Style).await?;
+    Ok(project_stats::summarize_tags(counts))
 }
 
-// Returns the total sends and sends count by grade.
+// Same as get_styles_summary, per hold type.
 #[tauri::command]
-async fn get_sends_summary(client: State<'_, MongoClient>, account_id: String) -> Result<(i64, Vec<(String, i64)>), String> {
-    let collection = client.database("hooked_db").collection::<Document>("projects");
-
-    // Log the number of matching projects before aggregation
-    let matching_count = collection.count_documents(doc! { "is_sent": 1 }, None).await.map_err(|e| e.to_string())?;
-    println!("Matching projects with is_sent = 1: {}", matching_count);
+async fn get_holds_summary(store: State<'_, SharedProjectStore>, session: Session, discipline: Option<Discipline>) -> HookedResult<Vec<TagSummary>> {
+    let filter = ProjectFilter { discipline, ..ProjectFilter::for_account(session.account_id) };
+    let counts = store.tag_grade_counts(&filter, GroupField::Holds).await?;
+    Ok(project_stats::summarize_tags(counts))
+}
 
-    let account_id = bson::oid::ObjectId::parse_str(&account_id)
-        .map_err(|e| format!("Invalid account_id: {}", e))?;
+// What upload_image returns. Save url, public_id and variants as the project's image_path, image_public_id and image_variants.
+#[derive(Serialize, Debug)]
+pub struct UploadedImage {
+    #[serde(flatten)]
+    pub image: StoredImage,
+    pub variants: Vec<StoredVariant>,
+    pub sizes: ImageSizes,
+}
 
-    // Aggregation pipeline: filter sent projects and group by grade.
-    let pipeline = vec![
-        doc! { "$match": { "account_id": account_id.clone(), "is_sent": 1 } },
-        doc! { "$group": { "_id": "$grade", "count": { "$sum": 1 } } }
-    ];
+// Processes an image (see image_pipeline.rs), stores it in every variant (thumbnail, medium, original) and reports the resulting sizes.
+// project_id: The project the image is for, when it already exists. New projects are linked to their image by insert_project.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)] // Every store is its own piece of Tauri state
+async fn upload_image(
+    images: State<'_, SharedImageStore>,
+    pipeline: State<'_, ImagePipeline>,
+    store: State<'_, SharedProjectStore>,
+    uploads: State<'_, SharedUploadStore>,
+    image_data: Vec<u8>, // A vector of bytes (Vec<u8>) representing the raw image data.
+    image_name: String,
+    project_id: Option<String>,
+    session: Session,
+) -> HookedResult<UploadedImage> {
+    if image_data.is_empty() {
+        return Err(HookedError::Validation("The image is empty".to_string()));
+    }
+    let project_id = project_id.as_deref().map(parse_object_id).transpose()?;
+    if let Some(project_id) = &project_id {
+        store.get(&session.account_id, project_id).await?
+            .ok_or_else(|| HookedError::NotFound("Project not found".to_string()))?;
+    }
 
-    // Execute the aggregation pipeline.
-    let mut cursor = collection.aggregate(pipeline, None).await.map_err(|e| e.to_string())?;
-    let mut grade_counts = Vec::new();
-    let mut total_count = 0;
+    let pipeline = *pipeline.inner();
+    let processed = tokio::task::spawn_blocking(move || pipeline.process(&image_data))
+        .await
+        .map_err(|e| HookedError::Internal(format!("Image processing failed: {}", e)))??;
+    let sizes = processed.sizes();
+    let content_type = processed.format.content_type();
+
+    let owner = ImageOwner { account_id: session.account_id, project_id };
+    let key = image_store::new_key(&image_pipeline::output_file_name(&image_name, processed.format));
+    let mut variants: Vec<StoredVariant> = Vec::new();
+    for (variant, encoded) in processed.into_variants() {
+        let (width, height) = (encoded.width, encoded.height);
+        match images.put(&image_pipeline::variant_key(&key, variant), encoded.data, content_type, &owner).await {
+            Ok(stored) => variants.push(StoredVariant { variant, url: stored.url, public_id: stored.public_id, width, height }),
+            Err(e) => {
+                // Don't leave part of the variants behind
+                discard_variants(images.inner(), &variants).await;
+                return Err(e);
+            }
+        }
+    }
 
-    // Iterate through the results.
-    while let Some(doc) = cursor.try_next().await.map_err(|e| e.to_string())? {
-        println!("Aggregation result: {:?}", doc);
-        
-        if let Some(grade) = doc.get_str("_id").ok() {
-            // Handle both Int32 and Int64
-            let count = match doc.get("count") {
-                Some(bson::Bson::Int32(n)) => *n as i64,
-                Some(bson::Bson::Int64(n)) => *n,
-                _ => 0,
-            };
-            
-            grade_counts.push((grade.to_string(), count));
-            total_count += count;
+    // Recorded so projects can only be saved with images their account uploaded
+    let uploaded_at = chrono::Utc::now().timestamp();
+    for stored in &variants {
+        let upload = ImageUpload { _id: None, account_id: session.account_id, public_id: stored.public_id.clone(), url: stored.url.clone(), uploaded_at };
+        if let Err(e) = uploads.insert(upload).await {
+            discard_variants(images.inner(), &variants).await;
+            return Err(e);
         }
     }
 
-    // Log the result before returning
-    println!("Returning sends summary: total_count = {}, grade_counts = {:?}", total_count, grade_counts);
+    // into_variants gives the original first
+    let image = StoredImage { public_id: variants[0].public_id.clone(), url: variants[0].url.clone() };
+    Ok(UploadedImage { image, variants, sizes })
+}
 
-    Ok((total_count, grade_counts))
+// Deletes the variants stored so far when an upload fails part way
+async fn discard_variants(images: &SharedImageStore, variants: &[StoredVariant]) {
+    for stored in variants {
+        if let Err(e) = images.delete(&stored.public_id).await {
+            eprintln!("Error deleting image {}: {}", stored.public_id, e);
+        }
+    }
 }
 
+// Returns the URL of the project's image best suited to show it `width` pixels wide, None if it has no image.
+// Cloudinary scales images on the fly to the exact width, other stores pick the closest variant made at upload.
 #[tauri::command]
-async fn get_styles_summary(client: State<'_, MongoClient>, account_id: String) -> Result<Vec<(String, i64, i64)>, String> {
-    let collection = client.database("hooked_db").collection::<Document>("projects");
-
-    let account_id = bson::oid::ObjectId::parse_str(&account_id)
-        .map_err(|e| format!("Invalid account_id: {}", e))?;
-
-    // Pipeline to group by style for done (is_sent = 1)
-    let done_pipeline = vec![
-        doc! { "$match": { "account_id": account_id.clone(), "is_sent": 1 }},
-        doc! { "$unwind": "$style" },  // break style array into multiple docs
-        doc! { "$group": { "_id": "$style", "count": { "$sum": 1 }}}
-    ];
-
-    let mut done_cursor = collection.aggregate(done_pipeline, None).await.map_err(|e| e.to_string())?;
-    let mut done_counts = std::collections::HashMap::new();
-    while let Some(doc) = done_cursor.try_next().await.map_err(|e| e.to_string())? {
-        if let Some(style) = doc.get_str("_id").ok() {
-            let count = match doc.get("count") {
-                Some(bson::Bson::Int32(n)) => *n as i64,
-                Some(bson::Bson::Int64(n)) => *n,
-                _ => 0,
-            };
-            done_counts.insert(style.to_string(), count);
-        }
+async fn get_image_variant(
+    store: State<'_, SharedProjectStore>,
+    images: State<'_, SharedImageStore>,
+    project_id: String,
+    width: u32,
+    session: Session,
+) -> HookedResult<Option<String>> {
+    if width == 0 {
+        return Err(HookedError::Validation("The width must be at least 1 pixel".to_string()));
     }
+    let project_id = parse_object_id(&project_id)?;
+    let project = store.get(&session.account_id, &project_id).await?
+        .ok_or_else(|| HookedError::NotFound("Project not found".to_string()))?;
 
-    // Pipeline to group by style for practicing (is_sent = 0)
-    let practicing_pipeline = vec![
-        doc! { "$match": { "account_id": account_id.clone(), "is_sent": 1 }},
-        doc! { "$unwind": "$style" },
-        doc! { "$group": { "_id": "$style", "count": { "$sum": 1 }}}
-    ];
-
-    let mut practicing_cursor = collection.aggregate(practicing_pipeline, None).await.map_err(|e| e.to_string())?;
-    let mut practicing_counts = std::collections::HashMap::new();
-    while let Some(doc) = practicing_cursor.try_next().await.map_err(|e| e.to_string())? {
-        if let Some(style) = doc.get_str("_id").ok() {
-            let count = match doc.get("count") {
-                Some(bson::Bson::Int32(n)) => *n as i64,
-                Some(bson::Bson::Int64(n)) => *n,
-                _ => 0,
-            };
-            practicing_counts.insert(style.to_string(), count);
+    if project.image_path.is_empty() {
+        return Ok(None);
+    }
+    if let Some(key) = images.key_from_url(&project.image_path) {
+        let key = project.image_public_id.clone().unwrap_or(key);
+        // Never scaled up past the stored original
+        let original_width = project.image_variants.iter().map(|variant| variant.width).max();
+        if let Some(url) = images.variant_url(&key, original_width.map_or(width, |original| width.min(original))) {
+            return Ok(Some(url));
         }
     }
 
-    // Build summary list
-    let mut summary = Vec::new();
-    for style in done_counts.keys().chain(practicing_counts.keys()) {
-        let done = *done_counts.get(style).unwrap_or(&0);
-        let practicing = *practicing_counts.get(style).unwrap_or(&0);
-        summary.push((style.clone(), done, practicing));
-    }
+    let url = match project.image_for_width(width) {
+        Some(variant) => variant.url.clone(),
+        None => project.image_path.clone(), // Uploaded before variants
+    };
+    Ok(Some(url))
+}
 
-    Ok(summary)
+// Returns the bytes of a stored image, by the URL upload_image returned. Only images of the caller's projects can be read.
+#[tauri::command]
+async fn get_image(
+    store: State<'_, SharedProjectStore>,
+    images: State<'_, SharedImageStore>,
+    uploads: State<'_, SharedUploadStore>,
+    image_url: String,
+    session: Session,
+) -> HookedResult<Vec<u8>> {
+    let not_found = || HookedError::NotFound("Image not found".to_string());
+    let key = images.key_from_url(&image_url).ok_or_else(not_found)?;
+
+    // Uploaded by the caller, or on one of their projects for images from before uploads were recorded
+    // (checked when the project was saved, see check_image) and projects synced from another device
+    let account = ProjectFilter::for_account(session.account_id);
+    let owned = uploads.find(&session.account_id, std::slice::from_ref(&key)).await?.iter().any(|upload| upload.url == image_url)
+        || store.count(&ProjectFilter { image_key: Some(key.clone()), ..account.clone() }).await? > 0
+        || store.count(&ProjectFilter { image_path: Some(image_url), ..account }).await? > 0;
+    if !owned {
+        return Err(not_found());
+    }
+    images.get(&key).await
 }
 
 #[tauri::command]
-async fn get_holds_summary(client: State<'_, MongoClient>, account_id: String) -> Result<Vec<(String, i64, i64)>, String> {
-    let collection = client.database("hooked_db").collection::<Document>("projects");
+async fn get_project_by_id(store: State<'_, SharedProjectStore>, id: String, session: Session) -> HookedResult<Option<Project>> {
+    let account_id = session.account_id;
 
-    let account_id = bson::oid::ObjectId::parse_str(&account_id)
-        .map_err(|e| format!("Invalid account_id: {}", e))?;
+    // Convert the string ID to ObjectId
+    let object_id = parse_object_id(&id)?;
 
-    // Pipeline to group by style for done (is_sent = 1)
-    let done_pipeline = vec![
-        doc! { "$match": { "account_id": account_id.clone(), "is_sent": 1 }},
-        doc! { "$unwind": "$holds" },  // break style array into multiple docs
-        doc! { "$group": { "_id": "$holds", "count": { "$sum": 1 }}}
-    ];
+    store.get(&account_id, &object_id).await
+}
 
-    let mut done_cursor = collection.aggregate(done_pipeline, None).await.map_err(|e| e.to_string())?;
-    let mut done_counts = std::collections::HashMap::new();
-    while let Some(doc) = done_cursor.try_next().await.map_err(|e| e.to_string())? {
-        if let Some(hold) = doc.get_str("_id").ok() {
-            let count = match doc.get("count") {
-                Some(bson::Bson::Int32(n)) => *n as i64,
-                Some(bson::Bson::Int64(n)) => *n,
-                _ => 0,
-            };
-            done_counts.insert(hold.to_string(), count);
-        }
+// Logs an attempt on one of the caller's projects, then updates the project's attempts, sent state and sent_date from its log.
+// Without a session_id the attempt is linked to the running climbing session, if any. Returns the saved attempt with its id.
+#[tauri::command]
+async fn log_attempt(
+    store: State<'_, SharedProjectStore>,
+    attempts: State<'_, SharedAttemptStore>,
+    climbing_sessions: State<'_, SharedClimbingSessionStore>,
+    session: Session,
+    mut attempt: Attempt,
+) -> HookedResult<Attempt> {
+    let account_id = session.account_id;
+    let mut project = store.get(&account_id, &attempt.project_id).await?
+        .ok_or_else(|| HookedError::NotFound("Project not found".to_string()))?;
+
+    attempt.account_id = account_id;
+    if attempt.timestamp == 0 {
+        attempt.timestamp = chrono::Utc::now().timestamp();
     }
-
-    // Pipeline to group by style for practicing (is_sent = 0)
-    let practicing_pipeline = vec![
-        doc! { "$match": { "account_id": account_id.clone(), "is_sent": 0 }},
-        doc! { "$unwind": "$holds" },
-        doc! { "$group": { "_id": "$holds", "count": { "$sum": 1 }}}
-    ];
-
-    let mut practicing_cursor = collection.aggregate(practicing_pipeline, None).await.map_err(|e| e.to_string())?;
-    let mut practicing_counts = std::collections::HashMap::new();
-    while let Some(doc) = practicing_cursor.try_next().await.map_err(|e| e.to_string())? {
-        if let Some(hold) = doc.get_str("_id").ok() {
-            let count = match doc.get("count") {
-                Some(bson::Bson::Int32(n)) => *n as i64,
-                Some(bson::Bson::Int64(n)) => *n,
-                _ => 0,
-            };
-            practicing_counts.insert(hold.to_string(), count);
+    attempt.session_id = match attempt.session_id {
+        Some(id) => {
+            climbing_sessions.find(&account_id, &id).await?.ok_or_else(|| HookedError::NotFound("Climbing session not found".to_string()))?;
+            Some(id)
         }
-    }
+        None => climbing_sessions.running(&account_id).await?.and_then(|running| running._id),
+    };
 
-    // Build summary list
-    let mut summary = Vec::new();
-    for hold in done_counts.keys().chain(practicing_counts.keys()) {
-        let done = *done_counts.get(hold).unwrap_or(&0);
-        let practicing = *practicing_counts.get(hold).unwrap_or(&0);
-        summary.push((hold.clone(), done, practicing));
-    }
+    let mut log = attempts.list_for_project(&account_id, &attempt.project_id).await?;
+    attempt.validate(&project, &log)?;
+    attempt._id = Some(attempts.insert(attempt.clone()).await?);
 
-    Ok(summary)
+    // Keep the log in time order, the attempt may have been logged after the fact
+    let position = log.partition_point(|logged| logged.timestamp <= attempt.timestamp);
+    log.insert(position, attempt.clone());
+
+    project.apply_attempts(&log);
+    project.normalize();
+    store.update(&account_id, project).await?;
+
+    Ok(attempt)
 }
 
-// Uploads image data to Cloudinary and returns the secure_url.
+// Lists the attempts on one of the caller's projects, oldest first.
 #[tauri::command]
-async fn upload_image(image_data: Vec<u8>, image_name: String) -> Result<String, String> { // image_data: A vector of bytes (Vec<u8>) representing the raw image data.
-    let client = reqwest::Client::new();
-    let cloud_name = std::env::var("CLOUDINARY_CLOUD_NAME").expect("CLOUDINARY_CLOUD_NAME must be set");
-    let upload_preset = std::env::var("CLOUDINARY_UPLOAD_PRESET").expect("CLOUDINARY_UPLOAD_PRESET must be set");
+async fn list_attempts(attempts: State<'_, SharedAttemptStore>, session: Session, project_id: String) -> HookedResult<Vec<Attempt>> {
+    let object_id = parse_object_id(&project_id)?;
+    attempts.list_for_project(&session.account_id, &object_id).await
+}
 
+// Starts a climbing session now. Fails while another session is running.
+#[tauri::command]
+async fn start_session(
+    climbing_sessions: State<'_, SharedClimbingSessionStore>,
+    session: Session,
+    location: Option<String>,
+    notes: Option<String>,
+) -> HookedResult<ClimbingSession> {
+    let account_id = session.account_id;
+    // Friendlier than waiting for the unique index to reject the insert, which still catches two calls at once
+    if climbing_sessions.running(&account_id).await?.is_some() {
+        return Err(already_running());
+    }
 
-    // Createthe image part.
-    let part = reqwest::multipart::Part::bytes(image_data) // Converts the raw image_data (a byte vector) into a multipart form part for file uploads.
-        .file_name(image_name); // Specifies the image name for the uploaded file.
+    let mut climbing_session = ClimbingSession {
+        _id: None,
+        account_id,
+        started_at: chrono::Utc::now().timestamp(),
+        ended_at: None,
+        location,
+        effort: None,
+        notes,
+    };
+    climbing_session._id = Some(climbing_sessions.insert(climbing_session.clone()).await?);
+    Ok(climbing_session)
+}
 
-    // Create the multipart form.
-    let form = reqwest::multipart::Form::new() // Creates a new multipart form object.
-        .part("file", part) // Adds the image data to the form under the "file" field (required by Cloudinary).
-        .text("upload_preset", upload_preset.to_string()); // Adds the upload preset to the form as a text field (Cloudinary requires an upload_preset unless you use authenticated uploads).
+// Ends a running climbing session now, with the perceived effort (1 to 10) and notes if given.
+#[tauri::command]
+async fn end_session(
+    climbing_sessions: State<'_, SharedClimbingSessionStore>,
+    session: Session,
+    id: String,
+    effort: Option<u8>,
+    notes: Option<String>,
+) -> HookedResult<ClimbingSession> {
+    let object_id = parse_object_id(&id)?;
+    let mut climbing_session = climbing_sessions.find(&session.account_id, &object_id).await?
+        .ok_or_else(|| HookedError::NotFound("Climbing session not found".to_string()))?;
+    if !climbing_session.is_running() {
+        return Err(HookedError::Validation("The climbing session already ended".to_string()));
+    }
 
-    // Send the upload request.
-    let res = client
-        .post(format!("https://api.cloudinary.com/v1_1/{}/upload", cloud_name))
-        .multipart(form) // Attaches the multipart form containing the image data and preset.
-        .send()
-        .await
-        .map_err(|e| format!("Request failed: {}", e))?;
-
-    // Handle successful upload.
-    if res.status().is_success() { // Checks if the HTTP response indicates success (status code 2xx).
-        let response_body: Value = res.json().await.map_err(|e| format!("Failed to parse JSON: {}", e))?; // res.json().await: Parses the response body as JSON asynchronously.
-        // Extract the secure URL.
-        if let Some(secure_url) = response_body.get("secure_url").and_then(Value::as_str) { // response_body.get("secure_url"): Retrieves the secure_url field from the JSON response.
-            Ok(secure_url.to_string()) // Returns the secure URL if found.
-        } else {
-            Err("`secure_url` not found in Cloudinary response".to_string())
-        }
-    // Handle upload failure.
-    } else {
-        let status = res.status();
-        let error_body = res.text().await.unwrap_or_else(|_| "Failed to read error body".to_string());
-        Err(format!("Cloudinary upload failed (Status: {}): {}", status, error_body))
+    climbing_session.ended_at = Some(chrono::Utc::now().timestamp());
+    climbing_session.effort = effort;
+    if notes.is_some() {
+        climbing_session.notes = notes;
     }
+    climbing_session.validate()?;
+
+    climbing_sessions.update(&climbing_session).await?;
+    Ok(climbing_session)
 }
 
+// Lists the caller's climbing sessions, newest first, with the attempts, sends and load of each.
+// from / to: Only sessions started within this range (UNIX seconds, inclusive).
 #[tauri::command]
-async fn get_project_by_id(state: State<'_, Arc<Mutex<DatabaseHelper>>>, id: String) -> Result<Option<Project>, String> {
-    let db = state.lock().await;
-
-    // Convert the string ID to ObjectId
-    let object_id = ObjectId::parse_str(&id).map_err(|e| format!("Invalid ObjectId: {}", e))?;
-
-    match db.get_project_by_id(&object_id).await {
-        Ok(Some(project)) => Ok(Some(project)),
-        Ok(none) => Ok(none),
-        Err(err) => Err(format!("Error fetching project: {}", err)),
-    }
+async fn list_sessions(
+    climbing_sessions: State<'_, SharedClimbingSessionStore>,
+    attempts: State<'_, SharedAttemptStore>,
+    session: Session,
+    from: Option<i64>,
+    to: Option<i64>,
+) -> HookedResult<Vec<SessionSummary>> {
+    let account_id = session.account_id;
+    let sessions = climbing_sessions.list(&account_id, from, to).await?;
+    let ids: Vec<ObjectId> = sessions.iter().filter_map(|climbing_session| climbing_session._id).collect();
+    let logged = attempts.list_for_sessions(&account_id, &ids).await?;
+
+    Ok(project_stats::summarize_sessions(sessions, &logged))
 }
 
 #[tauri::command]
-async fn create_account(email: String, password: String, db: State<'_, Arc<Mutex<DatabaseHelper>>>) -> Result<String, String> {
+async fn create_account(email: String, password: String, db: State<'_, Arc<Mutex<DatabaseHelper>>>) -> HookedResult<String> {
   // Lock the Mutex asynchronously
   let db = db.lock().await;
   db.create_account(&email, &password)
     .await
     .map(|oid| oid.to_hex())
-    .map_err(|e| format!("Error creating account: {:?}", e))
 }
 
 #[tauri::command]
-async fn login(email: String, password: String, db: State<'_, Arc<Mutex<DatabaseHelper>>>) -> Result<(String, String), String> {
+// device: A name for the device logging in, defaults to "unknown device"
+async fn login(email: String, password: String, device: Option<String>, db: State<'_, Arc<Mutex<DatabaseHelper>>>) -> HookedResult<SessionTokens> {
   // Lock the Mutex asynchronously
   let db = db.lock().await;
-  db.login(&email, &password).await
-    .map(|(token, account_id)| (token, account_id.to_hex()))
-    .map_err(|e| format!("Error logging in: {:?}", e))
-}
\ No newline at end of file
+  let device = device.unwrap_or_else(|| "unknown device".to_string());
+  db.login(&email, &password, &device).await
+}
+
+#[tauri::command]
+// Called by the frontend when its access token expired. Returns a new token pair, the old refresh token stops working.
+async fn refresh_session(refresh_token: String, db: State<'_, Arc<Mutex<DatabaseHelper>>>) -> HookedResult<SessionTokens> {
+  let db = db.lock().await;
+  db.refresh_session(&refresh_token).await
+}
+
+#[tauri::command]
+// Logs this device out
+async fn logout(refresh_token: String, db: State<'_, Arc<Mutex<DatabaseHelper>>>) -> HookedResult<()> {
+  let db = db.lock().await;
+  db.logout(&refresh_token).await
+}
+
+#[tauri::command]
+// Logs every device of the caller's account out, returning how many sessions were ended
+async fn logout_all_devices(session: Session, db: State<'_, Arc<Mutex<DatabaseHelper>>>) -> HookedResult<u64> {
+  let db = db.lock().await;
+  db.logout_all_devices(&session.account_id).await
+}
+
+#[tauri::command]
+// Emails a reset code if the address belongs to an account. Always succeeds, so it doesn't reveal which emails are registered.
+async fn request_password_reset(email: String, db: State<'_, Arc<Mutex<DatabaseHelper>>>) -> HookedResult<()> {
+  let db = db.lock().await;
+  db.request_password_reset(&email).await
+}
+
+#[tauri::command]
+// Sets a new password using the emailed reset code
+async fn confirm_password_reset(token: String, new_password: String, db: State<'_, Arc<Mutex<DatabaseHelper>>>) -> HookedResult<()> {
+  let db = db.lock().await;
+  db.confirm_password_reset(&token, &new_password).await
+}
+
+#[tauri::command]
+// Changes the caller's password. The current password has to be given again.
+async fn change_password(session: Session, old_password: String, new_password: String, db: State<'_, Arc<Mutex<DatabaseHelper>>>) -> HookedResult<()> {
+  let db = db.lock().await;
+  db.change_password(&session.account_id, &old_password, &new_password).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)] // Every store is its own piece of Tauri state
+// Deletes the caller's account with every project and project image. The password has to be given again.
+async fn delete_account(
+  session: Session,
+  password: String,
+  store: State<'_, SharedProjectStore>,
+  scales: State<'_, SharedGradeScaleStore>,
+  attempts: State<'_, SharedAttemptStore>,
+  climbing_sessions: State<'_, SharedClimbingSessionStore>,
+  locations: State<'_, SharedLocationStore>,
+  images: State<'_, SharedImageStore>,
+  uploads: State<'_, SharedUploadStore>,
+  db: State<'_, Arc<Mutex<DatabaseHelper>>>,
+) -> HookedResult<()> {
+  let db = db.lock().await;
+  let account_id = session.account_id;
+  db.verify_password(&account_id, &password).await?;
+
+  // Projects go first, so a failure part way leaves an account the user can still log into and retry
+  for project in store.query(&ProjectFilter::for_account(account_id)).await? {
+    let Some(project_id) = project._id else { continue };
+    if let Some(deleted) = store.delete(&account_id, &project_id).await? {
+      delete_project_image(images.inner(), uploads.inner(), deleted).await;
+    }
+  }
+  scales.delete_for_account(&account_id).await?;
+  attempts.delete_for_account(&account_id).await?;
+  climbing_sessions.delete_for_account(&account_id).await?;
+  locations.delete_for_account(&account_id).await?;
+  uploads.delete_for_account(&account_id).await?;
+
+  db.delete_account(&account_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database_helper::tests::project;
+    use crate::sqlite_store::tests::temp_store;
+    use crate::sqlite_store::SqliteStore;
+
+    fn with_image(key: &str) -> Project {
+        let url = format!("https://example.com/{}.webp", key);
+        Project {
+            image_path: url.clone(),
+            image_public_id: Some(key.to_string()),
+            image_variants: vec![StoredVariant { variant: ImageVariant::Original, url, public_id: key.to_string(), width: 10, height: 10 }],
+            ..project()
+        }
+    }
+
+    #[tokio::test]
+    async fn projects_only_take_images_their_account_uploaded() {
+        let temp = temp_store().await;
+        let uploads: SharedUploadStore = Arc::new(SqliteStore::open(&temp.path).await.unwrap());
+        let (owner, other) = (ObjectId::new(), ObjectId::new());
+        let upload = ImageUpload { _id: None, account_id: owner, public_id: "mine".to_string(), url: "https://example.com/mine.webp".to_string(), uploaded_at: 1 };
+        uploads.insert(upload).await.unwrap();
+
+        assert!(check_image(&uploads, &owner, &with_image("mine"), None).await.is_ok());
+        assert!(check_image(&uploads, &owner, &project(), None).await.is_ok());
+        assert!(matches!(check_image(&uploads, &other, &with_image("mine"), None).await, Err(HookedError::Validation(_))));
+
+        // An uploaded key can't be paired with someone else's URL
+        let mut mixed = with_image("mine");
+        mixed.image_path = "https://example.com/theirs.webp".to_string();
+        assert!(check_image(&uploads, &owner, &mixed, None).await.is_err());
+
+        // An image the project already had (uploaded before uploads were recorded) is kept
+        let legacy = with_image("legacy");
+        assert!(check_image(&uploads, &owner, &legacy, None).await.is_err());
+        assert!(check_image(&uploads, &owner, &legacy, Some(&legacy)).await.is_ok());
+        assert!(check_image(&uploads, &owner, &with_image("legacy_other"), Some(&legacy)).await.is_err());
+    }
+}
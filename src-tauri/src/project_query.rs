@@ -0,0 +1,223 @@
+// src-tauri/src/project_query.rs
+
+// Input and output of the `query_projects` command: filters, sort keys and cursor pagination.
+// Pages are keyset based: the cursor holds the sort values of the last project on the page,
+// so the next page starts right after it even if projects were added or removed in between.
+
+
+// IMPORTS
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+
+use crate::database_helper::{Discipline, Project};
+use crate::error::{HookedError, HookedResult};
+use crate::project_store::{ProjectFilter, TagMatch};
+
+// Page size when the frontend doesn't ask for one, and the most it can ask for
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+// QUERY
+
+// Everything the frontend can filter, sort and page projects by. Every field is optional.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ProjectQuery {
+    pub active: Option<bool>,
+    pub sent: Option<bool>,
+    pub discipline: Option<Discipline>,
+    pub location_id: Option<ObjectId>,
+    pub wall_id: Option<ObjectId>,
+    pub grades: Option<Vec<String>>,
+    pub styles: Option<Vec<String>>,
+    pub styles_match: TagMatch, // "any" (default) or "all" of `styles`
+    pub exclude_styles: Option<Vec<String>>,
+    pub holds: Option<Vec<String>>,
+    pub holds_match: TagMatch,
+    pub exclude_holds: Option<Vec<String>>,
+    pub date_from: Option<i64>, // date_time range (UNIX seconds, inclusive)
+    pub date_to: Option<i64>,
+    pub sent_from: Option<i64>, // sent_date range (UNIX seconds, inclusive)
+    pub sent_to: Option<i64>,
+    pub min_attempts: Option<i32>,
+    pub max_attempts: Option<i32>,
+    pub sort: Vec<SortKey>,     // Applied in order. Defaults to newest first.
+    pub cursor: Option<String>, // `next_cursor` of the previous page
+    pub limit: Option<i64>,
+}
+
+impl ProjectQuery {
+    // The store filter for this query, always scoped to the caller's account
+    pub fn filter(&self, account_id: ObjectId) -> ProjectFilter {
+        ProjectFilter {
+            account_id: Some(account_id),
+            is_active: self.active.map(i32::from),
+            is_sent: self.sent.map(i32::from),
+            discipline: self.discipline,
+            location_id: self.location_id,
+            wall_id: self.wall_id,
+            grade_system: None,
+            grades: self.grades.clone(),
+            styles: self.styles.clone(),
+            styles_match: self.styles_match,
+            exclude_styles: self.exclude_styles.clone(),
+            holds: self.holds.clone(),
+            holds_match: self.holds_match,
+            exclude_holds: self.exclude_holds.clone(),
+            date_from: self.date_from,
+            date_to: self.date_to,
+            sent_from: self.sent_from,
+            sent_to: self.sent_to,
+            min_attempts: self.min_attempts,
+            max_attempts: self.max_attempts,
+            image_key: None,
+            image_path: None,
+        }
+    }
+
+    pub fn sort_keys(&self) -> Vec<SortKey> {
+        if self.sort.is_empty() {
+            vec![SortKey { field: SortField::DateTime, direction: SortDirection::Desc }]
+        } else {
+            self.sort.clone()
+        }
+    }
+
+    pub fn page_size(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
+}
+
+// SORTING
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    DateTime,
+    SentDate, // Projects that aren't sent sort as if sent_date were 0
+    Grade,    // By difficulty (see grade.rs), projects with an unknown grade first
+    Attempts,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SortKey {
+    pub field: SortField,
+    #[serde(default)]
+    pub direction: SortDirection,
+}
+
+impl SortField {
+    // The value this field sorts a project by
+    pub fn value_of(self, project: &Project) -> i64 {
+        match self {
+            SortField::DateTime => project.date_time,
+            SortField::SentDate => project.sent_date.unwrap_or(0),
+            SortField::Grade => project.difficulty.map(i64::from).unwrap_or(-1),
+            SortField::Attempts => project.attempts as i64,
+        }
+    }
+}
+
+// PAGINATION
+
+// Where a page ends: the sort values and id of its last project.
+// The id breaks ties, so projects with equal sort values are never skipped or repeated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageCursor {
+    pub values: Vec<i64>,
+    pub id: ObjectId,
+}
+
+impl PageCursor {
+    pub fn after(project: &Project, sort: &[SortKey]) -> Option<Self> {
+        Some(PageCursor {
+            values: sort.iter().map(|key| key.field.value_of(project)).collect(),
+            id: project._id?,
+        })
+    }
+
+    // Opaque to the frontend: base64 of the JSON
+    pub fn encode(&self) -> HookedResult<String> {
+        let json = serde_json::to_vec(self).map_err(|e| HookedError::Internal(e.to_string()))?;
+        Ok(URL_SAFE_NO_PAD.encode(json))
+    }
+
+    // Also checks the cursor was made for as many sort keys
+    pub fn decode(cursor: &str, sort: &[SortKey]) -> HookedResult<Self> {
+        let invalid = || HookedError::Validation("Invalid page cursor".to_string());
+
+        let json = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+        let cursor: PageCursor = serde_json::from_slice(&json).map_err(|_| invalid())?;
+
+        if cursor.values.len() != sort.len() {
+            return Err(invalid());
+        }
+        Ok(cursor)
+    }
+}
+
+// One page of results
+#[derive(Debug, Serialize)]
+pub struct ProjectPage {
+    pub projects: Vec<Project>,
+    pub total: i64,                  // Projects matching the filters, across all pages
+    pub next_cursor: Option<String>, // None on the last page
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database_helper::tests::project;
+
+    fn sort() -> Vec<SortKey> {
+        vec![
+            SortKey { field: SortField::Grade, direction: SortDirection::Desc },
+            SortKey { field: SortField::DateTime, direction: SortDirection::Asc },
+        ]
+    }
+
+    #[test]
+    fn cursor_round_trips() {
+        let project = Project { difficulty: Some(40), date_time: 1_700_000_123, ..project() };
+        let cursor = PageCursor::after(&project, &sort()).unwrap();
+
+        let decoded = PageCursor::decode(&cursor.encode().unwrap(), &sort()).unwrap();
+        assert_eq!(decoded.values, vec![40, 1_700_000_123]);
+        assert_eq!(decoded.id, project._id.unwrap());
+    }
+
+    #[test]
+    fn cursor_of_unknown_grade_sorts_first() {
+        let cursor = PageCursor::after(&project(), &sort()).unwrap();
+        assert_eq!(cursor.values[0], -1);
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        assert!(matches!(PageCursor::decode("not base64!", &sort()), Err(HookedError::Validation(_))));
+        let not_json = URL_SAFE_NO_PAD.encode("[1, 2]");
+        assert!(matches!(PageCursor::decode(&not_json, &sort()), Err(HookedError::Validation(_))));
+    }
+
+    #[test]
+    fn rejects_cursor_for_other_sort_keys() {
+        let cursor = PageCursor::after(&project(), &sort()).unwrap().encode().unwrap();
+        assert!(PageCursor::decode(&cursor, &sort()[..1]).is_err());
+    }
+
+    #[test]
+    fn page_size_is_clamped() {
+        assert_eq!(ProjectQuery::default().page_size(), DEFAULT_PAGE_SIZE);
+        assert_eq!(ProjectQuery { limit: Some(0), ..Default::default() }.page_size(), 1);
+        assert_eq!(ProjectQuery { limit: Some(10_000), ..Default::default() }.page_size(), MAX_PAGE_SIZE);
+    }
+}
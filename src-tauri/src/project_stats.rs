@@ -0,0 +1,366 @@
+// src-tauri/src/project_stats.rs
+
+// Statistics for the stats page: sends per grade and period, per-tag stats for the style and hold radar graphs,
+// and volume and load per climbing session.
+// The stores only count or list projects; rates, averages, grade order (by the difficulty stored
+// on each project, see grade.rs) and periods are worked out here so every backend reports them the same way.
+
+
+// IMPORTS
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+use crate::database_helper::{Attempt, ClimbingSession, Discipline, Project, SendStyle};
+use crate::grade::Difficulty;
+use crate::project_store::TagGradeCount;
+
+// Timestamps below this are in seconds, above it in milliseconds (older projects stored ms)
+const MILLIS_THRESHOLD: i64 = 10_000_000_000;
+
+// SENDS
+
+// Length of the periods sends are broken down by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SendPeriod {
+    Week, // ISO weeks, starting on Monday
+    #[default]
+    Month,
+    Year,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GradeSends {
+    pub grade: String,
+    pub count: i64,
+}
+
+// Sends within one period
+#[derive(Debug, Clone, Serialize)]
+pub struct PeriodSends {
+    pub period: String, // "2025-W07", "2025-02" or "2025"
+    pub start: i64,     // UNIX timestamp of the first day (UTC)
+    pub total: i64,
+    pub by_grade: Vec<GradeSends>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SendsSummary {
+    pub total: i64,
+    pub by_grade: Vec<GradeSends>,   // Easiest grade first
+    pub periods: Vec<PeriodSends>,   // Oldest first, only periods with sends
+    pub by_discipline: Vec<(Discipline, i64)>,  // Every discipline, in Discipline::ALL order
+    pub by_send_style: Vec<(SendStyle, i64)>,   // Only styles that were used
+}
+
+// Breaks the given sent projects down by grade and by period of their sent_date
+// (date_time for projects sent before sent_date was recorded).
+pub fn summarize_sends(projects: &[Project], period: SendPeriod) -> SendsSummary {
+    let mut difficulties: HashMap<&str, Difficulty> = HashMap::new();
+    let mut by_grade: HashMap<&str, i64> = HashMap::new();
+    let mut by_period: BTreeMap<NaiveDate, HashMap<&str, i64>> = BTreeMap::new();
+    let mut by_discipline: HashMap<Discipline, i64> = HashMap::new();
+    let mut by_send_style: HashMap<SendStyle, i64> = HashMap::new();
+
+    for project in projects {
+        *by_discipline.entry(project.discipline).or_default() += 1;
+        if let Some(style) = project.send_style {
+            *by_send_style.entry(style).or_default() += 1;
+        }
+
+        if let Some(difficulty) = project.difficulty {
+            difficulties.insert(project.grade.as_str(), difficulty);
+        }
+        *by_grade.entry(project.grade.as_str()).or_default() += 1;
+
+        if let Some(day) = to_date(project.sent_date.unwrap_or(project.date_time)) {
+            let grades = by_period.entry(period_start(day, period)).or_default();
+            *grades.entry(project.grade.as_str()).or_default() += 1;
+        }
+    }
+
+    let periods = by_period.into_iter().map(|(start, grades)| {
+        let by_grade = ordered_grades(grades, &difficulties);
+        PeriodSends {
+            period: period_label(start, period),
+            start: start.and_hms_opt(0, 0, 0).map(|midnight| midnight.and_utc().timestamp()).unwrap_or_default(),
+            total: by_grade.iter().map(|grade| grade.count).sum(),
+            by_grade,
+        }
+    }).collect();
+
+    SendsSummary {
+        total: projects.len() as i64,
+        by_grade: ordered_grades(by_grade, &difficulties),
+        periods,
+        by_discipline: Discipline::ALL.into_iter()
+            .map(|discipline| (discipline, by_discipline.get(&discipline).copied().unwrap_or(0)))
+            .collect(),
+        by_send_style: SendStyle::ALL.into_iter()
+            .filter_map(|style| by_send_style.get(&style).map(|count| (style, *count)))
+            .collect(),
+    }
+}
+
+// Grades by difficulty, then any grades without one alphabetically
+fn ordered_grades(counts: HashMap<&str, i64>, difficulties: &HashMap<&str, Difficulty>) -> Vec<GradeSends> {
+    let mut grades: Vec<GradeSends> = counts.into_iter()
+        .map(|(grade, count)| GradeSends { grade: grade.to_string(), count })
+        .collect();
+    grades.sort_by(|a, b| {
+        let rank = |grade: &str| difficulties.get(grade).copied().unwrap_or(Difficulty::MAX);
+        rank(&a.grade).cmp(&rank(&b.grade)).then_with(|| a.grade.cmp(&b.grade))
+    });
+    grades
+}
+
+// A timestamp in seconds or milliseconds, in seconds
+pub fn to_seconds(timestamp: i64) -> i64 {
+    if timestamp.abs() < MILLIS_THRESHOLD { timestamp } else { timestamp / 1000 }
+}
+
+// Calendar day (UTC) of a timestamp in seconds or milliseconds
+fn to_date(timestamp: i64) -> Option<NaiveDate> {
+    DateTime::from_timestamp(to_seconds(timestamp), 0).map(|date| date.date_naive())
+}
+
+fn period_start(day: NaiveDate, period: SendPeriod) -> NaiveDate {
+    match period {
+        SendPeriod::Week => day - Duration::days(day.weekday().num_days_from_monday() as i64),
+        SendPeriod::Month => day.with_day(1).unwrap_or(day),
+        SendPeriod::Year => day.with_ordinal(1).unwrap_or(day),
+    }
+}
+
+fn period_label(start: NaiveDate, period: SendPeriod) -> String {
+    match period {
+        SendPeriod::Week => start.format("%G-W%V").to_string(),
+        SendPeriod::Month => start.format("%Y-%m").to_string(),
+        SendPeriod::Year => start.format("%Y").to_string(),
+    }
+}
+
+// TAGS
+
+// Summary of every project carrying one style or hold tag
+#[derive(Debug, Clone, Serialize)]
+pub struct TagSummary {
+    pub tag: String,
+    pub sent: i64,                          // Projects with this tag that are sent
+    pub open: i64,                          // ... and that aren't yet
+    pub send_rate: f64,                     // sent / (sent + open), between 0 and 1
+    pub avg_attempts_to_send: Option<f64>,  // Average attempts of the sent projects, None if nothing is sent
+    pub hardest_grade_sent: Option<String>, // None if nothing is sent or no sent grade has a difficulty
+}
+
+// Folds the per-grade rows into one summary per tag, sorted by tag
+pub fn summarize_tags(counts: Vec<TagGradeCount>) -> Vec<TagSummary> {
+    #[derive(Default)]
+    struct Totals {
+        sent: i64,
+        open: i64,
+        sent_attempts: i64,
+        hardest: Option<(Difficulty, String)>,
+    }
+
+    // BTreeMap keeps one entry per tag, in order
+    let mut by_tag: BTreeMap<String, Totals> = BTreeMap::new();
+
+    for row in counts {
+        let totals = by_tag.entry(row.tag).or_default();
+        if !row.sent {
+            totals.open += row.projects;
+            continue;
+        }
+
+        totals.sent += row.projects;
+        totals.sent_attempts += row.attempts;
+        if let Some(difficulty) = row.difficulty {
+            if totals.hardest.as_ref().is_none_or(|(hardest, _)| difficulty > *hardest) {
+                totals.hardest = Some((difficulty, row.grade));
+            }
+        }
+    }
+
+    by_tag.into_iter().map(|(tag, totals)| {
+        let projects = totals.sent + totals.open;
+        TagSummary {
+            tag,
+            sent: totals.sent,
+            open: totals.open,
+            send_rate: if projects > 0 { totals.sent as f64 / projects as f64 } else { 0.0 },
+            avg_attempts_to_send: (totals.sent > 0).then(|| totals.sent_attempts as f64 / totals.sent as f64),
+            hardest_grade_sent: totals.hardest.map(|(_, grade)| grade),
+        }
+    }).collect()
+}
+
+// SESSIONS
+
+// A climbing session with what was climbed during it
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    #[serde(flatten)]
+    pub session: ClimbingSession,
+    pub attempts: i64,
+    pub sends: i64,                    // Attempts that ended in a send or flash
+    pub projects: i64,                 // Different projects tried
+    pub duration_minutes: Option<i64>, // None while the session is running
+    pub load: Option<i64>,             // Effort x minutes (session RPE), None until the session ended with an effort
+}
+
+// Pairs every session with the attempts linked to it. Sessions keep their order.
+pub fn summarize_sessions(sessions: Vec<ClimbingSession>, attempts: &[Attempt]) -> Vec<SessionSummary> {
+    let mut by_session: HashMap<ObjectId, Vec<&Attempt>> = HashMap::new();
+    for attempt in attempts {
+        if let Some(session_id) = attempt.session_id {
+            by_session.entry(session_id).or_default().push(attempt);
+        }
+    }
+
+    sessions.into_iter().map(|session| {
+        let logged = session._id.and_then(|id| by_session.get(&id)).map(Vec::as_slice).unwrap_or_default();
+        let duration_minutes = session.ended_at.map(|ended_at| (ended_at - session.started_at).max(0) / 60);
+
+        SessionSummary {
+            attempts: logged.len() as i64,
+            sends: logged.iter().filter(|attempt| attempt.outcome.is_send()).count() as i64,
+            projects: logged.iter().map(|attempt| attempt.project_id).collect::<HashSet<_>>().len() as i64,
+            duration_minutes,
+            load: duration_minutes.zip(session.effort).map(|(minutes, effort)| minutes * i64::from(effort)),
+            session,
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database_helper::tests::project;
+
+    fn row(tag: &str, grade: &str, difficulty: Option<i32>, sent: bool, projects: i64, attempts: i64) -> TagGradeCount {
+        TagGradeCount { tag: tag.to_string(), grade: grade.to_string(), difficulty, sent, projects, attempts }
+    }
+
+    #[test]
+    fn folds_rows_into_one_summary_per_tag() {
+        let summaries = summarize_tags(vec![
+            row("slab", "V2", Some(20), false, 1, 3),
+            row("crimp", "V3", Some(30), true, 2, 6),
+            row("slab", "V4", Some(40), true, 1, 2),
+            row("crimp", "V5", Some(50), true, 1, 4),
+            row("crimp", "V4", Some(40), false, 3, 9),
+        ]);
+        let tags: Vec<&str> = summaries.iter().map(|summary| summary.tag.as_str()).collect();
+        assert_eq!(tags, ["crimp", "slab"]);
+
+        let crimp = &summaries[0];
+        assert_eq!((crimp.sent, crimp.open), (3, 3));
+        assert_eq!(crimp.send_rate, 0.5);
+        // Attempts of open projects don't count towards attempts-to-send
+        assert_eq!(crimp.avg_attempts_to_send, Some(10.0 / 3.0));
+        assert_eq!(crimp.hardest_grade_sent.as_deref(), Some("V5"));
+    }
+
+    #[test]
+    fn hardest_grade_goes_by_difficulty() {
+        // "V10" sorts before "V9" as a string
+        let summaries = summarize_tags(vec![
+            row("pinch", "V9", Some(90), true, 1, 5),
+            row("pinch", "V10", Some(100), true, 1, 12),
+            // Free text grades have no difficulty and are never the hardest
+            row("pinch", "V11+", None, true, 1, 20),
+        ]);
+        assert_eq!(summaries[0].hardest_grade_sent.as_deref(), Some("V10"));
+    }
+
+    #[test]
+    fn tags_without_sends() {
+        let summaries = summarize_tags(vec![row("pocket", "V6", Some(60), false, 2, 7)]);
+        let pocket = &summaries[0];
+        assert_eq!((pocket.sent, pocket.open), (0, 2));
+        assert_eq!(pocket.send_rate, 0.0);
+        assert_eq!(pocket.avg_attempts_to_send, None);
+        assert_eq!(pocket.hardest_grade_sent, None);
+        assert!(summarize_tags(Vec::new()).is_empty());
+    }
+
+    fn at(year: i32, month: u32, day: u32) -> i64 {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap().and_hms_opt(12, 0, 0).unwrap().and_utc().timestamp()
+    }
+
+    fn sent(grade: &str, difficulty: Option<i32>, sent_date: Option<i64>, date_time: i64) -> Project {
+        Project { grade: grade.to_string(), difficulty, is_sent: 1, sent_date, date_time, ..project() }
+    }
+
+    fn sends() -> Vec<Project> {
+        vec![
+            Project { send_style: Some(SendStyle::Flash), ..sent("V10", Some(100), Some(at(2025, 2, 5)), 0) },
+            // Stored in milliseconds by older versions
+            sent("V9", Some(90), Some(at(2025, 2, 9) * 1000), 0),
+            // Sent before sent_date was recorded
+            sent("V9", Some(90), None, at(2025, 3, 1)),
+            sent("V5+", None, Some(at(2025, 3, 2)), 0),
+            Project { discipline: Discipline::Sport, send_style: Some(SendStyle::Lead), ..sent("6a", Some(60), Some(at(2024, 12, 30)), 0) },
+        ]
+    }
+
+    fn grades(by_grade: &[GradeSends]) -> Vec<(&str, i64)> {
+        by_grade.iter().map(|grade| (grade.grade.as_str(), grade.count)).collect()
+    }
+
+    fn periods(summary: &SendsSummary) -> Vec<(&str, i64)> {
+        summary.periods.iter().map(|period| (period.period.as_str(), period.total)).collect()
+    }
+
+    #[test]
+    fn orders_grades_by_difficulty() {
+        let summary = summarize_sends(&sends(), SendPeriod::Month);
+        assert_eq!(summary.total, 5);
+        // Not "V10" before "V9", and grades without a difficulty last
+        assert_eq!(grades(&summary.by_grade), [("6a", 1), ("V9", 2), ("V10", 1), ("V5+", 1)]);
+        assert_eq!(grades(&summary.periods[1].by_grade), [("V9", 1), ("V10", 1)]);
+    }
+
+    #[test]
+    fn counts_disciplines_and_send_styles() {
+        let summary = summarize_sends(&sends(), SendPeriod::Month);
+        let count = |discipline| summary.by_discipline.iter().find(|(d, _)| *d == discipline).map(|(_, count)| *count);
+        assert_eq!(count(Discipline::Boulder), Some(4));
+        assert_eq!(count(Discipline::Sport), Some(1));
+        assert_eq!(count(Discipline::Trad), Some(0));
+        assert_eq!(summary.by_send_style.len(), 2);
+        assert!(summary.by_send_style.contains(&(SendStyle::Flash, 1)));
+        assert!(summary.by_send_style.contains(&(SendStyle::Lead, 1)));
+    }
+
+    #[test]
+    fn buckets_sends_by_iso_week() {
+        let summary = summarize_sends(&sends(), SendPeriod::Week);
+        // 30 December 2024 is in the first week of 2025, 9 February (a Sunday) in the week of the 5th
+        assert_eq!(periods(&summary), [("2025-W01", 1), ("2025-W06", 2), ("2025-W09", 2)]);
+        assert_eq!(summary.periods[0].start, at(2024, 12, 30) - 12 * 3600);
+        assert_eq!(summary.periods[1].start, at(2025, 2, 3) - 12 * 3600);
+    }
+
+    #[test]
+    fn buckets_sends_by_month_and_year() {
+        let months = summarize_sends(&sends(), SendPeriod::Month);
+        assert_eq!(periods(&months), [("2024-12", 1), ("2025-02", 2), ("2025-03", 2)]);
+        assert_eq!(months.periods[2].start, at(2025, 3, 1) - 12 * 3600);
+
+        let years = summarize_sends(&sends(), SendPeriod::Year);
+        assert_eq!(periods(&years), [("2024", 1), ("2025", 4)]);
+        assert_eq!(years.periods[1].start, at(2025, 1, 1) - 12 * 3600);
+    }
+
+    #[test]
+    fn reads_seconds_and_milliseconds() {
+        assert_eq!(to_seconds(at(2025, 2, 9)), at(2025, 2, 9));
+        assert_eq!(to_seconds(at(2025, 2, 9) * 1000), at(2025, 2, 9));
+        assert_eq!(to_date(at(2025, 2, 9) * 1000), NaiveDate::from_ymd_opt(2025, 2, 9));
+    }
+}
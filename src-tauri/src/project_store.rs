@@ -0,0 +1,521 @@
+// src-tauri/src/project_store.rs
+
+// Storage abstraction for projects.
+// Tauri commands only ever talk to a `ProjectStore`, so the backend (MongoDB, or SQLite in sqlite_store.rs)
+// can be swapped out or faked without touching the commands themselves.
+
+
+// IMPORTS
+use async_trait::async_trait; // Lets us declare async functions on a trait object (Arc<dyn ProjectStore>).
+use serde::Deserialize;
+use std::sync::Arc;
+
+// MongoDB & BSON
+use mongodb::{Client, Collection, bson::{self, doc, Bson, Document, oid::ObjectId}};
+use futures_util::stream::TryStreamExt; // Provides asynchronous streaming methods (try_next on cursors).
+
+use crate::database_helper::{Coordinate, Discipline, Project};
+use crate::grade::{DIFFICULTY_STEP, FONT_SCALE_GRADES, FONT_SCALE_ID, V_SCALE_GRADES, V_SCALE_ID};
+use crate::project_query::{PageCursor, SortDirection, SortField, SortKey};
+use crate::error::{HookedError, HookedResult};
+use crate::sync::{now_millis, stamp_changes, RemoteChange};
+
+// Database + collection names shared by every Mongo-backed store
+pub const DATABASE_NAME: &str = "hooked_db";
+pub const PROJECTS_COLLECTION: &str = "projects";
+
+// Computed fields used to sort by sent_date with unsent projects as 0, and by difficulty with unknown grades as -1
+const SORT_SENT_DATE_FIELD: &str = "sort_sent_date";
+const SORT_DIFFICULTY_FIELD: &str = "sort_difficulty";
+// When MongoDB received the latest write of a project (server clock, UNIX ms), what devices pull changes by
+const PUSHED_AT_FIELD: &str = "pushed_at";
+
+// The store shared through Tauri's state. Commands take `State<'_, SharedProjectStore>`.
+pub type SharedProjectStore = Arc<dyn ProjectStore>;
+
+// QUERY TYPES
+
+// Backend-agnostic filter for project queries. Every field is optional, `None` means "don't filter on this".
+#[derive(Debug, Clone, Default)]
+pub struct ProjectFilter {
+    pub account_id: Option<ObjectId>,
+    pub is_active: Option<i32>,
+    pub is_sent: Option<i32>,
+    pub discipline: Option<Discipline>,
+    pub location_id: Option<ObjectId>,
+    pub wall_id: Option<ObjectId>,
+    pub grade_system: Option<String>, // A built-in system's id or a custom scale's id
+    pub grades: Option<Vec<String>>,
+    pub styles: Option<Vec<String>>,
+    pub styles_match: TagMatch,
+    pub exclude_styles: Option<Vec<String>>, // Projects with any of these styles never match
+    pub holds: Option<Vec<String>>,
+    pub holds_match: TagMatch,
+    pub exclude_holds: Option<Vec<String>>,
+    pub date_from: Option<i64>, // Inclusive bounds on date_time
+    pub date_to: Option<i64>,
+    pub sent_from: Option<i64>, // Inclusive bounds on sent_date (projects without one never match)
+    pub sent_to: Option<i64>,
+    pub min_attempts: Option<i32>,
+    pub max_attempts: Option<i32>,
+    pub image_key: Option<String>, // Projects whose image, or one of its variants, is stored under this key
+    pub image_path: Option<String>, // Projects whose image has this URL
+}
+
+impl ProjectFilter {
+    // Shorthand for the most common filter: all projects of one account
+    pub fn for_account(account_id: ObjectId) -> Self {
+        ProjectFilter { account_id: Some(account_id), ..Default::default() }
+    }
+}
+
+// How a list of style or hold tags is matched against a project's tags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagMatch {
+    #[default]
+    Any, // At least one of the tags
+    All, // Every one of the tags
+}
+
+// Which tag array a per-tag breakdown groups on. Each tag on a project counts once.
+#[derive(Debug, Clone, Copy)]
+pub enum GroupField {
+    Style,
+    Holds,
+}
+
+// One row of a per-tag breakdown: how many projects with this tag and grade are (or aren't) sent, the grade's difficulty,
+// and their attempts added up. A project with the same tag twice is counted once.
+#[derive(Debug, Clone)]
+pub struct TagGradeCount {
+    pub tag: String,
+    pub grade: String,
+    pub difficulty: Option<i32>,
+    pub sent: bool,
+    pub projects: i64,
+    pub attempts: i64,
+}
+
+// STORE TRAIT
+// Every call taking an account_id only sees projects owned by that account,
+// so a guessed id from another account behaves as if the project didn't exist.
+#[async_trait]
+pub trait ProjectStore: Send + Sync {
+    // Inserts a new project and returns its generated id
+    async fn create(&self, project: Project) -> HookedResult<ObjectId>;
+    // Fetches a single project by id
+    async fn get(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<Project>>;
+    // Replaces the stored fields of an existing project (project._id must be set)
+    async fn update(&self, account_id: &ObjectId, project: Project) -> HookedResult<()>;
+    // Replaces only the annotations (coordinates) of a project
+    async fn update_coordinates(&self, account_id: &ObjectId, id: &ObjectId, coordinates: Vec<Coordinate>) -> HookedResult<()>;
+    // Deletes a project, returning it so the caller can clean up related data (e.g. its image)
+    async fn delete(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<Project>>;
+    // Fetches every project matching the filter
+    async fn query(&self, filter: &ProjectFilter) -> HookedResult<Vec<Project>>;
+    // Fetches up to `limit` matching projects in `sort` order, starting after `after` (from the start when None)
+    async fn query_page(&self, filter: &ProjectFilter, sort: &[SortKey], after: Option<&PageCursor>, limit: i64) -> HookedResult<Vec<Project>>;
+    // Counts the projects matching the filter
+    async fn count(&self, filter: &ProjectFilter) -> HookedResult<i64>;
+    // Counts matching projects grouped by tag, grade and sent status
+    async fn tag_grade_counts(&self, filter: &ProjectFilter, group_by: GroupField) -> HookedResult<Vec<TagGradeCount>>;
+    // Sets grade_system and difficulty on projects saved before grades were checked, when their grade is a built-in one.
+    // Returns how many projects were updated.
+    async fn backfill_difficulty(&self) -> HookedResult<u64>;
+}
+
+// MONGODB IMPLEMENTATION
+pub struct MongoProjectStore {
+    collection: Collection<Document>,
+}
+
+impl MongoProjectStore {
+    pub fn new(client: &Client) -> Self {
+        let collection = client.database(DATABASE_NAME).collection::<Document>(PROJECTS_COLLECTION);
+        MongoProjectStore { collection }
+    }
+
+    // Converts a ProjectFilter into a MongoDB filter document
+    fn filter_document(filter: &ProjectFilter) -> Document {
+        let mut document = Document::new();
+
+        if let Some(account_id) = filter.account_id {
+            document.insert("account_id", account_id);
+        }
+        if let Some(is_active) = filter.is_active {
+            document.insert("is_active", Bson::Int32(is_active));
+        }
+        if let Some(is_sent) = filter.is_sent {
+            document.insert("is_sent", Bson::Int32(is_sent));
+        }
+        match filter.discipline {
+            // Projects saved before disciplines existed have no field and are boulders
+            Some(Discipline::Boulder) => { document.insert("discipline", doc! { "$in": [Bson::Null, Discipline::Boulder.as_str()] }); }
+            Some(discipline) => { document.insert("discipline", discipline.as_str()); }
+            None => {}
+        }
+        if let Some(location_id) = filter.location_id {
+            document.insert("location_id", location_id);
+        }
+        if let Some(wall_id) = filter.wall_id {
+            document.insert("wall_id", wall_id);
+        }
+        if let Some(grade_system) = &filter.grade_system {
+            document.insert("grade_system", grade_system);
+        }
+        if let Some(key) = &filter.image_key {
+            document.insert("$or", vec![doc! { "image_public_id": key }, doc! { "image_variants.public_id": key }]);
+        }
+        if let Some(path) = &filter.image_path {
+            document.insert("image_path", path);
+        }
+
+        // Empty lists are treated the same as no filter
+        if let Some(grades) = filter.grades.as_ref().filter(|list| !list.is_empty()) {
+            document.insert("grade", doc! { "$in": grades }); // Use $in to match multiple grades
+        }
+        if let Some(tags) = Self::tags_document(filter.styles.as_ref(), filter.styles_match, filter.exclude_styles.as_ref()) {
+            document.insert("style", tags);
+        }
+        if let Some(tags) = Self::tags_document(filter.holds.as_ref(), filter.holds_match, filter.exclude_holds.as_ref()) {
+            document.insert("holds", tags);
+        }
+
+        if let Some(range) = Self::range_document(filter.date_from, filter.date_to) {
+            document.insert("date_time", range);
+        }
+        if let Some(range) = Self::range_document(filter.sent_from, filter.sent_to) {
+            document.insert("sent_date", range);
+        }
+        if let Some(range) = Self::range_document(filter.min_attempts.map(i64::from), filter.max_attempts.map(i64::from)) {
+            document.insert("attempts", range);
+        }
+
+        document
+    }
+
+    // { $in | $all, $nin } on a tag array, for whichever lists are set
+    fn tags_document(tags: Option<&Vec<String>>, matching: TagMatch, exclude: Option<&Vec<String>>) -> Option<Document> {
+        let mut condition = Document::new();
+        if let Some(tags) = tags.filter(|list| !list.is_empty()) {
+            let operator = match matching {
+                TagMatch::Any => "$in",
+                TagMatch::All => "$all",
+            };
+            condition.insert(operator, tags);
+        }
+        if let Some(exclude) = exclude.filter(|list| !list.is_empty()) {
+            condition.insert("$nin", exclude); // Also matches projects without the field
+        }
+        (!condition.is_empty()).then_some(condition)
+    }
+
+    // { $gte, $lte } for whichever bounds are set
+    fn range_document(from: Option<i64>, to: Option<i64>) -> Option<Document> {
+        let mut range = Document::new();
+        if let Some(from) = from {
+            range.insert("$gte", from);
+        }
+        if let Some(to) = to {
+            range.insert("$lte", to);
+        }
+        (!range.is_empty()).then_some(range)
+    }
+
+    // Field a sort key reads. sent_date and difficulty are sorted through computed fields so missing values sort low.
+    fn sort_field_name(field: SortField) -> &'static str {
+        match field {
+            SortField::DateTime => "date_time",
+            SortField::SentDate => SORT_SENT_DATE_FIELD,
+            SortField::Grade => SORT_DIFFICULTY_FIELD,
+            SortField::Attempts => "attempts",
+        }
+    }
+
+    // Matches projects that come after the cursor in sort order:
+    // k1 past v1, or k1 = v1 and k2 past v2, ..., or all equal and a greater _id
+    fn after_cursor_document(sort: &[SortKey], cursor: &PageCursor) -> Document {
+        let mut branches = Vec::new();
+        let mut equal_so_far = Document::new();
+
+        for (key, value) in sort.iter().zip(&cursor.values) {
+            let value = Bson::Int64(*value);
+            let operator = match key.direction {
+                SortDirection::Asc => "$gt",
+                SortDirection::Desc => "$lt",
+            };
+            let field = Self::sort_field_name(key.field);
+
+            let mut branch = equal_so_far.clone();
+            branch.insert(field, doc! { operator: value.clone() });
+            branches.push(Bson::Document(branch));
+            equal_so_far.insert(field, value);
+        }
+
+        equal_so_far.insert("_id", doc! { "$gt": cursor.id });
+        branches.push(Bson::Document(equal_so_far));
+        doc! { "$or": branches }
+    }
+
+    // Update pipeline that makes `doc` the whole document (fields it leaves out are removed) and stamps pushed_at
+    // with the server's clock. Device clocks differ, and an edit made offline reaches MongoDB long after its updated_at.
+    fn replace_pipeline(doc: Document) -> Vec<Document> {
+        vec![doc! { "$replaceWith": { "$mergeObjects": [
+            { "$literal": doc },
+            { PUSHED_AT_FIELD: { "$toLong": "$$NOW" } },
+        ] } }]
+    }
+
+    // Writes a project exactly as given (including its sync versions), inserting it if missing.
+    // Used by the sync engine, which has already resolved any conflict.
+    pub async fn replace(&self, project: &Project) -> HookedResult<()> {
+        let _id = project._id.ok_or_else(|| HookedError::Validation("Project ID is required for replace".to_string()))?;
+        let doc = bson::to_document(project).map_err(HookedError::storage)?;
+        let options = mongodb::options::UpdateOptions::builder().upsert(true).build();
+
+        self.collection.update_one(doc! { "_id": _id }, Self::replace_pipeline(doc), options).await.map_err(HookedError::storage)?;
+        Ok(())
+    }
+
+    // Projects of an account pushed after `since` (pushed_at, the server's clock), or every project when `since` is None.
+    // Documents written before pushed_at existed are stamped first, so they are pulled once more.
+    pub async fn changed_since(&self, account_id: &ObjectId, since: Option<i64>) -> HookedResult<Vec<RemoteChange>> {
+        let unstamped = doc! { "account_id": account_id, PUSHED_AT_FIELD: { "$exists": false } };
+        let stamp = vec![doc! { "$set": { PUSHED_AT_FIELD: { "$toLong": "$$NOW" } } }];
+        self.collection.update_many(unstamped, stamp, None).await.map_err(HookedError::storage)?;
+
+        let filter = match since {
+            Some(since) => doc! { "account_id": account_id, PUSHED_AT_FIELD: { "$gt": since } },
+            None => doc! { "account_id": account_id },
+        };
+
+        let mut cursor = self.collection.find(filter, None).await.map_err(HookedError::storage)?;
+        let mut changes = Vec::new();
+
+        while let Some(doc) = cursor.try_next().await.map_err(HookedError::storage)? {
+            let pushed_at = doc.get_i64(PUSHED_AT_FIELD).unwrap_or(0);
+            let project = bson::from_document(doc).map_err(HookedError::storage)?;
+            changes.push(RemoteChange { project, pushed_at });
+        }
+        Ok(changes)
+    }
+
+    // Ids of every project an account has, without loading the documents
+    pub async fn ids_for_account(&self, account_id: &ObjectId) -> HookedResult<Vec<ObjectId>> {
+        let options = mongodb::options::FindOptions::builder().projection(doc! { "_id": 1 }).build();
+        let mut cursor = self.collection.find(doc! { "account_id": account_id }, options).await.map_err(HookedError::storage)?;
+        let mut ids = Vec::new();
+
+        while let Some(doc) = cursor.try_next().await.map_err(HookedError::storage)? {
+            if let Ok(id) = doc.get_object_id("_id") {
+                ids.push(id);
+            }
+        }
+        Ok(ids)
+    }
+
+    // Reads a $sum that MongoDB may return as either Int32 or Int64
+    fn number_value(doc: &Document, key: &str) -> i64 {
+        match doc.get(key) {
+            Some(Bson::Int32(n)) => *n as i64,
+            Some(Bson::Int64(n)) => *n,
+            _ => 0,
+        }
+    }
+}
+
+#[async_trait]
+impl ProjectStore for MongoProjectStore {
+    async fn create(&self, mut project: Project) -> HookedResult<ObjectId> {
+        stamp_changes(None, &mut project, now_millis())?;
+
+        // Convert project to BSON document
+        let doc = bson::to_document(&project).map_err(|e| HookedError::Internal(format!("Serialization error: {}", e)))?;
+
+        // Inserted without pushed_at, the next changed_since stamps it
+        let result = self.collection.insert_one(doc, None).await.map_err(HookedError::storage)?;
+        result.inserted_id.as_object_id().ok_or_else(|| HookedError::Storage("Inserted id is not an ObjectId".to_string()))
+    }
+
+    async fn get(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<Project>> {
+        let filter = doc! { "_id": id, "account_id": account_id };
+        let doc = self.collection.find_one(filter, None).await.map_err(HookedError::storage)?;
+
+        match doc {
+            Some(doc) => bson::from_document(doc).map(Some).map_err(HookedError::storage),
+            None => Ok(None),
+        }
+    }
+
+    async fn update(&self, account_id: &ObjectId, mut project: Project) -> HookedResult<()> {
+        let _id = project._id.ok_or_else(|| HookedError::Validation("Project ID is required for update".to_string()))?;
+        let filter = doc! {"_id": _id, "account_id": account_id};
+
+        let existing = self.get(account_id, &_id).await?.ok_or_else(|| HookedError::NotFound("Project not found".to_string()))?;
+
+        // The owner can't be changed through an update
+        project.account_id = *account_id;
+
+        // Only overwrite coordinates when a non-empty array was sent, otherwise keep the stored ones
+        if project.coordinates.is_empty() {
+            project.coordinates = existing.coordinates.clone();
+        }
+
+        // Record which fields changed, for sync conflict resolution
+        stamp_changes(Some(&existing), &mut project, now_millis())?;
+
+        // Replaced rather than $set, so fields cleared on the project (left out when serialized) are removed too
+        let doc = bson::to_document(&project).map_err(HookedError::storage)?;
+
+        let result = self.collection.update_one(filter, Self::replace_pipeline(doc), None).await.map_err(HookedError::storage)?;
+        if result.matched_count == 0 {
+            return Err(HookedError::NotFound("Project not found".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn update_coordinates(&self, account_id: &ObjectId, id: &ObjectId, coordinates: Vec<Coordinate>) -> HookedResult<()> {
+        let coordinates = bson::to_bson(&coordinates).map_err(HookedError::storage)?;
+        let now = now_millis();
+        let update = vec![doc! { "$set": {
+            "coordinates": { "$literal": coordinates },
+            "updated_at": now,
+            "field_versions.coordinates": now,
+            PUSHED_AT_FIELD: { "$toLong": "$$NOW" },
+        } }];
+
+        let result = self.collection.update_one(doc! { "_id": id, "account_id": account_id }, update, None).await.map_err(HookedError::storage)?;
+        if result.matched_count == 0 {
+            return Err(HookedError::NotFound("Project not found".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<Project>> {
+        let filter = doc! { "_id": id, "account_id": account_id };
+
+        // find_one_and_delete hands back the removed document in the same round trip
+        let deleted = self.collection.find_one_and_delete(filter, None).await.map_err(HookedError::storage)?;
+
+        match deleted {
+            Some(doc) => bson::from_document(doc).map(Some).map_err(HookedError::storage),
+            None => Ok(None),
+        }
+    }
+
+    async fn query(&self, filter: &ProjectFilter) -> HookedResult<Vec<Project>> {
+        let filter = Self::filter_document(filter);
+
+        let mut cursor = self.collection.find(filter, None).await.map_err(HookedError::storage)?;
+        let mut projects = Vec::new();
+
+        while let Some(doc) = cursor.try_next().await.map_err(HookedError::storage)? {
+            let project: Project = bson::from_document(doc).map_err(HookedError::storage)?;
+            projects.push(project);
+        }
+        Ok(projects)
+    }
+
+    async fn query_page(&self, filter: &ProjectFilter, sort: &[SortKey], after: Option<&PageCursor>, limit: i64) -> HookedResult<Vec<Project>> {
+        let mut pipeline = vec![
+            doc! { "$match": Self::filter_document(filter) },
+            doc! { "$addFields": {
+                SORT_SENT_DATE_FIELD: { "$ifNull": ["$sent_date", 0] },
+                SORT_DIFFICULTY_FIELD: { "$ifNull": ["$difficulty", -1] },
+            } },
+        ];
+        if let Some(cursor) = after {
+            pipeline.push(doc! { "$match": Self::after_cursor_document(sort, cursor) });
+        }
+
+        let mut sort_document = Document::new();
+        for key in sort {
+            let direction = match key.direction {
+                SortDirection::Asc => 1,
+                SortDirection::Desc => -1,
+            };
+            sort_document.insert(Self::sort_field_name(key.field), direction);
+        }
+        sort_document.insert("_id", 1); // Tie breaker, matches the cursor
+        pipeline.push(doc! { "$sort": sort_document });
+        pipeline.push(doc! { "$limit": limit });
+        pipeline.push(doc! { "$project": { SORT_SENT_DATE_FIELD: 0, SORT_DIFFICULTY_FIELD: 0 } });
+
+        let mut cursor = self.collection.aggregate(pipeline, None).await.map_err(HookedError::storage)?;
+        let mut projects = Vec::new();
+
+        while let Some(doc) = cursor.try_next().await.map_err(HookedError::storage)? {
+            projects.push(bson::from_document(doc).map_err(HookedError::storage)?);
+        }
+        Ok(projects)
+    }
+
+    async fn count(&self, filter: &ProjectFilter) -> HookedResult<i64> {
+        let filter = Self::filter_document(filter);
+
+        let count = self.collection.count_documents(filter, None).await.map_err(HookedError::storage)?;
+        count.try_into().map_err(|_| HookedError::Storage("Count exceeds i64 capacity".to_string()))
+    }
+
+    async fn tag_grade_counts(&self, filter: &ProjectFilter, group_by: GroupField) -> HookedResult<Vec<TagGradeCount>> {
+        // $setUnion drops repeated tags within a project before unwinding
+        let tags = match group_by {
+            GroupField::Style => Bson::Document(doc! { "$setUnion": [{ "$ifNull": ["$style", []] }] }),
+            GroupField::Holds => Bson::Document(doc! { "$setUnion": [{ "$ifNull": ["$holds", []] }] }),
+        };
+
+        let pipeline = vec![
+            doc! { "$match": Self::filter_document(filter) },
+            doc! { "$project": { "tag": tags, "grade": 1, "difficulty": 1, "attempts": 1, "sent": { "$eq": ["$is_sent", 1] } } },
+            doc! { "$unwind": "$tag" },
+            doc! { "$group": {
+                "_id": { "tag": "$tag", "grade": "$grade", "sent": "$sent" },
+                "projects": { "$sum": 1 },
+                "attempts": { "$sum": "$attempts" },
+                "difficulty": { "$max": "$difficulty" },
+            } },
+        ];
+
+        let mut cursor = self.collection.aggregate(pipeline, None).await.map_err(HookedError::storage)?;
+        let mut counts = Vec::new();
+
+        while let Some(doc) = cursor.try_next().await.map_err(HookedError::storage)? {
+            let group = doc.get_document("_id").map_err(HookedError::storage)?;
+            let (Ok(tag), Ok(grade)) = (group.get_str("tag"), group.get_str("grade")) else {
+                continue; // Non-string tags or grades can't be reported
+            };
+
+            counts.push(TagGradeCount {
+                tag: tag.to_string(),
+                grade: grade.to_string(),
+                difficulty: doc.get_i32("difficulty").ok(),
+                sent: group.get_bool("sent").unwrap_or(false),
+                projects: Self::number_value(&doc, "projects"),
+                attempts: Self::number_value(&doc, "attempts"),
+            });
+        }
+        Ok(counts)
+    }
+
+    async fn backfill_difficulty(&self) -> HookedResult<u64> {
+        let mut updated = 0;
+        for (system, grades) in [(V_SCALE_ID, V_SCALE_GRADES), (FONT_SCALE_ID, FONT_SCALE_GRADES)] {
+            // Grades are one step apart, so the difficulty is the grade's position times the step.
+            // Only boulders (or projects from before disciplines, which are boulders): a route graded "5" isn't Font 5.
+            let filter = doc! {
+                "difficulty": { "$exists": false },
+                "grade": { "$in": grades.to_vec() },
+                "discipline": { "$in": [Bson::Null, Discipline::Boulder.as_str()] },
+            };
+            let set = vec![doc! { "$set": {
+                "grade_system": system,
+                "difficulty": { "$multiply": [{ "$indexOfArray": [grades.to_vec(), "$grade"] }, DIFFICULTY_STEP] },
+            } }];
+
+            let result = self.collection.update_many(filter, set, None).await.map_err(HookedError::storage)?;
+            updated += result.modified_count;
+        }
+        Ok(updated)
+    }
+}
@@ -0,0 +1,74 @@
+// src-tauri/src/reset.rs
+
+// Archives projects when the gym resets their wall.
+// A background task looks for walls whose reset_date has passed, makes every active project on them
+// that was added before the reset inactive (inactive_reason "wall_reset", inactive_since the reset date),
+// then moves the wall to its next scheduled reset.
+
+
+// IMPORTS
+use std::time::Duration;
+
+use chrono::Utc;
+use mongodb::bson::oid::ObjectId;
+
+use crate::database_helper::InactiveReason;
+use crate::error::{HookedError, HookedResult};
+use crate::location_store::SharedLocationStore;
+use crate::project_stats::to_seconds;
+use crate::project_store::{ProjectFilter, SharedProjectStore};
+
+// Default pause between checks (overridable with RESET_CHECK_INTERVAL_SECS)
+const DEFAULT_RESET_CHECK_INTERVAL_SECS: u64 = 60 * 60;
+
+// Applies the resets that are due, of one account or of every account when account_id is None.
+// Returns how many projects were archived.
+pub async fn apply_due_resets(projects: &SharedProjectStore, locations: &SharedLocationStore, account_id: Option<&ObjectId>) -> HookedResult<u64> {
+    let now = Utc::now().timestamp();
+    let mut archived = 0;
+
+    for mut wall in locations.due_walls(account_id, now).await? {
+        let wall_id = wall._id.ok_or_else(|| HookedError::Storage("Stored wall has no id".to_string()))?;
+        let Some(reset_at) = wall.advance_reset(now) else { continue };
+
+        let filter = ProjectFilter {
+            is_active: Some(1),
+            wall_id: Some(wall_id),
+            ..ProjectFilter::for_account(wall.account_id)
+        };
+        for mut project in projects.query(&filter).await? {
+            // Problems set after the reset are still on the wall (date_time can be in ms on older projects)
+            if to_seconds(project.date_time) >= reset_at {
+                continue;
+            }
+            project.is_active = 0;
+            project.inactive_reason = Some(InactiveReason::WallReset);
+            project.inactive_since = Some(reset_at);
+            projects.update(&wall.account_id, project).await?;
+            archived += 1;
+        }
+
+        // Saved last, so a failure part way is retried on the next round
+        locations.save_wall(wall).await?;
+    }
+    Ok(archived)
+}
+
+// Checks for due resets now and then every interval
+pub fn spawn(projects: SharedProjectStore, locations: SharedLocationStore) {
+    let interval = std::env::var("RESET_CHECK_INTERVAL_SECS").ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_RESET_CHECK_INTERVAL_SECS);
+
+    tokio::spawn(async move {
+        loop {
+            match apply_due_resets(&projects, &locations, None).await {
+                Ok(0) => {}
+                Ok(archived) => println!("Wall resets archived {} project(s)", archived),
+                Err(e) => eprintln!("Applying wall resets failed: {}", e),
+            }
+
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+        }
+    });
+}
@@ -0,0 +1,210 @@
+// src-tauri/src/session.rs
+
+// Login sessions: signing and verifying the JWTs handed out by `login`.
+// The signing secret comes from the JWT_SECRET env var, or else from the OS keyring (generated on first run).
+// Commands take a `Session` argument instead of a raw token, so every one of them is authenticated
+// before its body runs and only ever sees the caller's account id.
+
+
+// IMPORTS
+use std::fmt;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use chrono::Utc;
+use mongodb::bson::oid::ObjectId;
+
+// JWT tokens
+use jsonwebtoken::{decode, encode, errors::ErrorKind, DecodingKey, EncodingKey, Header, Validation};
+
+// Random bytes for generated secrets and refresh tokens
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::{Engine as _, engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD}};
+use sha2::{Digest, Sha256};
+
+// Custom command arguments
+use tauri::ipc::{CommandArg, CommandItem, InvokeError};
+use tauri::Runtime;
+
+use crate::error::HookedError;
+
+// Where the generated secret is kept in the OS keyring
+const KEYRING_SERVICE: &str = "hooked";
+const KEYRING_USER: &str = "jwt_secret";
+
+// How long an access token stays valid (overridable with ACCESS_TOKEN_LIFETIME_SECS).
+// Kept short because access tokens can't be revoked, refresh tokens renew them.
+const DEFAULT_ACCESS_TOKEN_LIFETIME_SECS: i64 = 15 * 60;
+
+// How long a device stays logged in without being used
+pub const REFRESH_TOKEN_LIFETIME_DAYS: i64 = 30;
+
+// Loaded once at startup by `init`
+static SIGNING_KEYS: OnceLock<SigningKeys> = OnceLock::new();
+
+struct SigningKeys {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+}
+
+// Represents a JWT claim
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String, // Subject: the account's ObjectId as hex
+    pub iat: i64,    // Issued at (UNIX seconds)
+    pub exp: i64,    // Expiration time (UNIX seconds)
+}
+
+// ERRORS
+
+// Why a token was rejected. Reaches the frontend as an Unauthorized HookedError.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionError {
+    MissingToken,     // The command was invoked without a token
+    Expired,          // Valid signature, but past its expiry
+    InvalidToken,     // Malformed, tampered with or signed with another secret
+    NotInitialized,   // `init` wasn't called before the first command
+    Secret(String),   // The signing secret couldn't be loaded or the token couldn't be signed
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::MissingToken => write!(f, "Unauthorized: missing session token"),
+            SessionError::Expired => write!(f, "Unauthorized: session expired"),
+            SessionError::InvalidToken => write!(f, "Unauthorized: invalid session token"),
+            SessionError::NotInitialized => write!(f, "Session keys are not initialized"),
+            SessionError::Secret(e) => write!(f, "Session secret error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+// SECRET
+
+// Loads the signing secret. Must be called once before the app starts handling commands.
+pub fn init() -> Result<(), SessionError> {
+    let secret = load_secret()?;
+
+    let keys = SigningKeys {
+        encoding: EncodingKey::from_secret(secret.as_bytes()),
+        decoding: DecodingKey::from_secret(secret.as_bytes()),
+    };
+    // A second call keeps the keys from the first one
+    let _ = SIGNING_KEYS.set(keys);
+    Ok(())
+}
+
+// JWT_SECRET wins, then the keyring. Without either, a new secret is generated and stored in the keyring.
+fn load_secret() -> Result<String, SessionError> {
+    if let Ok(secret) = std::env::var("JWT_SECRET") {
+        if !secret.trim().is_empty() {
+            return Ok(secret);
+        }
+    }
+
+    let entry = match keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER) {
+        Ok(entry) => entry,
+        Err(e) => {
+            // No keyring on this machine: tokens only stay valid until the app restarts
+            eprintln!("OS keyring unavailable ({}), using a temporary session secret", e);
+            return Ok(generate_secret());
+        }
+    };
+
+    match entry.get_password() {
+        Ok(secret) => Ok(secret),
+        Err(keyring::Error::NoEntry) => {
+            let secret = generate_secret();
+            if let Err(e) = entry.set_password(&secret) {
+                eprintln!("Could not store the session secret in the OS keyring ({}), using a temporary one", e);
+            }
+            Ok(secret)
+        }
+        Err(e) => Err(SessionError::Secret(e.to_string())),
+    }
+}
+
+// 32 random bytes, base64 encoded so it can be stored as a keyring password
+fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    BASE64.encode(bytes)
+}
+
+fn keys() -> Result<&'static SigningKeys, SessionError> {
+    SIGNING_KEYS.get().ok_or(SessionError::NotInitialized)
+}
+
+// TOKENS
+
+fn access_token_lifetime() -> i64 {
+    std::env::var("ACCESS_TOKEN_LIFETIME_SECS").ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_ACCESS_TOKEN_LIFETIME_SECS)
+}
+
+// Signs a new access token for an account, returning it with its expiry (UNIX seconds)
+pub fn issue_token(account_id: &ObjectId) -> Result<(String, i64), SessionError> {
+    let now = Utc::now().timestamp();
+    let claims = Claims { sub: account_id.to_hex(), iat: now, exp: now + access_token_lifetime() };
+
+    let token = encode(&Header::default(), &claims, &keys()?.encoding).map_err(|e| SessionError::Secret(e.to_string()))?;
+    Ok((token, claims.exp))
+}
+
+// Checks the signature and expiry of a token and returns its claims
+pub fn verify_token(token: &str) -> Result<Claims, SessionError> {
+    let data = decode::<Claims>(token, &keys()?.decoding, &Validation::default()).map_err(|e| match e.kind() {
+        ErrorKind::ExpiredSignature => SessionError::Expired,
+        _ => SessionError::InvalidToken,
+    })?;
+    Ok(data.claims)
+}
+
+// Returns the account a token was issued for
+pub fn authenticate(token: &str) -> Result<ObjectId, SessionError> {
+    if token.trim().is_empty() {
+        return Err(SessionError::MissingToken);
+    }
+    let claims = verify_token(token)?;
+    ObjectId::parse_str(&claims.sub).map_err(|_| SessionError::InvalidToken)
+}
+
+// OPAQUE TOKENS (refresh tokens, password reset tokens)
+
+// A new opaque token. Unlike access tokens these carry no data, they only point at a stored record.
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+// What gets stored instead of the token itself.
+// The token is 256 random bits, so a fast hash is enough (no salt or Argon2 needed) and it can be looked up directly.
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+// COMMAND EXTRACTOR
+
+// The authenticated caller of a command.
+// Declaring `session: Session` on a command reads the `token` argument sent by the frontend
+// and rejects the call before the command runs if the token isn't valid.
+#[derive(Debug, Clone, Copy)]
+pub struct Session {
+    pub account_id: ObjectId,
+}
+
+impl<'de, R: Runtime> CommandArg<'de, R> for Session {
+    fn from_command(command: CommandItem<'de, R>) -> Result<Self, InvokeError> {
+        // Deserialize the `token` argument the same way a `token: String` parameter would be
+        let token = Option::<String>::from_command(CommandItem { key: "token", ..command })?
+            .ok_or(SessionError::MissingToken)
+            .map_err(|e| InvokeError::from(HookedError::from(e)))?;
+
+        let account_id = authenticate(&token).map_err(|e| InvokeError::from(HookedError::from(e)))?;
+        Ok(Session { account_id })
+    }
+}
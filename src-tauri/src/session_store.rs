@@ -0,0 +1,90 @@
+// src-tauri/src/session_store.rs
+
+// Storage abstraction for login sessions (one per logged-in device).
+// Only hashes of refresh tokens are stored, so a leaked database can't be used to log in.
+
+
+// IMPORTS
+use async_trait::async_trait;
+use std::sync::Arc;
+
+// MongoDB & BSON
+use mongodb::{Client, Collection, bson::{self, doc, oid::ObjectId}};
+use chrono::{DateTime, Utc};
+
+use crate::database_helper::DeviceSession;
+use crate::project_store::DATABASE_NAME;
+use crate::error::{HookedError, HookedResult};
+
+pub const SESSIONS_COLLECTION: &str = "sessions";
+
+// The store shared with DatabaseHelper
+pub type SharedSessionStore = Arc<dyn SessionStore>;
+
+// STORE TRAIT
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    // Inserts a new session and returns its generated id
+    async fn insert(&self, session: DeviceSession) -> HookedResult<ObjectId>;
+    // Finds the session whose current or previous refresh token has this hash
+    async fn find_by_token_hash(&self, token_hash: &str) -> HookedResult<Option<DeviceSession>>;
+    // Replaces the refresh token of a session, only if `current_hash` is still the stored one.
+    // Returns false when another refresh rotated it first.
+    async fn rotate(&self, id: &ObjectId, current_hash: &str, new_hash: &str, expires_at: DateTime<Utc>) -> HookedResult<bool>;
+    // Ends one session
+    async fn delete(&self, id: &ObjectId) -> HookedResult<()>;
+    // Ends every session of an account, returning how many there were
+    async fn delete_for_account(&self, account_id: &ObjectId) -> HookedResult<u64>;
+}
+
+// MONGODB IMPLEMENTATION
+pub struct MongoSessionStore {
+    collection: Collection<DeviceSession>,
+}
+
+impl MongoSessionStore {
+    pub fn new(client: &Client) -> Self {
+        let collection = client.database(DATABASE_NAME).collection::<DeviceSession>(SESSIONS_COLLECTION);
+        MongoSessionStore { collection }
+    }
+}
+
+#[async_trait]
+impl SessionStore for MongoSessionStore {
+    async fn insert(&self, session: DeviceSession) -> HookedResult<ObjectId> {
+        let insert_result = self.collection.insert_one(session, None).await.map_err(HookedError::storage)?;
+        insert_result.inserted_id.as_object_id().ok_or_else(|| HookedError::Storage("Inserted id is not an ObjectId".to_string()))
+    }
+
+    async fn find_by_token_hash(&self, token_hash: &str) -> HookedResult<Option<DeviceSession>> {
+        let filter = doc! { "$or": [ { "refresh_token_hash": token_hash }, { "previous_token_hash": token_hash } ] };
+        self.collection.find_one(filter, None).await.map_err(HookedError::storage)
+    }
+
+    async fn rotate(&self, id: &ObjectId, current_hash: &str, new_hash: &str, expires_at: DateTime<Utc>) -> HookedResult<bool> {
+        // Dates are stored the same way serde writes them on insert
+        let now = bson::to_bson(&Utc::now()).map_err(HookedError::storage)?;
+        let expires_at = bson::to_bson(&expires_at).map_err(HookedError::storage)?;
+        let update = doc! { "$set": {
+            "refresh_token_hash": new_hash,
+            "previous_token_hash": current_hash,
+            "last_used_at": now,
+            "expires_at": expires_at,
+        } };
+
+        // Matching on the current hash makes two refreshes with the same token race safely
+        let filter = doc! { "_id": id, "refresh_token_hash": current_hash };
+        let result = self.collection.update_one(filter, update, None).await.map_err(HookedError::storage)?;
+        Ok(result.matched_count == 1)
+    }
+
+    async fn delete(&self, id: &ObjectId) -> HookedResult<()> {
+        self.collection.delete_one(doc! { "_id": id }, None).await.map_err(HookedError::storage)?;
+        Ok(())
+    }
+
+    async fn delete_for_account(&self, account_id: &ObjectId) -> HookedResult<u64> {
+        let result = self.collection.delete_many(doc! { "account_id": account_id }, None).await.map_err(HookedError::storage)?;
+        Ok(result.deleted_count)
+    }
+}
@@ -0,0 +1,1782 @@
+// src-tauri/src/sqlite_store.rs
+
+// Local SQLite storage for projects, annotations, attempts, climbing sessions, locations, image uploads, accounts and login sessions.
+// Lets the app run without any network connection. The schema lives in src-tauri/migrations
+// and is applied on every startup (already-applied migrations are skipped).
+
+
+// IMPORTS
+use async_trait::async_trait;
+use std::path::Path;
+use std::str::FromStr;
+
+// SQLite access
+use sqlx::{QueryBuilder, Row, Sqlite, SqlitePool};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+
+// Ids stay ObjectIds so the frontend sees the same shape on every backend
+use mongodb::bson::oid::ObjectId;
+
+use crate::account_store::AccountStore;
+use crate::attempt_store::AttemptStore;
+use crate::climbing_session_store::{already_running, ClimbingSessionStore};
+use crate::location::{Environment, GeoPoint, Location, Wall};
+use crate::location_store::LocationStore;
+use crate::database_helper::{Account, Attempt, AttemptOutcome, ClimbingSession, Coordinate, DeviceSession, Discipline, HighPoint, ImageUpload, InactiveReason, PasswordReset, Project, SendStyle};
+use crate::grade::{CustomScale, DIFFICULTY_STEP, FONT_SCALE_GRADES, FONT_SCALE_ID, V_SCALE_GRADES, V_SCALE_ID};
+use crate::grade_store::GradeScaleStore;
+use crate::project_store::{GroupField, ProjectFilter, ProjectStore, TagGradeCount, TagMatch};
+use crate::project_query::{PageCursor, SortDirection, SortField, SortKey};
+use crate::session_store::SessionStore;
+use crate::upload_store::UploadStore;
+use crate::error::{HookedError, HookedResult};
+use crate::sync::{now_millis, stamp_changes};
+
+// Columns selected whenever a full project row is loaded
+const PROJECT_COLUMNS: &str = "id, account_id, date_time, sent_date, image_path, image_public_id, image_variants, is_sent, attempts, discipline, send_style, grade, location_id, wall_id, grade_system, difficulty, is_active, inactive_reason, inactive_since, style, holds, updated_at, field_versions";
+
+// What a queued local change has to do remotely
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PendingOperation {
+    Upsert,
+    Delete,
+}
+
+impl PendingOperation {
+    fn as_str(self) -> &'static str {
+        match self {
+            PendingOperation::Upsert => "upsert",
+            PendingOperation::Delete => "delete",
+        }
+    }
+}
+
+// The latest queued change of one project
+#[derive(Debug, Clone)]
+pub struct PendingChange {
+    pub queue_id: i64, // Newest sync_queue row for this project, older rows are cleared with it
+    pub project_id: ObjectId,
+    pub operation: PendingOperation,
+    pub queued_at: i64,
+}
+
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteStore {
+    // Opens (or creates) the database file and brings the schema up to date
+    pub async fn open(path: &Path) -> HookedResult<Self> {
+        // Make sure the folder exists, SQLite only creates the file itself
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| HookedError::Storage(format!("Could not create database folder: {}", e)))?;
+        }
+
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .foreign_keys(true); // Needed for ON DELETE CASCADE on coordinates
+
+        let pool = SqlitePoolOptions::new()
+            .connect_with(options)
+            .await
+            .map_err(|e| HookedError::Storage(format!("Could not open SQLite database: {}", e)))?;
+
+        // Apply any migration from src-tauri/migrations that hasn't run yet
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .map_err(|e| HookedError::Storage(format!("SQLite migration failed: {}", e)))?;
+
+        Ok(SqliteStore { pool })
+    }
+
+    // Appends the WHERE clause for a ProjectFilter to a query
+    fn push_filter(builder: &mut QueryBuilder<'_, Sqlite>, filter: &ProjectFilter) {
+        builder.push(" WHERE 1 = 1");
+
+        if let Some(account_id) = filter.account_id {
+            builder.push(" AND projects.account_id = ").push_bind(account_id.to_hex());
+        }
+        if let Some(is_active) = filter.is_active {
+            builder.push(" AND projects.is_active = ").push_bind(is_active);
+        }
+        if let Some(is_sent) = filter.is_sent {
+            builder.push(" AND projects.is_sent = ").push_bind(is_sent);
+        }
+        if let Some(discipline) = filter.discipline {
+            builder.push(" AND projects.discipline = ").push_bind(discipline.as_str());
+        }
+        if let Some(location_id) = filter.location_id {
+            builder.push(" AND projects.location_id = ").push_bind(location_id.to_hex());
+        }
+        if let Some(wall_id) = filter.wall_id {
+            builder.push(" AND projects.wall_id = ").push_bind(wall_id.to_hex());
+        }
+        if let Some(grade_system) = &filter.grade_system {
+            builder.push(" AND projects.grade_system = ").push_bind(grade_system.clone());
+        }
+        if let Some(key) = &filter.image_key {
+            builder.push(" AND (projects.image_public_id = ").push_bind(key.clone());
+            builder.push(" OR EXISTS (SELECT 1 FROM json_each(projects.image_variants) AS variant WHERE json_extract(variant.value, '$.public_id') = ")
+                .push_bind(key.clone());
+            builder.push("))");
+        }
+        if let Some(path) = &filter.image_path {
+            builder.push(" AND projects.image_path = ").push_bind(path.clone());
+        }
+
+        // Empty lists are treated the same as no filter
+        if let Some(grades) = filter.grades.as_ref().filter(|list| !list.is_empty()) {
+            builder.push(" AND projects.grade IN (");
+            let mut separated = builder.separated(", ");
+            for grade in grades {
+                separated.push_bind(grade.clone());
+            }
+            builder.push(")");
+        }
+        if let Some(styles) = filter.styles.as_ref().filter(|list| !list.is_empty()) {
+            Self::push_tag_filter(builder, "style", styles, filter.styles_match);
+        }
+        if let Some(exclude) = filter.exclude_styles.as_ref().filter(|list| !list.is_empty()) {
+            Self::push_tag_exclusion(builder, "style", exclude);
+        }
+        if let Some(holds) = filter.holds.as_ref().filter(|list| !list.is_empty()) {
+            Self::push_tag_filter(builder, "holds", holds, filter.holds_match);
+        }
+        if let Some(exclude) = filter.exclude_holds.as_ref().filter(|list| !list.is_empty()) {
+            Self::push_tag_exclusion(builder, "holds", exclude);
+        }
+
+        Self::push_range(builder, "projects.date_time", filter.date_from, filter.date_to);
+        Self::push_range(builder, "projects.sent_date", filter.sent_from, filter.sent_to);
+        Self::push_range(builder, "projects.attempts", filter.min_attempts.map(i64::from), filter.max_attempts.map(i64::from));
+    }
+
+    // Inclusive bounds on a column, for whichever bounds are set
+    fn push_range(builder: &mut QueryBuilder<'_, Sqlite>, column: &str, from: Option<i64>, to: Option<i64>) {
+        if let Some(from) = from {
+            builder.push(format!(" AND {} >= ", column)).push_bind(from);
+        }
+        if let Some(to) = to {
+            builder.push(format!(" AND {} <= ", column)).push_bind(to);
+        }
+    }
+
+    // Column expression a sort key reads. Unsent projects sort as if sent_date were 0, unknown grades as difficulty -1.
+    fn sort_column(field: SortField) -> &'static str {
+        match field {
+            SortField::DateTime => "projects.date_time",
+            SortField::SentDate => "COALESCE(projects.sent_date, 0)",
+            SortField::Grade => "COALESCE(projects.difficulty, -1)",
+            SortField::Attempts => "projects.attempts",
+        }
+    }
+
+    // Matches rows that come after the cursor in sort order:
+    // k1 past v1, or k1 = v1 and k2 past v2, ..., or all equal and a greater id
+    fn push_after_cursor(builder: &mut QueryBuilder<'_, Sqlite>, sort: &[SortKey], cursor: &PageCursor) {
+        builder.push(" AND (");
+        for branch in 0..=sort.len() {
+            if branch > 0 {
+                builder.push(" OR ");
+            }
+            builder.push("(1 = 1");
+
+            // Every earlier key equal
+            for (key, value) in sort.iter().zip(&cursor.values).take(branch) {
+                builder.push(format!(" AND {} = ", Self::sort_column(key.field))).push_bind(*value);
+            }
+
+            // This key past the cursor, or the id for the last branch
+            match sort.get(branch).zip(cursor.values.get(branch)) {
+                Some((key, value)) => {
+                    let operator = match key.direction {
+                        SortDirection::Asc => ">",
+                        SortDirection::Desc => "<",
+                    };
+                    builder.push(format!(" AND {} {} ", Self::sort_column(key.field), operator)).push_bind(*value);
+                }
+                None => {
+                    builder.push(" AND projects.id > ").push_bind(cursor.id.to_hex());
+                }
+            }
+            builder.push(")");
+        }
+        builder.push(")");
+    }
+
+    // Matches projects whose JSON tag array contains any (or all) of the given tags
+    fn push_tag_filter(builder: &mut QueryBuilder<'_, Sqlite>, column: &str, tags: &[String], matching: TagMatch) {
+        match matching {
+            TagMatch::Any => {
+                builder.push(format!(" AND EXISTS (SELECT 1 FROM json_each(projects.{}) AS tag WHERE tag.value IN (", column));
+                Self::push_tag_list(builder, tags);
+                builder.push("))");
+            }
+            // Every wanted tag is present when the project has as many distinct ones among them as were asked for
+            TagMatch::All => {
+                let mut wanted: Vec<&String> = tags.iter().collect();
+                wanted.sort();
+                wanted.dedup();
+
+                builder.push(format!(" AND (SELECT COUNT(DISTINCT tag.value) FROM json_each(projects.{}) AS tag WHERE tag.value IN (", column));
+                Self::push_tag_list(builder, tags);
+                builder.push(")) = ").push_bind(wanted.len() as i64);
+            }
+        }
+    }
+
+    // Projects with none of the tags in the JSON array column (projects without tags included)
+    fn push_tag_exclusion(builder: &mut QueryBuilder<'_, Sqlite>, column: &str, tags: &[String]) {
+        builder.push(format!(" AND NOT EXISTS (SELECT 1 FROM json_each(projects.{}) AS tag WHERE tag.value IN (", column));
+        Self::push_tag_list(builder, tags);
+        builder.push("))");
+    }
+
+    fn push_tag_list(builder: &mut QueryBuilder<'_, Sqlite>, tags: &[String]) {
+        let mut separated = builder.separated(", ");
+        for tag in tags {
+            separated.push_bind(tag.clone());
+        }
+    }
+
+    // Builds a Project from a projects row, loading its coordinates separately
+    async fn project_from_row(&self, row: &sqlx::sqlite::SqliteRow) -> HookedResult<Project> {
+        let id: String = row.try_get("id").map_err(HookedError::storage)?;
+        let account_id: String = row.try_get("account_id").map_err(HookedError::storage)?;
+        let style: Option<String> = row.try_get("style").map_err(HookedError::storage)?;
+        let holds: Option<String> = row.try_get("holds").map_err(HookedError::storage)?;
+        let field_versions: String = row.try_get("field_versions").map_err(HookedError::storage)?;
+        let discipline: String = row.try_get("discipline").map_err(HookedError::storage)?;
+        let send_style: Option<String> = row.try_get("send_style").map_err(HookedError::storage)?;
+        let location_id: Option<String> = row.try_get("location_id").map_err(HookedError::storage)?;
+        let wall_id: Option<String> = row.try_get("wall_id").map_err(HookedError::storage)?;
+        let inactive_reason: Option<String> = row.try_get("inactive_reason").map_err(HookedError::storage)?;
+        let image_variants: String = row.try_get("image_variants").map_err(HookedError::storage)?;
+
+        let object_id = ObjectId::parse_str(&id).map_err(HookedError::storage)?;
+
+        Ok(Project {
+            _id: Some(object_id),
+            account_id: ObjectId::parse_str(&account_id).map_err(HookedError::storage)?,
+            date_time: row.try_get("date_time").map_err(HookedError::storage)?,
+            sent_date: row.try_get("sent_date").map_err(HookedError::storage)?,
+            image_path: row.try_get("image_path").map_err(HookedError::storage)?,
+            image_public_id: row.try_get("image_public_id").map_err(HookedError::storage)?,
+            image_variants: serde_json::from_str(&image_variants).map_err(HookedError::storage)?,
+            is_sent: row.try_get("is_sent").map_err(HookedError::storage)?,
+            attempts: row.try_get("attempts").map_err(HookedError::storage)?,
+            discipline: Discipline::parse(&discipline).unwrap_or_default(),
+            send_style: send_style.as_deref().and_then(SendStyle::parse),
+            grade: row.try_get("grade").map_err(HookedError::storage)?,
+            location_id: parse_optional_id(location_id)?,
+            wall_id: parse_optional_id(wall_id)?,
+            grade_system: row.try_get("grade_system").map_err(HookedError::storage)?,
+            difficulty: row.try_get("difficulty").map_err(HookedError::storage)?,
+            is_active: row.try_get("is_active").map_err(HookedError::storage)?,
+            inactive_reason: inactive_reason.as_deref().and_then(InactiveReason::parse),
+            inactive_since: row.try_get("inactive_since").map_err(HookedError::storage)?,
+            coordinates: self.load_coordinates(&id).await?,
+            style: parse_tags(style)?,
+            holds: parse_tags(holds)?,
+            updated_at: row.try_get("updated_at").map_err(HookedError::storage)?,
+            field_versions: serde_json::from_str(&field_versions).map_err(HookedError::storage)?,
+        })
+    }
+
+    async fn load_coordinates(&self, project_id: &str) -> HookedResult<Vec<Coordinate>> {
+        let rows = sqlx::query("SELECT lat, lng, note FROM coordinates WHERE project_id = ? ORDER BY position")
+            .bind(project_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        rows.iter().map(|row| {
+            let note: String = row.try_get("note").map_err(HookedError::storage)?;
+            Ok(Coordinate {
+                lat: row.try_get("lat").map_err(HookedError::storage)?,
+                lng: row.try_get("lng").map_err(HookedError::storage)?,
+                note: serde_json::from_str(&note).map_err(HookedError::storage)?,
+            })
+        }).collect()
+    }
+
+    // Replaces every coordinate of a project inside an open transaction
+    async fn replace_coordinates(
+        tx: &mut sqlx::Transaction<'_, Sqlite>,
+        project_id: &str,
+        coordinates: &[Coordinate],
+    ) -> HookedResult<()> {
+        sqlx::query("DELETE FROM coordinates WHERE project_id = ?")
+            .bind(project_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(HookedError::storage)?;
+
+        for (position, coordinate) in coordinates.iter().enumerate() {
+            let note = serde_json::to_string(&coordinate.note).map_err(HookedError::storage)?;
+            sqlx::query("INSERT INTO coordinates (project_id, position, lat, lng, note) VALUES (?, ?, ?, ?, ?)")
+                .bind(project_id)
+                .bind(position as i64)
+                .bind(coordinate.lat)
+                .bind(coordinate.lng)
+                .bind(note)
+                .execute(&mut **tx)
+                .await
+                .map_err(HookedError::storage)?;
+        }
+        Ok(())
+    }
+
+    // Inserts or overwrites a project row and its coordinates inside an open transaction
+    async fn write_project(tx: &mut sqlx::Transaction<'_, Sqlite>, project: &Project) -> HookedResult<()> {
+        let id = project._id.ok_or_else(|| HookedError::Validation("Project ID is required".to_string()))?.to_hex();
+        let field_versions = serde_json::to_string(&project.field_versions).map_err(HookedError::storage)?;
+
+        sqlx::query(
+            "INSERT INTO projects (id, account_id, date_time, sent_date, image_path, image_public_id, image_variants, is_sent, attempts, discipline, send_style, grade, location_id, wall_id, grade_system, difficulty, is_active, inactive_reason, inactive_since, style, holds, updated_at, field_versions)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT (id) DO UPDATE SET account_id = excluded.account_id, date_time = excluded.date_time,
+             sent_date = excluded.sent_date, image_path = excluded.image_path, image_public_id = excluded.image_public_id,
+             image_variants = excluded.image_variants, is_sent = excluded.is_sent,
+             attempts = excluded.attempts, discipline = excluded.discipline, send_style = excluded.send_style, grade = excluded.grade,
+             location_id = excluded.location_id, wall_id = excluded.wall_id, grade_system = excluded.grade_system,
+             difficulty = excluded.difficulty, is_active = excluded.is_active, inactive_reason = excluded.inactive_reason,
+             inactive_since = excluded.inactive_since, style = excluded.style,
+             holds = excluded.holds, updated_at = excluded.updated_at, field_versions = excluded.field_versions",
+        )
+        .bind(&id)
+        .bind(project.account_id.to_hex())
+        .bind(project.date_time)
+        .bind(project.sent_date)
+        .bind(&project.image_path)
+        .bind(&project.image_public_id)
+        .bind(serde_json::to_string(&project.image_variants).map_err(HookedError::storage)?)
+        .bind(project.is_sent)
+        .bind(project.attempts)
+        .bind(project.discipline.as_str())
+        .bind(project.send_style.map(SendStyle::as_str))
+        .bind(&project.grade)
+        .bind(project.location_id.map(|id| id.to_hex()))
+        .bind(project.wall_id.map(|id| id.to_hex()))
+        .bind(&project.grade_system)
+        .bind(project.difficulty)
+        .bind(project.is_active)
+        .bind(project.inactive_reason.map(InactiveReason::as_str))
+        .bind(project.inactive_since)
+        .bind(tags_to_json(&project.style)?)
+        .bind(tags_to_json(&project.holds)?)
+        .bind(project.updated_at)
+        .bind(field_versions)
+        .execute(&mut **tx)
+        .await
+        .map_err(HookedError::storage)?;
+
+        Self::replace_coordinates(tx, &id, &project.coordinates).await
+    }
+
+    // Queues a local change for the sync engine
+    async fn enqueue(
+        tx: &mut sqlx::Transaction<'_, Sqlite>,
+        project_id: &ObjectId,
+        account_id: &ObjectId,
+        operation: PendingOperation,
+    ) -> HookedResult<()> {
+        sqlx::query("INSERT INTO sync_queue (project_id, account_id, operation, queued_at) VALUES (?, ?, ?, ?)")
+            .bind(project_id.to_hex())
+            .bind(account_id.to_hex())
+            .bind(operation.as_str())
+            .bind(now_millis())
+            .execute(&mut **tx)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(())
+    }
+
+    // Stamps the changed fields, writes the project and queues it for sync
+    async fn save_local(&self, previous: Option<&Project>, mut project: Project) -> HookedResult<ObjectId> {
+        let object_id = *project._id.get_or_insert_with(ObjectId::new);
+        stamp_changes(previous, &mut project, now_millis())?;
+
+        let mut tx = self.pool.begin().await.map_err(HookedError::storage)?;
+        Self::write_project(&mut tx, &project).await?;
+        Self::enqueue(&mut tx, &object_id, &project.account_id, PendingOperation::Upsert).await?;
+        tx.commit().await.map_err(HookedError::storage)?;
+
+        Ok(object_id)
+    }
+
+    // SYNC SUPPORT
+    // Used by the sync engine. These writes are not queued again.
+
+    // Every account with local data
+    pub async fn known_account_ids(&self) -> HookedResult<Vec<ObjectId>> {
+        let rows = sqlx::query("SELECT id FROM accounts UNION SELECT DISTINCT account_id FROM projects")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        rows.iter().map(|row| {
+            let id: String = row.try_get(0).map_err(HookedError::storage)?;
+            ObjectId::parse_str(&id).map_err(HookedError::storage)
+        }).collect()
+    }
+
+    // The latest queued change per project of an account, oldest first
+    pub async fn pending_changes(&self, account_id: &ObjectId) -> HookedResult<Vec<PendingChange>> {
+        let rows = sqlx::query(
+            "SELECT id, project_id, operation, queued_at FROM sync_queue
+             WHERE id IN (SELECT MAX(id) FROM sync_queue WHERE account_id = ? GROUP BY project_id)
+             ORDER BY id",
+        )
+        .bind(account_id.to_hex())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(HookedError::storage)?;
+
+        rows.iter().map(|row| {
+            let project_id: String = row.try_get("project_id").map_err(HookedError::storage)?;
+            let operation: String = row.try_get("operation").map_err(HookedError::storage)?;
+            Ok(PendingChange {
+                queue_id: row.try_get("id").map_err(HookedError::storage)?,
+                project_id: ObjectId::parse_str(&project_id).map_err(HookedError::storage)?,
+                operation: if operation == "delete" { PendingOperation::Delete } else { PendingOperation::Upsert },
+                queued_at: row.try_get("queued_at").map_err(HookedError::storage)?,
+            })
+        }).collect()
+    }
+
+    // Removes the queued changes of a project up to (and including) queue_id
+    pub async fn clear_changes(&self, project_id: &ObjectId, queue_id: i64) -> HookedResult<()> {
+        sqlx::query("DELETE FROM sync_queue WHERE project_id = ? AND id <= ?")
+            .bind(project_id.to_hex())
+            .bind(queue_id)
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(())
+    }
+
+    pub async fn has_pending_changes(&self, project_id: &ObjectId) -> HookedResult<bool> {
+        let row = sqlx::query("SELECT EXISTS (SELECT 1 FROM sync_queue WHERE project_id = ?)")
+            .bind(project_id.to_hex())
+            .fetch_one(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        row.try_get(0).map_err(HookedError::storage)
+    }
+
+    // When the project was last in sync with MongoDB, None if it never was
+    pub async fn synced_at(&self, project_id: &ObjectId) -> HookedResult<Option<i64>> {
+        let row = sqlx::query("SELECT synced_at FROM projects WHERE id = ?")
+            .bind(project_id.to_hex())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        match row {
+            Some(row) => row.try_get("synced_at").map_err(HookedError::storage),
+            None => Ok(None),
+        }
+    }
+
+    // Stores a project that is now identical to the remote copy
+    pub async fn write_synced(&self, project: &Project, synced_at: i64) -> HookedResult<()> {
+        let id = project._id.ok_or_else(|| HookedError::Validation("Project ID is required".to_string()))?;
+
+        let mut tx = self.pool.begin().await.map_err(HookedError::storage)?;
+        Self::write_project(&mut tx, project).await?;
+        sqlx::query("UPDATE projects SET synced_at = ? WHERE id = ?")
+            .bind(synced_at)
+            .bind(id.to_hex())
+            .execute(&mut *tx)
+            .await
+            .map_err(HookedError::storage)?;
+        tx.commit().await.map_err(HookedError::storage)
+    }
+
+    // Ids of the account's projects that have been synced at least once
+    pub async fn synced_ids(&self, account_id: &ObjectId) -> HookedResult<Vec<ObjectId>> {
+        let rows = sqlx::query("SELECT id FROM projects WHERE account_id = ? AND synced_at IS NOT NULL")
+            .bind(account_id.to_hex())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        rows.iter().map(|row| {
+            let id: String = row.try_get("id").map_err(HookedError::storage)?;
+            ObjectId::parse_str(&id).map_err(HookedError::storage)
+        }).collect()
+    }
+
+    // Deletes a project that was deleted remotely
+    pub async fn delete_synced(&self, project_id: &ObjectId) -> HookedResult<()> {
+        sqlx::query("DELETE FROM projects WHERE id = ?")
+            .bind(project_id.to_hex())
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(())
+    }
+
+    pub async fn last_pulled_at(&self, account_id: &ObjectId) -> HookedResult<Option<i64>> {
+        let row = sqlx::query("SELECT last_pulled_at FROM sync_state WHERE account_id = ?")
+            .bind(account_id.to_hex())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        match row {
+            Some(row) => row.try_get("last_pulled_at").map(Some).map_err(HookedError::storage),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn set_last_pulled_at(&self, account_id: &ObjectId, last_pulled_at: i64) -> HookedResult<()> {
+        sqlx::query(
+            "INSERT INTO sync_state (account_id, last_pulled_at) VALUES (?, ?)
+             ON CONFLICT (account_id) DO UPDATE SET last_pulled_at = excluded.last_pulled_at",
+        )
+        .bind(account_id.to_hex())
+        .bind(last_pulled_at)
+        .execute(&self.pool)
+        .await
+        .map_err(HookedError::storage)?;
+        Ok(())
+    }
+}
+
+// Tags are stored as JSON arrays, NULL meaning "no tags set"
+fn parse_tags(value: Option<String>) -> HookedResult<Option<Vec<String>>> {
+    value.map(|json| serde_json::from_str(&json).map_err(HookedError::storage)).transpose()
+}
+
+// Optional references to other records are stored as hex ids, NULL meaning none
+fn parse_optional_id(value: Option<String>) -> HookedResult<Option<ObjectId>> {
+    value.map(|id| ObjectId::parse_str(&id).map_err(HookedError::storage)).transpose()
+}
+
+fn tags_to_json(tags: &Option<Vec<String>>) -> HookedResult<Option<String>> {
+    tags.as_ref().map(|list| serde_json::to_string(list).map_err(HookedError::storage)).transpose()
+}
+
+#[async_trait]
+impl ProjectStore for SqliteStore {
+    async fn create(&self, project: Project) -> HookedResult<ObjectId> {
+        self.save_local(None, project).await
+    }
+
+    async fn get(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<Project>> {
+        let row = sqlx::query(&format!("SELECT {} FROM projects WHERE id = ? AND account_id = ?", PROJECT_COLUMNS))
+            .bind(id.to_hex())
+            .bind(account_id.to_hex())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        match row {
+            Some(row) => self.project_from_row(&row).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    async fn update(&self, account_id: &ObjectId, mut project: Project) -> HookedResult<()> {
+        let id = project._id.ok_or_else(|| HookedError::Validation("Project ID is required for update".to_string()))?;
+        let existing = self.get(account_id, &id).await?.ok_or_else(|| HookedError::NotFound("Project not found".to_string()))?;
+
+        // The owner can't be changed through an update
+        project.account_id = *account_id;
+
+        // Same rule as the MongoDB store: an empty list keeps the stored coordinates
+        if project.coordinates.is_empty() {
+            project.coordinates = existing.coordinates.clone();
+        }
+
+        self.save_local(Some(&existing), project).await.map(|_| ())
+    }
+
+    async fn update_coordinates(&self, account_id: &ObjectId, id: &ObjectId, coordinates: Vec<Coordinate>) -> HookedResult<()> {
+        let existing = self.get(account_id, id).await?.ok_or_else(|| HookedError::NotFound("Project not found".to_string()))?;
+
+        let mut project = existing.clone();
+        project.coordinates = coordinates;
+        self.save_local(Some(&existing), project).await.map(|_| ())
+    }
+
+    async fn delete(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<Project>> {
+        // Not found or owned by someone else: nothing to delete
+        let Some(project) = self.get(account_id, id).await? else { return Ok(None) };
+
+        let mut tx = self.pool.begin().await.map_err(HookedError::storage)?;
+
+        // Coordinates are removed by ON DELETE CASCADE
+        sqlx::query("DELETE FROM projects WHERE id = ?")
+            .bind(id.to_hex())
+            .execute(&mut *tx)
+            .await
+            .map_err(HookedError::storage)?;
+
+        // Queue the delete so it also happens remotely
+        Self::enqueue(&mut tx, id, account_id, PendingOperation::Delete).await?;
+
+        tx.commit().await.map_err(HookedError::storage)?;
+        Ok(Some(project))
+    }
+
+    async fn query(&self, filter: &ProjectFilter) -> HookedResult<Vec<Project>> {
+        let mut builder = QueryBuilder::<Sqlite>::new(format!("SELECT {} FROM projects", PROJECT_COLUMNS));
+        Self::push_filter(&mut builder, filter);
+
+        let rows = builder.build().fetch_all(&self.pool).await.map_err(HookedError::storage)?;
+
+        let mut projects = Vec::with_capacity(rows.len());
+        for row in &rows {
+            projects.push(self.project_from_row(row).await?);
+        }
+        Ok(projects)
+    }
+
+    async fn query_page(&self, filter: &ProjectFilter, sort: &[SortKey], after: Option<&PageCursor>, limit: i64) -> HookedResult<Vec<Project>> {
+        let mut builder = QueryBuilder::<Sqlite>::new(format!("SELECT {} FROM projects", PROJECT_COLUMNS));
+        Self::push_filter(&mut builder, filter);
+        if let Some(cursor) = after {
+            Self::push_after_cursor(&mut builder, sort, cursor);
+        }
+
+        builder.push(" ORDER BY ");
+        for key in sort {
+            let direction = match key.direction {
+                SortDirection::Asc => "ASC",
+                SortDirection::Desc => "DESC",
+            };
+            builder.push(format!("{} {}, ", Self::sort_column(key.field), direction));
+        }
+        builder.push("projects.id ASC LIMIT ").push_bind(limit); // id breaks ties, matches the cursor
+
+        let rows = builder.build().fetch_all(&self.pool).await.map_err(HookedError::storage)?;
+
+        let mut projects = Vec::with_capacity(rows.len());
+        for row in &rows {
+            projects.push(self.project_from_row(row).await?);
+        }
+        Ok(projects)
+    }
+
+    async fn count(&self, filter: &ProjectFilter) -> HookedResult<i64> {
+        let mut builder = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM projects");
+        Self::push_filter(&mut builder, filter);
+
+        let row = builder.build().fetch_one(&self.pool).await.map_err(HookedError::storage)?;
+        row.try_get(0).map_err(HookedError::storage)
+    }
+
+    async fn tag_grade_counts(&self, filter: &ProjectFilter, group_by: GroupField) -> HookedResult<Vec<TagGradeCount>> {
+        // DISTINCT on (project, tag) so a project with the same tag twice is counted once
+        let select = match group_by {
+            GroupField::Style => "SELECT DISTINCT projects.id, grouped.value AS tag, projects.grade, projects.difficulty, projects.is_sent = 1 AS sent, projects.attempts FROM projects, json_each(projects.style) AS grouped",
+            GroupField::Holds => "SELECT DISTINCT projects.id, grouped.value AS tag, projects.grade, projects.difficulty, projects.is_sent = 1 AS sent, projects.attempts FROM projects, json_each(projects.holds) AS grouped",
+        };
+
+        let mut builder = QueryBuilder::<Sqlite>::new("SELECT tag, grade, MAX(difficulty) AS difficulty, sent, COUNT(*) AS projects, SUM(attempts) AS attempts FROM (");
+        builder.push(select);
+        Self::push_filter(&mut builder, filter);
+        builder.push(") GROUP BY tag, grade, sent");
+
+        let rows = builder.build().fetch_all(&self.pool).await.map_err(HookedError::storage)?;
+
+        rows.iter().map(|row| {
+            Ok(TagGradeCount {
+                tag: row.try_get("tag").map_err(HookedError::storage)?,
+                grade: row.try_get("grade").map_err(HookedError::storage)?,
+                difficulty: row.try_get("difficulty").map_err(HookedError::storage)?,
+                sent: row.try_get("sent").map_err(HookedError::storage)?,
+                projects: row.try_get("projects").map_err(HookedError::storage)?,
+                attempts: row.try_get("attempts").map_err(HookedError::storage)?,
+            })
+        }).collect()
+    }
+
+    async fn backfill_difficulty(&self) -> HookedResult<u64> {
+        let mut updated = 0;
+        for (system, grades) in [(V_SCALE_ID, V_SCALE_GRADES), (FONT_SCALE_ID, FONT_SCALE_GRADES)] {
+            for (index, grade) in grades.iter().enumerate() {
+                // Boulder scales, a route graded "5" isn't Font 5
+                let result = sqlx::query("UPDATE projects SET grade_system = ?, difficulty = ? WHERE difficulty IS NULL AND grade = ? AND discipline = ?")
+                    .bind(system)
+                    .bind(index as i32 * DIFFICULTY_STEP)
+                    .bind(grade)
+                    .bind(Discipline::Boulder.as_str())
+                    .execute(&self.pool)
+                    .await
+                    .map_err(HookedError::storage)?;
+                updated += result.rows_affected();
+            }
+        }
+        Ok(updated)
+    }
+}
+
+// Columns selected whenever an account row is loaded
+const ACCOUNT_COLUMNS: &str = "id, email, hashed_password, created_at, reset_token_hash, reset_expires_at";
+
+fn account_from_row(row: &sqlx::sqlite::SqliteRow) -> HookedResult<Account> {
+    let id: String = row.try_get("id").map_err(HookedError::storage)?;
+    let reset_token_hash: Option<String> = row.try_get("reset_token_hash").map_err(HookedError::storage)?;
+
+    let password_reset = match reset_token_hash {
+        Some(token_hash) => Some(PasswordReset { token_hash, expires_at: parse_date(row, "reset_expires_at")? }),
+        None => None,
+    };
+
+    Ok(Account {
+        _id: Some(ObjectId::parse_str(&id).map_err(HookedError::storage)?),
+        email: row.try_get("email").map_err(HookedError::storage)?,
+        hashed_password: row.try_get("hashed_password").map_err(HookedError::storage)?,
+        created_at: parse_date(row, "created_at")?,
+        password_reset,
+    })
+}
+
+#[async_trait]
+impl AccountStore for SqliteStore {
+    async fn ensure_indexes(&self) -> HookedResult<()> {
+        // Accounts registered before emails were normalised, skipping any that would collide
+        sqlx::query(
+            "UPDATE accounts SET email = lower(trim(email))
+             WHERE email <> lower(trim(email))
+               AND NOT EXISTS (SELECT 1 FROM accounts AS other WHERE other.email = lower(trim(accounts.email)))",
+        )
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        // What is left collides with another account and has to be merged by hand
+        let skipped = sqlx::query("SELECT id, email FROM accounts WHERE email <> lower(trim(email))")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        for row in skipped {
+            let id: String = row.try_get("id").map_err(HookedError::storage)?;
+            let email: String = row.try_get("email").map_err(HookedError::storage)?;
+            eprintln!("Account {} ({}) wasn't normalised, {} is already registered", id, email, email.trim().to_lowercase());
+        }
+
+        sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_accounts_email ON accounts (email)")
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(())
+    }
+
+    async fn insert(&self, account: Account) -> HookedResult<ObjectId> {
+        let object_id = account._id.unwrap_or_default(); // ObjectId::default() generates a new id
+
+        sqlx::query("INSERT INTO accounts (id, email, hashed_password, created_at) VALUES (?, ?, ?, ?)")
+            .bind(object_id.to_hex())
+            .bind(&account.email)
+            .bind(&account.hashed_password)
+            .bind(account.created_at.to_rfc3339())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::Database(ref db_error) if db_error.is_unique_violation() => HookedError::EmailAlreadyRegistered,
+                _ => HookedError::Storage(e.to_string()),
+            })?;
+
+        Ok(object_id)
+    }
+
+    async fn find_by_email(&self, email: &str) -> HookedResult<Option<Account>> {
+        let row = sqlx::query(&format!("SELECT {} FROM accounts WHERE email = ?", ACCOUNT_COLUMNS))
+            .bind(email)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        row.as_ref().map(account_from_row).transpose()
+    }
+
+    async fn find_by_id(&self, account_id: &ObjectId) -> HookedResult<Option<Account>> {
+        let row = sqlx::query(&format!("SELECT {} FROM accounts WHERE id = ?", ACCOUNT_COLUMNS))
+            .bind(account_id.to_hex())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        row.as_ref().map(account_from_row).transpose()
+    }
+
+    async fn update_password(&self, account_id: &ObjectId, hashed_password: &str) -> HookedResult<()> {
+        let result = sqlx::query("UPDATE accounts SET hashed_password = ?, reset_token_hash = NULL, reset_expires_at = NULL WHERE id = ?")
+            .bind(hashed_password)
+            .bind(account_id.to_hex())
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        if result.rows_affected() == 0 {
+            return Err(HookedError::NotFound("Account not found".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, account_id: &ObjectId) -> HookedResult<()> {
+        sqlx::query("DELETE FROM accounts WHERE id = ?")
+            .bind(account_id.to_hex())
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(())
+    }
+
+    async fn set_password_reset(&self, account_id: &ObjectId, reset: Option<PasswordReset>) -> HookedResult<()> {
+        sqlx::query("UPDATE accounts SET reset_token_hash = ?, reset_expires_at = ? WHERE id = ?")
+            .bind(reset.as_ref().map(|reset| reset.token_hash.clone()))
+            .bind(reset.as_ref().map(|reset| reset.expires_at.to_rfc3339()))
+            .bind(account_id.to_hex())
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(())
+    }
+
+    async fn find_by_reset_token(&self, token_hash: &str) -> HookedResult<Option<Account>> {
+        let row = sqlx::query(&format!("SELECT {} FROM accounts WHERE reset_token_hash = ?", ACCOUNT_COLUMNS))
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        row.as_ref().map(account_from_row).transpose()
+    }
+
+    async fn reset_password(&self, account_id: &ObjectId, token_hash: &str, hashed_password: &str) -> HookedResult<bool> {
+        let result = sqlx::query(
+            "UPDATE accounts SET hashed_password = ?, reset_token_hash = NULL, reset_expires_at = NULL
+             WHERE id = ? AND reset_token_hash = ?",
+        )
+            .bind(hashed_password)
+            .bind(account_id.to_hex())
+            .bind(token_hash)
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
+
+// Parses an RFC 3339 date column
+fn parse_date(row: &sqlx::sqlite::SqliteRow, column: &str) -> HookedResult<chrono::DateTime<chrono::Utc>> {
+    let value: String = row.try_get(column).map_err(HookedError::storage)?;
+    chrono::DateTime::from_str(&value).map_err(HookedError::storage)
+}
+
+fn session_from_row(row: &sqlx::sqlite::SqliteRow) -> HookedResult<DeviceSession> {
+    let id: String = row.try_get("id").map_err(HookedError::storage)?;
+    let account_id: String = row.try_get("account_id").map_err(HookedError::storage)?;
+
+    Ok(DeviceSession {
+        _id: Some(ObjectId::parse_str(&id).map_err(HookedError::storage)?),
+        account_id: ObjectId::parse_str(&account_id).map_err(HookedError::storage)?,
+        device: row.try_get("device").map_err(HookedError::storage)?,
+        refresh_token_hash: row.try_get("refresh_token_hash").map_err(HookedError::storage)?,
+        previous_token_hash: row.try_get("previous_token_hash").map_err(HookedError::storage)?,
+        created_at: parse_date(row, "created_at")?,
+        last_used_at: parse_date(row, "last_used_at")?,
+        expires_at: parse_date(row, "expires_at")?,
+    })
+}
+
+#[async_trait]
+impl SessionStore for SqliteStore {
+    async fn insert(&self, session: DeviceSession) -> HookedResult<ObjectId> {
+        let object_id = session._id.unwrap_or_default(); // ObjectId::default() generates a new id
+
+        sqlx::query(
+            "INSERT INTO sessions (id, account_id, device, refresh_token_hash, previous_token_hash, created_at, last_used_at, expires_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+            .bind(object_id.to_hex())
+            .bind(session.account_id.to_hex())
+            .bind(&session.device)
+            .bind(&session.refresh_token_hash)
+            .bind(&session.previous_token_hash)
+            .bind(session.created_at.to_rfc3339())
+            .bind(session.last_used_at.to_rfc3339())
+            .bind(session.expires_at.to_rfc3339())
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        Ok(object_id)
+    }
+
+    async fn find_by_token_hash(&self, token_hash: &str) -> HookedResult<Option<DeviceSession>> {
+        let row = sqlx::query("SELECT * FROM sessions WHERE refresh_token_hash = ? OR previous_token_hash = ?")
+            .bind(token_hash)
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        row.as_ref().map(session_from_row).transpose()
+    }
+
+    async fn rotate(&self, id: &ObjectId, current_hash: &str, new_hash: &str, expires_at: chrono::DateTime<chrono::Utc>) -> HookedResult<bool> {
+        let result = sqlx::query(
+            "UPDATE sessions SET refresh_token_hash = ?, previous_token_hash = ?, last_used_at = ?, expires_at = ?
+             WHERE id = ? AND refresh_token_hash = ?",
+        )
+            .bind(new_hash)
+            .bind(current_hash)
+            .bind(chrono::Utc::now().to_rfc3339())
+            .bind(expires_at.to_rfc3339())
+            .bind(id.to_hex())
+            .bind(current_hash)
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn delete(&self, id: &ObjectId) -> HookedResult<()> {
+        sqlx::query("DELETE FROM sessions WHERE id = ?")
+            .bind(id.to_hex())
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(())
+    }
+
+    async fn delete_for_account(&self, account_id: &ObjectId) -> HookedResult<u64> {
+        let result = sqlx::query("DELETE FROM sessions WHERE account_id = ?")
+            .bind(account_id.to_hex())
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(result.rows_affected())
+    }
+}
+
+fn scale_from_row(row: &sqlx::sqlite::SqliteRow) -> HookedResult<CustomScale> {
+    let id: String = row.try_get("id").map_err(HookedError::storage)?;
+    let account_id: String = row.try_get("account_id").map_err(HookedError::storage)?;
+    let circuits: String = row.try_get("circuits").map_err(HookedError::storage)?;
+
+    Ok(CustomScale {
+        _id: Some(ObjectId::parse_str(&id).map_err(HookedError::storage)?),
+        account_id: ObjectId::parse_str(&account_id).map_err(HookedError::storage)?,
+        name: row.try_get("name").map_err(HookedError::storage)?,
+        circuits: serde_json::from_str(&circuits).map_err(HookedError::storage)?,
+    })
+}
+
+#[async_trait]
+impl GradeScaleStore for SqliteStore {
+    async fn list(&self, account_id: &ObjectId) -> HookedResult<Vec<CustomScale>> {
+        let rows = sqlx::query("SELECT id, account_id, name, circuits FROM grade_scales WHERE account_id = ? ORDER BY name")
+            .bind(account_id.to_hex())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        rows.iter().map(scale_from_row).collect()
+    }
+
+    async fn save(&self, mut scale: CustomScale) -> HookedResult<CustomScale> {
+        let circuits = serde_json::to_string(&scale.circuits).map_err(HookedError::storage)?;
+
+        match scale._id {
+            Some(id) => {
+                // Only replaces a scale the account owns
+                let result = sqlx::query("UPDATE grade_scales SET name = ?, circuits = ? WHERE id = ? AND account_id = ?")
+                    .bind(&scale.name)
+                    .bind(&circuits)
+                    .bind(id.to_hex())
+                    .bind(scale.account_id.to_hex())
+                    .execute(&self.pool)
+                    .await
+                    .map_err(HookedError::storage)?;
+                if result.rows_affected() == 0 {
+                    return Err(HookedError::NotFound("Grade scale not found".to_string()));
+                }
+            }
+            None => {
+                let id = ObjectId::new();
+                sqlx::query("INSERT INTO grade_scales (id, account_id, name, circuits) VALUES (?, ?, ?, ?)")
+                    .bind(id.to_hex())
+                    .bind(scale.account_id.to_hex())
+                    .bind(&scale.name)
+                    .bind(&circuits)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(HookedError::storage)?;
+                scale._id = Some(id);
+            }
+        }
+        Ok(scale)
+    }
+
+    async fn delete(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<bool> {
+        let result = sqlx::query("DELETE FROM grade_scales WHERE id = ? AND account_id = ?")
+            .bind(id.to_hex())
+            .bind(account_id.to_hex())
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn delete_for_account(&self, account_id: &ObjectId) -> HookedResult<u64> {
+        let result = sqlx::query("DELETE FROM grade_scales WHERE account_id = ?")
+            .bind(account_id.to_hex())
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(result.rows_affected())
+    }
+}
+
+// Columns selected whenever an attempt row is loaded
+const ATTEMPT_COLUMNS: &str = "id, account_id, project_id, session_id, timestamp, high_point_annotation, high_point_percent, outcome, notes";
+
+fn attempt_from_row(row: &sqlx::sqlite::SqliteRow) -> HookedResult<Attempt> {
+    let id: String = row.try_get("id").map_err(HookedError::storage)?;
+    let account_id: String = row.try_get("account_id").map_err(HookedError::storage)?;
+    let project_id: String = row.try_get("project_id").map_err(HookedError::storage)?;
+    let session_id: Option<String> = row.try_get("session_id").map_err(HookedError::storage)?;
+    let annotation: Option<i64> = row.try_get("high_point_annotation").map_err(HookedError::storage)?;
+    let percent: Option<f64> = row.try_get("high_point_percent").map_err(HookedError::storage)?;
+    let outcome: String = row.try_get("outcome").map_err(HookedError::storage)?;
+
+    Ok(Attempt {
+        _id: Some(ObjectId::parse_str(&id).map_err(HookedError::storage)?),
+        account_id: ObjectId::parse_str(&account_id).map_err(HookedError::storage)?,
+        project_id: ObjectId::parse_str(&project_id).map_err(HookedError::storage)?,
+        session_id: parse_optional_id(session_id)?,
+        timestamp: row.try_get("timestamp").map_err(HookedError::storage)?,
+        high_point: match (annotation, percent) {
+            (Some(index), _) => Some(HighPoint::Annotation(index as u32)),
+            (None, Some(percent)) => Some(HighPoint::Percent(percent)),
+            (None, None) => None,
+        },
+        outcome: AttemptOutcome::parse(&outcome).ok_or_else(|| HookedError::Storage(format!("Unknown attempt outcome '{}'", outcome)))?,
+        notes: row.try_get("notes").map_err(HookedError::storage)?,
+    })
+}
+
+#[async_trait]
+impl AttemptStore for SqliteStore {
+    async fn insert(&self, attempt: Attempt) -> HookedResult<ObjectId> {
+        let id = ObjectId::new();
+        let (annotation, percent) = match attempt.high_point {
+            Some(HighPoint::Annotation(index)) => (Some(index as i64), None),
+            Some(HighPoint::Percent(percent)) => (None, Some(percent)),
+            None => (None, None),
+        };
+
+        sqlx::query(&format!("INSERT INTO attempts ({}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)", ATTEMPT_COLUMNS))
+            .bind(id.to_hex())
+            .bind(attempt.account_id.to_hex())
+            .bind(attempt.project_id.to_hex())
+            .bind(attempt.session_id.map(|id| id.to_hex()))
+            .bind(attempt.timestamp)
+            .bind(annotation)
+            .bind(percent)
+            .bind(attempt.outcome.as_str())
+            .bind(&attempt.notes)
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(id)
+    }
+
+    async fn list_for_project(&self, account_id: &ObjectId, project_id: &ObjectId) -> HookedResult<Vec<Attempt>> {
+        let rows = sqlx::query(&format!("SELECT {} FROM attempts WHERE account_id = ? AND project_id = ? ORDER BY timestamp, id", ATTEMPT_COLUMNS))
+            .bind(account_id.to_hex())
+            .bind(project_id.to_hex())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        rows.iter().map(attempt_from_row).collect()
+    }
+
+    async fn list_for_sessions(&self, account_id: &ObjectId, session_ids: &[ObjectId]) -> HookedResult<Vec<Attempt>> {
+        if session_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut builder = QueryBuilder::<Sqlite>::new(format!("SELECT {} FROM attempts WHERE account_id = ", ATTEMPT_COLUMNS));
+        builder.push_bind(account_id.to_hex()).push(" AND session_id IN (");
+        let mut ids = builder.separated(", ");
+        for id in session_ids {
+            ids.push_bind(id.to_hex());
+        }
+        builder.push(") ORDER BY timestamp, id");
+
+        let rows = builder.build().fetch_all(&self.pool).await.map_err(HookedError::storage)?;
+        rows.iter().map(attempt_from_row).collect()
+    }
+
+    async fn delete_for_project(&self, account_id: &ObjectId, project_id: &ObjectId) -> HookedResult<u64> {
+        let result = sqlx::query("DELETE FROM attempts WHERE account_id = ? AND project_id = ?")
+            .bind(account_id.to_hex())
+            .bind(project_id.to_hex())
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_for_account(&self, account_id: &ObjectId) -> HookedResult<u64> {
+        let result = sqlx::query("DELETE FROM attempts WHERE account_id = ?")
+            .bind(account_id.to_hex())
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(result.rows_affected())
+    }
+}
+
+// Columns selected whenever an image upload row is loaded
+const IMAGE_UPLOAD_COLUMNS: &str = "id, account_id, public_id, url, uploaded_at";
+
+fn image_upload_from_row(row: &sqlx::sqlite::SqliteRow) -> HookedResult<ImageUpload> {
+    let id: String = row.try_get("id").map_err(HookedError::storage)?;
+    let account_id: String = row.try_get("account_id").map_err(HookedError::storage)?;
+
+    Ok(ImageUpload {
+        _id: Some(ObjectId::parse_str(&id).map_err(HookedError::storage)?),
+        account_id: ObjectId::parse_str(&account_id).map_err(HookedError::storage)?,
+        public_id: row.try_get("public_id").map_err(HookedError::storage)?,
+        url: row.try_get("url").map_err(HookedError::storage)?,
+        uploaded_at: row.try_get("uploaded_at").map_err(HookedError::storage)?,
+    })
+}
+
+#[async_trait]
+impl UploadStore for SqliteStore {
+    async fn insert(&self, upload: ImageUpload) -> HookedResult<()> {
+        sqlx::query(&format!("INSERT INTO image_uploads ({}) VALUES (?, ?, ?, ?, ?)", IMAGE_UPLOAD_COLUMNS))
+            .bind(ObjectId::new().to_hex())
+            .bind(upload.account_id.to_hex())
+            .bind(&upload.public_id)
+            .bind(&upload.url)
+            .bind(upload.uploaded_at)
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(())
+    }
+
+    async fn find(&self, account_id: &ObjectId, public_ids: &[String]) -> HookedResult<Vec<ImageUpload>> {
+        if public_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut builder = QueryBuilder::<Sqlite>::new(format!("SELECT {} FROM image_uploads WHERE account_id = ", IMAGE_UPLOAD_COLUMNS));
+        builder.push_bind(account_id.to_hex()).push(" AND public_id IN (");
+        let mut keys = builder.separated(", ");
+        for public_id in public_ids {
+            keys.push_bind(public_id);
+        }
+        builder.push(")");
+
+        let rows = builder.build().fetch_all(&self.pool).await.map_err(HookedError::storage)?;
+        rows.iter().map(image_upload_from_row).collect()
+    }
+
+    async fn delete(&self, account_id: &ObjectId, public_ids: &[String]) -> HookedResult<u64> {
+        if public_ids.is_empty() {
+            return Ok(0);
+        }
+        let mut builder = QueryBuilder::<Sqlite>::new("DELETE FROM image_uploads WHERE account_id = ");
+        builder.push_bind(account_id.to_hex()).push(" AND public_id IN (");
+        let mut keys = builder.separated(", ");
+        for public_id in public_ids {
+            keys.push_bind(public_id);
+        }
+        builder.push(")");
+
+        let result = builder.build().execute(&self.pool).await.map_err(HookedError::storage)?;
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_for_account(&self, account_id: &ObjectId) -> HookedResult<u64> {
+        let result = sqlx::query("DELETE FROM image_uploads WHERE account_id = ?")
+            .bind(account_id.to_hex())
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(result.rows_affected())
+    }
+}
+
+// Columns selected whenever a climbing session row is loaded
+const CLIMBING_SESSION_COLUMNS: &str = "id, account_id, started_at, ended_at, location, effort, notes";
+
+fn climbing_session_from_row(row: &sqlx::sqlite::SqliteRow) -> HookedResult<ClimbingSession> {
+    let id: String = row.try_get("id").map_err(HookedError::storage)?;
+    let account_id: String = row.try_get("account_id").map_err(HookedError::storage)?;
+    let effort: Option<i64> = row.try_get("effort").map_err(HookedError::storage)?;
+
+    Ok(ClimbingSession {
+        _id: Some(ObjectId::parse_str(&id).map_err(HookedError::storage)?),
+        account_id: ObjectId::parse_str(&account_id).map_err(HookedError::storage)?,
+        started_at: row.try_get("started_at").map_err(HookedError::storage)?,
+        ended_at: row.try_get("ended_at").map_err(HookedError::storage)?,
+        location: row.try_get("location").map_err(HookedError::storage)?,
+        effort: effort.map(|effort| effort as u8),
+        notes: row.try_get("notes").map_err(HookedError::storage)?,
+    })
+}
+
+#[async_trait]
+impl ClimbingSessionStore for SqliteStore {
+    async fn ensure_indexes(&self) -> HookedResult<()> {
+        Ok(()) // Created by migration 0013
+    }
+
+    async fn insert(&self, session: ClimbingSession) -> HookedResult<ObjectId> {
+        let id = ObjectId::new();
+        sqlx::query(&format!("INSERT INTO climbing_sessions ({}) VALUES (?, ?, ?, ?, ?, ?, ?)", CLIMBING_SESSION_COLUMNS))
+            .bind(id.to_hex())
+            .bind(session.account_id.to_hex())
+            .bind(session.started_at)
+            .bind(session.ended_at)
+            .bind(&session.location)
+            .bind(session.effort.map(i64::from))
+            .bind(&session.notes)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::Database(ref db_error) if db_error.is_unique_violation() => already_running(),
+                _ => HookedError::Storage(e.to_string()),
+            })?;
+        Ok(id)
+    }
+
+    async fn find(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<ClimbingSession>> {
+        let row = sqlx::query(&format!("SELECT {} FROM climbing_sessions WHERE id = ? AND account_id = ?", CLIMBING_SESSION_COLUMNS))
+            .bind(id.to_hex())
+            .bind(account_id.to_hex())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        row.as_ref().map(climbing_session_from_row).transpose()
+    }
+
+    async fn running(&self, account_id: &ObjectId) -> HookedResult<Option<ClimbingSession>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM climbing_sessions WHERE account_id = ? AND ended_at IS NULL ORDER BY started_at DESC LIMIT 1",
+            CLIMBING_SESSION_COLUMNS,
+        ))
+            .bind(account_id.to_hex())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        row.as_ref().map(climbing_session_from_row).transpose()
+    }
+
+    async fn update(&self, session: &ClimbingSession) -> HookedResult<()> {
+        let id = session._id.ok_or_else(|| HookedError::Validation("Session ID is required for update".to_string()))?;
+        let result = sqlx::query(
+            "UPDATE climbing_sessions SET started_at = ?, ended_at = ?, location = ?, effort = ?, notes = ? WHERE id = ? AND account_id = ?",
+        )
+            .bind(session.started_at)
+            .bind(session.ended_at)
+            .bind(&session.location)
+            .bind(session.effort.map(i64::from))
+            .bind(&session.notes)
+            .bind(id.to_hex())
+            .bind(session.account_id.to_hex())
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        if result.rows_affected() == 0 {
+            return Err(HookedError::NotFound("Climbing session not found".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn list(&self, account_id: &ObjectId, from: Option<i64>, to: Option<i64>) -> HookedResult<Vec<ClimbingSession>> {
+        let mut builder = QueryBuilder::<Sqlite>::new(format!("SELECT {} FROM climbing_sessions WHERE account_id = ", CLIMBING_SESSION_COLUMNS));
+        builder.push_bind(account_id.to_hex());
+        if let Some(from) = from {
+            builder.push(" AND started_at >= ").push_bind(from);
+        }
+        if let Some(to) = to {
+            builder.push(" AND started_at <= ").push_bind(to);
+        }
+        builder.push(" ORDER BY started_at DESC");
+
+        let rows = builder.build().fetch_all(&self.pool).await.map_err(HookedError::storage)?;
+        rows.iter().map(climbing_session_from_row).collect()
+    }
+
+    async fn delete_for_account(&self, account_id: &ObjectId) -> HookedResult<u64> {
+        let result = sqlx::query("DELETE FROM climbing_sessions WHERE account_id = ?")
+            .bind(account_id.to_hex())
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(result.rows_affected())
+    }
+}
+
+fn location_from_row(row: &sqlx::sqlite::SqliteRow) -> HookedResult<Location> {
+    let id: String = row.try_get("id").map_err(HookedError::storage)?;
+    let account_id: String = row.try_get("account_id").map_err(HookedError::storage)?;
+    let lat: Option<f64> = row.try_get("lat").map_err(HookedError::storage)?;
+    let lng: Option<f64> = row.try_get("lng").map_err(HookedError::storage)?;
+    let environment: String = row.try_get("environment").map_err(HookedError::storage)?;
+
+    Ok(Location {
+        _id: Some(ObjectId::parse_str(&id).map_err(HookedError::storage)?),
+        account_id: ObjectId::parse_str(&account_id).map_err(HookedError::storage)?,
+        name: row.try_get("name").map_err(HookedError::storage)?,
+        coordinates: lat.zip(lng).map(|(lat, lng)| GeoPoint { lat, lng }),
+        environment: Environment::parse(&environment).unwrap_or_default(),
+    })
+}
+
+// Columns selected whenever a wall row is loaded
+const WALL_COLUMNS: &str = "id, account_id, location_id, name, angle, reset_date, reset_interval_days, last_reset_at";
+
+fn wall_from_row(row: &sqlx::sqlite::SqliteRow) -> HookedResult<Wall> {
+    let id: String = row.try_get("id").map_err(HookedError::storage)?;
+    let account_id: String = row.try_get("account_id").map_err(HookedError::storage)?;
+    let location_id: String = row.try_get("location_id").map_err(HookedError::storage)?;
+
+    Ok(Wall {
+        _id: Some(ObjectId::parse_str(&id).map_err(HookedError::storage)?),
+        account_id: ObjectId::parse_str(&account_id).map_err(HookedError::storage)?,
+        location_id: ObjectId::parse_str(&location_id).map_err(HookedError::storage)?,
+        name: row.try_get("name").map_err(HookedError::storage)?,
+        angle: row.try_get("angle").map_err(HookedError::storage)?,
+        reset_date: row.try_get("reset_date").map_err(HookedError::storage)?,
+        reset_interval_days: row.try_get("reset_interval_days").map_err(HookedError::storage)?,
+        last_reset_at: row.try_get("last_reset_at").map_err(HookedError::storage)?,
+    })
+}
+
+#[async_trait]
+impl LocationStore for SqliteStore {
+    async fn list_locations(&self, account_id: &ObjectId) -> HookedResult<Vec<Location>> {
+        let rows = sqlx::query("SELECT id, account_id, name, lat, lng, environment FROM locations WHERE account_id = ? ORDER BY name")
+            .bind(account_id.to_hex())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        rows.iter().map(location_from_row).collect()
+    }
+
+    async fn find_location(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<Location>> {
+        let row = sqlx::query("SELECT id, account_id, name, lat, lng, environment FROM locations WHERE id = ? AND account_id = ?")
+            .bind(id.to_hex())
+            .bind(account_id.to_hex())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        row.as_ref().map(location_from_row).transpose()
+    }
+
+    async fn save_location(&self, mut location: Location) -> HookedResult<Location> {
+        let (lat, lng) = location.coordinates.map(|point| (point.lat, point.lng)).unzip();
+
+        match location._id {
+            Some(id) => {
+                // Only replaces a location the account owns
+                let result = sqlx::query("UPDATE locations SET name = ?, lat = ?, lng = ?, environment = ? WHERE id = ? AND account_id = ?")
+                    .bind(&location.name)
+                    .bind(lat)
+                    .bind(lng)
+                    .bind(location.environment.as_str())
+                    .bind(id.to_hex())
+                    .bind(location.account_id.to_hex())
+                    .execute(&self.pool)
+                    .await
+                    .map_err(HookedError::storage)?;
+                if result.rows_affected() == 0 {
+                    return Err(HookedError::NotFound("Location not found".to_string()));
+                }
+            }
+            None => {
+                let id = ObjectId::new();
+                sqlx::query("INSERT INTO locations (id, account_id, name, lat, lng, environment) VALUES (?, ?, ?, ?, ?, ?)")
+                    .bind(id.to_hex())
+                    .bind(location.account_id.to_hex())
+                    .bind(&location.name)
+                    .bind(lat)
+                    .bind(lng)
+                    .bind(location.environment.as_str())
+                    .execute(&self.pool)
+                    .await
+                    .map_err(HookedError::storage)?;
+                location._id = Some(id);
+            }
+        }
+        Ok(location)
+    }
+
+    async fn delete_location(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<bool> {
+        // Walls are removed by ON DELETE CASCADE
+        let result = sqlx::query("DELETE FROM locations WHERE id = ? AND account_id = ?")
+            .bind(id.to_hex())
+            .bind(account_id.to_hex())
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn list_walls(&self, account_id: &ObjectId, location_id: Option<&ObjectId>) -> HookedResult<Vec<Wall>> {
+        let mut builder = QueryBuilder::<Sqlite>::new(format!("SELECT {} FROM walls WHERE account_id = ", WALL_COLUMNS));
+        builder.push_bind(account_id.to_hex());
+        if let Some(location_id) = location_id {
+            builder.push(" AND location_id = ").push_bind(location_id.to_hex());
+        }
+        builder.push(" ORDER BY name");
+
+        let rows = builder.build().fetch_all(&self.pool).await.map_err(HookedError::storage)?;
+        rows.iter().map(wall_from_row).collect()
+    }
+
+    async fn find_wall(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<Wall>> {
+        let row = sqlx::query(&format!("SELECT {} FROM walls WHERE id = ? AND account_id = ?", WALL_COLUMNS))
+            .bind(id.to_hex())
+            .bind(account_id.to_hex())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        row.as_ref().map(wall_from_row).transpose()
+    }
+
+    async fn save_wall(&self, mut wall: Wall) -> HookedResult<Wall> {
+        match wall._id {
+            Some(id) => {
+                let result = sqlx::query(
+                    "UPDATE walls SET location_id = ?, name = ?, angle = ?, reset_date = ?, reset_interval_days = ?, last_reset_at = ?
+                     WHERE id = ? AND account_id = ?",
+                )
+                    .bind(wall.location_id.to_hex())
+                    .bind(&wall.name)
+                    .bind(wall.angle)
+                    .bind(wall.reset_date)
+                    .bind(wall.reset_interval_days)
+                    .bind(wall.last_reset_at)
+                    .bind(id.to_hex())
+                    .bind(wall.account_id.to_hex())
+                    .execute(&self.pool)
+                    .await
+                    .map_err(HookedError::storage)?;
+                if result.rows_affected() == 0 {
+                    return Err(HookedError::NotFound("Wall not found".to_string()));
+                }
+            }
+            None => {
+                let id = ObjectId::new();
+                sqlx::query(&format!("INSERT INTO walls ({}) VALUES (?, ?, ?, ?, ?, ?, ?, ?)", WALL_COLUMNS))
+                    .bind(id.to_hex())
+                    .bind(wall.account_id.to_hex())
+                    .bind(wall.location_id.to_hex())
+                    .bind(&wall.name)
+                    .bind(wall.angle)
+                    .bind(wall.reset_date)
+                    .bind(wall.reset_interval_days)
+                    .bind(wall.last_reset_at)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(HookedError::storage)?;
+                wall._id = Some(id);
+            }
+        }
+        Ok(wall)
+    }
+
+    async fn due_walls(&self, account_id: Option<&ObjectId>, now: i64) -> HookedResult<Vec<Wall>> {
+        let account_id = account_id.map(|id| id.to_hex());
+        let rows = sqlx::query(&format!("SELECT {} FROM walls WHERE reset_date <= ? AND (? IS NULL OR account_id = ?)", WALL_COLUMNS))
+            .bind(now)
+            .bind(&account_id)
+            .bind(&account_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+
+        rows.iter().map(wall_from_row).collect()
+    }
+
+    async fn delete_wall(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<bool> {
+        let result = sqlx::query("DELETE FROM walls WHERE id = ? AND account_id = ?")
+            .bind(id.to_hex())
+            .bind(account_id.to_hex())
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn delete_for_account(&self, account_id: &ObjectId) -> HookedResult<u64> {
+        let walls = sqlx::query("DELETE FROM walls WHERE account_id = ?")
+            .bind(account_id.to_hex())
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        let locations = sqlx::query("DELETE FROM locations WHERE account_id = ?")
+            .bind(account_id.to_hex())
+            .execute(&self.pool)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(walls.rows_affected() + locations.rows_affected())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::database_helper::{tests::project, DatabaseHelper, ImageVariant, StoredVariant};
+
+    // A fresh database file in the temp folder, removed with the returned guard. Shared with the sync tests.
+    pub(crate) struct TempStore {
+        pub(crate) store: SqliteStore,
+        pub(crate) path: std::path::PathBuf,
+    }
+
+    impl Drop for TempStore {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    pub(crate) async fn temp_store() -> TempStore {
+        let path = std::env::temp_dir().join(format!("hooked-test-{}.db", ObjectId::new().to_hex()));
+        TempStore { store: SqliteStore::open(&path).await.unwrap(), path }
+    }
+
+    // Reads every page of `limit` projects, the way query_projects does
+    async fn all_pages(store: &SqliteStore, filter: &ProjectFilter, sort: &[SortKey], limit: i64) -> Vec<ObjectId> {
+        let mut ids = Vec::new();
+        let mut after: Option<PageCursor> = None;
+        loop {
+            let page = store.query_page(filter, sort, after.as_ref(), limit).await.unwrap();
+            ids.extend(page.iter().filter_map(|project| project._id));
+            match page.last() {
+                Some(last) if page.len() as i64 == limit => after = PageCursor::after(last, sort),
+                _ => return ids,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn due_walls_of_one_account() {
+        let temp = temp_store().await;
+        let (mine, theirs) = (ObjectId::new(), ObjectId::new());
+        for (account_id, reset_date) in [(mine, Some(100)), (mine, Some(500)), (mine, None), (theirs, Some(100))] {
+            let location = temp.store.save_location(Location {
+                _id: None, account_id, name: "Gym".to_string(), coordinates: None, environment: Environment::Indoor,
+            }).await.unwrap();
+            temp.store.save_wall(Wall {
+                _id: None, account_id, location_id: location._id.unwrap(), name: "Cave".to_string(), angle: None,
+                reset_date, reset_interval_days: None, last_reset_at: None,
+            }).await.unwrap();
+        }
+
+        let due = temp.store.due_walls(Some(&mine), 200).await.unwrap();
+        assert_eq!(due.iter().map(|wall| (wall.account_id, wall.reset_date)).collect::<Vec<_>>(), vec![(mine, Some(100))]);
+        assert_eq!(temp.store.due_walls(None, 200).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn counts_a_repeated_tag_once() {
+        let temp = temp_store().await;
+        let account_id = ObjectId::new();
+        let tags = |tags: &[&str]| Some(tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>());
+        temp.store.create(Project { account_id, style: tags(&["crimp", "crimp", "slab"]), is_sent: 1, attempts: 4, ..project() }).await.unwrap();
+        temp.store.create(Project { account_id, style: tags(&["crimp"]), attempts: 2, ..project() }).await.unwrap();
+
+        let mut counts = temp.store.tag_grade_counts(&ProjectFilter::for_account(account_id), GroupField::Style).await.unwrap();
+        counts.sort_by(|a, b| (&a.tag, a.sent).cmp(&(&b.tag, b.sent)));
+        let counts: Vec<(&str, bool, i64, i64)> = counts.iter().map(|count| (count.tag.as_str(), count.sent, count.projects, count.attempts)).collect();
+        assert_eq!(counts, [("crimp", false, 1, 2), ("crimp", true, 1, 4), ("slab", true, 1, 4)]);
+    }
+
+    #[tokio::test]
+    async fn backfills_difficulty_of_boulders_only() {
+        let temp = temp_store().await;
+        let account_id = ObjectId::new();
+        let boulder = temp.store.create(Project { account_id, grade: "5".to_string(), ..project() }).await.unwrap();
+        let route = temp.store.create(Project { account_id, grade: "5".to_string(), discipline: Discipline::Sport, ..project() }).await.unwrap();
+
+        temp.store.backfill_difficulty().await.unwrap();
+        let boulder = temp.store.get(&account_id, &boulder).await.unwrap().unwrap();
+        assert_eq!(boulder.grade_system.as_deref(), Some(FONT_SCALE_ID));
+        assert!(boulder.difficulty.is_some());
+        let route = temp.store.get(&account_id, &route).await.unwrap().unwrap();
+        assert_eq!((route.grade_system, route.difficulty), (None, None));
+    }
+
+    #[tokio::test]
+    async fn counts_projects_of_one_grade_system() {
+        let temp = temp_store().await;
+        let account_id = ObjectId::new();
+        let scale_id = ObjectId::new().to_hex();
+        for grade_system in [Some(scale_id.clone()), Some(V_SCALE_ID.to_string()), None] {
+            temp.store.create(Project { account_id, grade_system, ..project() }).await.unwrap();
+        }
+
+        let filter = ProjectFilter { grade_system: Some(scale_id.clone()), ..ProjectFilter::for_account(account_id) };
+        assert_eq!(temp.store.count(&filter).await.unwrap(), 1);
+        let filter = ProjectFilter { grade_system: Some(scale_id), ..ProjectFilter::for_account(ObjectId::new()) };
+        assert_eq!(temp.store.count(&filter).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn finds_uploads_of_one_account() {
+        let temp = temp_store().await;
+        let (owner, other) = (ObjectId::new(), ObjectId::new());
+        for (account_id, public_id) in [(owner, "a"), (owner, "a_thumb"), (other, "b")] {
+            let upload = ImageUpload { _id: None, account_id, public_id: public_id.to_string(), url: format!("https://example.com/{}.webp", public_id), uploaded_at: 1 };
+            UploadStore::insert(&temp.store, upload).await.unwrap();
+        }
+        let keys = |uploads: Vec<ImageUpload>| {
+            let mut keys: Vec<String> = uploads.into_iter().map(|upload| upload.public_id).collect();
+            keys.sort();
+            keys
+        };
+        let wanted = ["a".to_string(), "a_thumb".to_string(), "b".to_string()];
+
+        assert_eq!(keys(UploadStore::find(&temp.store, &owner, &wanted).await.unwrap()), ["a", "a_thumb"]);
+        assert_eq!(keys(UploadStore::find(&temp.store, &other, &wanted).await.unwrap()), ["b"]);
+        assert!(UploadStore::find(&temp.store, &owner, &[]).await.unwrap().is_empty());
+
+        // Another account's keys are left alone
+        assert_eq!(UploadStore::delete(&temp.store, &owner, &wanted).await.unwrap(), 2);
+        assert_eq!(keys(UploadStore::find(&temp.store, &other, &wanted).await.unwrap()), ["b"]);
+    }
+
+    #[tokio::test]
+    async fn finds_projects_by_image_key() {
+        let temp = temp_store().await;
+        let account_id = ObjectId::new();
+        let variant = StoredVariant {
+            variant: ImageVariant::Thumbnail,
+            url: "https://example.com/a_thumb.webp".to_string(),
+            public_id: "a_thumb.webp".to_string(),
+            width: 320,
+            height: 240,
+        };
+        temp.store.create(Project {
+            account_id,
+            image_path: "https://example.com/a.webp".to_string(),
+            image_public_id: Some("a.webp".to_string()),
+            image_variants: vec![variant],
+            ..project()
+        }).await.unwrap();
+
+        for (owner, key, expected) in [(account_id, "a.webp", 1), (account_id, "a_thumb.webp", 1), (account_id, "b.webp", 0), (ObjectId::new(), "a.webp", 0)] {
+            let filter = ProjectFilter { image_key: Some(key.to_string()), ..ProjectFilter::for_account(owner) };
+            assert_eq!(temp.store.count(&filter).await.unwrap(), expected, "{}", key);
+        }
+    }
+
+    #[tokio::test]
+    async fn pages_through_ties_without_skipping_or_repeating() {
+        let temp = temp_store().await;
+        let account_id = ObjectId::new();
+
+        // Several projects share a grade, some also share a date, so only the id tells them apart
+        let rows = [(Some(40), 100), (Some(40), 100), (Some(40), 90), (None, 100), (Some(20), 100), (Some(40), 100), (None, 100)];
+        let mut expected = Vec::new();
+        for (difficulty, date_time) in rows {
+            let id = temp.store.create(Project { account_id, difficulty, date_time, ..project() }).await.unwrap();
+            expected.push((-(difficulty.unwrap_or(-1) as i64), date_time, id));
+        }
+        expected.sort_by_key(|(difficulty, date_time, id)| (*difficulty, *date_time, id.to_hex()));
+        let expected: Vec<ObjectId> = expected.into_iter().map(|(_, _, id)| id).collect();
+
+        let sort = [
+            SortKey { field: SortField::Grade, direction: SortDirection::Desc },
+            SortKey { field: SortField::DateTime, direction: SortDirection::Asc },
+        ];
+        let filter = ProjectFilter::for_account(account_id);
+        for limit in [1, 2, 3, 10] {
+            assert_eq!(all_pages(&temp.store, &filter, &sort, limit).await, expected, "pages of {}", limit);
+        }
+    }
+
+    #[tokio::test]
+    async fn pages_newest_first() {
+        let temp = temp_store().await;
+        let account_id = ObjectId::new();
+
+        let mut expected = Vec::new();
+        for date_time in [300, 100, 200, 200] {
+            expected.push((-date_time, temp.store.create(Project { account_id, date_time, ..project() }).await.unwrap()));
+        }
+        expected.sort_by_key(|(date_time, id)| (*date_time, id.to_hex()));
+        let expected: Vec<ObjectId> = expected.into_iter().map(|(_, id)| id).collect();
+
+        let sort = [SortKey { field: SortField::DateTime, direction: SortDirection::Desc }];
+        assert_eq!(all_pages(&temp.store, &ProjectFilter::for_account(account_id), &sort, 1).await, expected);
+    }
+
+    // Keeps the emails it is given instead of sending them
+    #[derive(Default)]
+    struct RecordingMailer {
+        sent: std::sync::Mutex<Vec<crate::mailer::Email>>,
+    }
+
+    #[async_trait]
+    impl crate::mailer::Mailer for RecordingMailer {
+        async fn send(&self, email: crate::mailer::Email) -> Result<(), String> {
+            self.sent.lock().unwrap().push(email);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn normalises_emails_without_merging_accounts() {
+        let temp = temp_store().await;
+        let account = |email: &str| Account {
+            _id: None,
+            email: email.to_string(),
+            hashed_password: "hash".to_string(),
+            created_at: chrono::Utc::now(),
+            password_reset: None,
+        };
+        let registered = AccountStore::insert(&temp.store, account("climber@example.com")).await.unwrap();
+        let variant = AccountStore::insert(&temp.store, account(" Climber@Example.com")).await.unwrap();
+        let other = AccountStore::insert(&temp.store, account("Other@Example.com")).await.unwrap();
+
+        AccountStore::ensure_indexes(&temp.store).await.unwrap();
+        assert_eq!(temp.store.find_by_email("climber@example.com").await.unwrap().unwrap()._id, Some(registered));
+        assert_eq!(temp.store.find_by_id(&variant).await.unwrap().unwrap().email, " Climber@Example.com");
+        assert_eq!(temp.store.find_by_email("other@example.com").await.unwrap().unwrap()._id, Some(other));
+    }
+
+    #[tokio::test]
+    async fn password_reset_needs_a_mailer() {
+        let temp = temp_store().await;
+        let store = std::sync::Arc::new(SqliteStore::open(&temp.path).await.unwrap());
+
+        let helper = DatabaseHelper::new(store.clone(), store.clone(), None);
+        helper.create_account("climber@example.com", "abcdefg1").await.unwrap();
+        assert!(matches!(helper.request_password_reset("climber@example.com").await, Err(HookedError::Internal(_))));
+        assert!(store.find_by_email("climber@example.com").await.unwrap().unwrap().password_reset.is_none());
+
+        let mailer = std::sync::Arc::new(RecordingMailer::default());
+        let helper = DatabaseHelper::new(store.clone(), store.clone(), Some(mailer.clone()));
+        helper.request_password_reset("climber@example.com").await.unwrap();
+        let sent = mailer.sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "climber@example.com");
+    }
+}
@@ -0,0 +1,159 @@
+// src-tauri/src/storage.rs
+
+// Picks and connects the storage backend at startup.
+// HOOKED_STORAGE=sqlite|mongodb chooses explicitly. Without it MongoDB is used when MONGODB_URI is set,
+// and local SQLite otherwise. If MongoDB can't be reached (no signal), we fall back to SQLite instead of exiting.
+// While on SQLite with MONGODB_URI set, sync.rs keeps the local data in sync with MongoDB.
+
+
+// IMPORTS
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use mongodb::{Client as MongoClient, bson::doc, options::ClientOptions};
+
+use crate::account_store::{MongoAccountStore, SharedAccountStore};
+use crate::attempt_store::{MongoAttemptStore, SharedAttemptStore};
+use crate::climbing_session_store::{MongoClimbingSessionStore, SharedClimbingSessionStore};
+use crate::grade_store::{MongoGradeScaleStore, SharedGradeScaleStore};
+use crate::location_store::{MongoLocationStore, SharedLocationStore};
+use crate::project_store::{MongoProjectStore, SharedProjectStore};
+use crate::session_store::{MongoSessionStore, SharedSessionStore};
+use crate::upload_store::{MongoUploadStore, SharedUploadStore};
+use crate::sqlite_store::SqliteStore;
+use crate::error::{HookedError, HookedResult};
+
+// File name used inside the app data folder when SQLITE_DATABASE_PATH isn't set
+const SQLITE_FILE_NAME: &str = "hooked.db";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageBackend {
+    Sqlite,
+    MongoDb,
+}
+
+impl StorageBackend {
+    // Reads the backend choice from the environment
+    pub fn from_env() -> Self {
+        match std::env::var("HOOKED_STORAGE").ok().as_deref().map(str::trim) {
+            Some("sqlite") => StorageBackend::Sqlite,
+            Some("mongodb") => StorageBackend::MongoDb,
+            Some(other) => {
+                eprintln!("Unknown HOOKED_STORAGE value '{}', using local SQLite storage", other);
+                StorageBackend::Sqlite
+            }
+            None if std::env::var("MONGODB_URI").is_ok() => StorageBackend::MongoDb,
+            None => StorageBackend::Sqlite,
+        }
+    }
+}
+
+// The stores every command and helper works with, whatever the backend
+pub struct Storage {
+    pub projects: SharedProjectStore,
+    pub accounts: SharedAccountStore,
+    pub sessions: SharedSessionStore,
+    pub grade_scales: SharedGradeScaleStore,
+    pub attempts: SharedAttemptStore,
+    pub climbing_sessions: SharedClimbingSessionStore,
+    pub locations: SharedLocationStore,
+    pub uploads: SharedUploadStore,
+    pub local: Option<Arc<SqliteStore>>, // Set when running on SQLite, so it can be synced with MongoDB
+}
+
+// Connects the configured backend, falling back to SQLite when MongoDB is unavailable
+pub async fn connect() -> HookedResult<Storage> {
+    let storage = match StorageBackend::from_env() {
+        StorageBackend::MongoDb => match connect_mongodb().await {
+            Ok(storage) => storage,
+            Err(e) => {
+                eprintln!("MongoDB unavailable ({}), using local SQLite storage", e);
+                connect_sqlite().await?
+            }
+        },
+        StorageBackend::Sqlite => connect_sqlite().await?,
+    };
+
+    // Duplicate emails stored before the index existed make this fail, the app still starts
+    if let Err(e) = storage.accounts.ensure_indexes().await {
+        eprintln!("Could not create the unique email index, duplicate accounts need to be merged by hand: {}", e);
+    }
+    // Extra running climbing sessions from before the index are ended first, so this only fails when MongoDB does
+    if let Err(e) = storage.climbing_sessions.ensure_indexes().await {
+        eprintln!("Could not create the running climbing session index: {}", e);
+    }
+    // Projects saved before grades were checked get their difficulty, so they sort by grade too
+    if let Err(e) = storage.projects.backfill_difficulty().await {
+        eprintln!("Could not set the difficulty of older projects: {}", e);
+    }
+    Ok(storage)
+}
+
+// Creates a MongoDB client and pings it to make sure the cluster is reachable
+pub async fn mongo_client() -> HookedResult<MongoClient> {
+    // MongoDB connection string.
+    let mongo_uri = std::env::var("MONGODB_URI").map_err(|_| HookedError::Storage("MONGODB_URI is not set".to_string()))?;
+
+    // Parse MongoDB connection options asynchronously.
+    let mut client_options = ClientOptions::parse(mongo_uri).await.map_err(HookedError::storage)?;
+    // Sets the MongoDB API version to V1 for stability.
+    let server_api = mongodb::options::ServerApi::builder().version(mongodb::options::ServerApiVersion::V1).build();
+    client_options.server_api = Some(server_api);
+    // Fail fast when offline instead of waiting for the default 30 seconds
+    client_options.server_selection_timeout = Some(Duration::from_secs(5));
+
+    let client = MongoClient::with_options(client_options).map_err(HookedError::storage)?;
+
+    // Ping MongoDB to verify connection
+    client.database("admin").run_command(doc! {"ping": 1}, None).await.map_err(HookedError::storage)?;
+    Ok(client)
+}
+
+async fn connect_mongodb() -> HookedResult<Storage> {
+    let client = mongo_client().await?;
+    println!("Successfully connected to MongoDB!");
+
+    Ok(Storage {
+        projects: Arc::new(MongoProjectStore::new(&client)),
+        accounts: Arc::new(MongoAccountStore::new(&client)),
+        sessions: Arc::new(MongoSessionStore::new(&client)),
+        grade_scales: Arc::new(MongoGradeScaleStore::new(&client)),
+        attempts: Arc::new(MongoAttemptStore::new(&client)),
+        climbing_sessions: Arc::new(MongoClimbingSessionStore::new(&client)),
+        locations: Arc::new(MongoLocationStore::new(&client)),
+        uploads: Arc::new(MongoUploadStore::new(&client)),
+        local: None,
+    })
+}
+
+async fn connect_sqlite() -> HookedResult<Storage> {
+    let path = sqlite_path();
+    let store = Arc::new(SqliteStore::open(&path).await?);
+    println!("Using local SQLite database at {}", path.display());
+
+    // One SQLite database holds projects, accounts, sessions, grade scales, attempts, climbing sessions, locations and uploads
+    Ok(Storage {
+        projects: store.clone(),
+        accounts: store.clone(),
+        sessions: store.clone(),
+        grade_scales: store.clone(),
+        attempts: store.clone(),
+        climbing_sessions: store.clone(),
+        locations: store.clone(),
+        uploads: store.clone(),
+        local: Some(store),
+    })
+}
+
+// SQLITE_DATABASE_PATH overrides the default location in the user's data folder
+pub fn sqlite_path() -> PathBuf {
+    if let Ok(path) = std::env::var("SQLITE_DATABASE_PATH") {
+        return PathBuf::from(path);
+    }
+
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("hooked")
+        .join(SQLITE_FILE_NAME)
+}
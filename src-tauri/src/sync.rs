@@ -0,0 +1,543 @@
+// src-tauri/src/sync.rs
+
+// Two-way sync between the local SQLite store and the MongoDB `projects` collection.
+// Only projects are synced. Locations, walls, grade scales, attempts and climbing sessions stay on the device
+// they were made on, so a synced project keeps its location_id and wall_id in MongoDB but drops them locally
+// when they point at a location or wall this device doesn't have (see drop_missing_places).
+//
+// Change tracking: every store stamps `updated_at` and a per-field version (UNIX ms) on each write,
+// and the SQLite store also queues the changed project id in `sync_queue`. MongoDB also stamps `pushed_at`
+// with its own clock on every write, and devices pull what was pushed since their last pull by it: updated_at
+// comes from the editing device's clock, and an edit made offline is pushed long after it.
+// Conflict resolution: last writer wins per field. Annotations (coordinates) edited on both sides
+// since the last sync are merged instead, so markers added offline aren't lost.
+
+
+// IMPORTS
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use mongodb::bson::{self, Document, oid::ObjectId};
+
+use crate::database_helper::{Coordinate, Project};
+use crate::error::{HookedError, HookedResult};
+use crate::location_store::LocationStore;
+use crate::project_store::{MongoProjectStore, ProjectStore};
+use crate::sqlite_store::{PendingOperation, SqliteStore};
+use crate::storage;
+
+// Fields that are versioned and synced one by one
+pub const SYNCED_FIELDS: [&str; 20] = [
+    "date_time", "sent_date", "image_path", "image_public_id", "image_variants", "is_sent", "attempts", "discipline",
+    "send_style", "grade", "location_id", "wall_id", "grade_system", "difficulty", "is_active", "inactive_reason",
+    "inactive_since", "coordinates", "style", "holds",
+];
+
+// Default pause between sync rounds (overridable with SYNC_INTERVAL_SECS)
+const DEFAULT_SYNC_INTERVAL_SECS: u64 = 60;
+
+// Current time in milliseconds, the unit used by updated_at and field_versions
+pub fn now_millis() -> i64 {
+    Utc::now().timestamp_millis()
+}
+
+// CHANGE TRACKING
+
+// Stamps updated_at and the version of every field that changed compared to `previous`.
+// A new project (no previous version) gets every field stamped.
+pub fn stamp_changes(previous: Option<&Project>, project: &mut Project, now: i64) -> HookedResult<()> {
+    let new_doc = bson::to_document(project).map_err(HookedError::storage)?;
+
+    match previous {
+        Some(previous) => {
+            let old_doc = bson::to_document(previous).map_err(HookedError::storage)?;
+            project.field_versions = previous.field_versions.clone();
+            project.updated_at = previous.updated_at;
+
+            for field in SYNCED_FIELDS {
+                if old_doc.get(field) != new_doc.get(field) {
+                    project.field_versions.insert(field.to_string(), now);
+                    project.updated_at = Some(now);
+                }
+            }
+        }
+        None => {
+            for field in SYNCED_FIELDS {
+                project.field_versions.insert(field.to_string(), now);
+            }
+            project.updated_at = Some(now);
+        }
+    }
+    Ok(())
+}
+
+// CONFLICT RESOLUTION
+
+// Merges two versions of the same project.
+// `last_synced` is when the local copy was last in sync with the remote one (None if it never was).
+pub fn merge(local: &Project, remote: &Project, last_synced: Option<i64>) -> HookedResult<Project> {
+    let local_doc = bson::to_document(local).map_err(HookedError::storage)?;
+    let mut merged_doc: Document = bson::to_document(remote).map_err(HookedError::storage)?;
+    let mut merged_versions = remote.field_versions.clone();
+
+    for field in SYNCED_FIELDS {
+        let local_version = local.field_versions.get(field).copied().unwrap_or(0);
+        let remote_version = remote.field_versions.get(field).copied().unwrap_or(0);
+
+        // Ties go to the remote copy so every device converges on the same value
+        if local_version > remote_version {
+            // Fields cleared locally aren't serialized, they have to be cleared in the merge too
+            match local_doc.get(field) {
+                Some(value) => merged_doc.insert(field, value.clone()),
+                None => merged_doc.remove(field),
+            };
+            merged_versions.insert(field.to_string(), local_version);
+        }
+    }
+
+    let mut merged: Project = bson::from_document(merged_doc).map_err(HookedError::storage)?;
+
+    // Annotations changed on both sides since the last sync: keep the markers from both
+    let synced = last_synced.unwrap_or(0);
+    let local_coordinates_changed = local.field_versions.get("coordinates").copied().unwrap_or(0) > synced;
+    let remote_coordinates_changed = remote.field_versions.get("coordinates").copied().unwrap_or(0) > synced;
+    if local_coordinates_changed && remote_coordinates_changed {
+        merged.coordinates = merge_coordinates(&merged.coordinates, &local.coordinates, &remote.coordinates);
+    }
+
+    merged.field_versions = merged_versions;
+    merged.updated_at = local.updated_at.max(remote.updated_at);
+    Ok(merged)
+}
+
+// Keeps the winning list's order and adds markers only the other side has.
+// A marker at the same position on both sides keeps the notes from both.
+fn merge_coordinates(winner: &[Coordinate], local: &[Coordinate], remote: &[Coordinate]) -> Vec<Coordinate> {
+    let mut merged: Vec<Coordinate> = winner.to_vec();
+
+    for coordinate in local.iter().chain(remote.iter()) {
+        match merged.iter_mut().find(|existing| existing.lat == coordinate.lat && existing.lng == coordinate.lng) {
+            Some(existing) => {
+                for note in &coordinate.note {
+                    if !existing.note.contains(note) {
+                        existing.note.push(note.clone());
+                    }
+                }
+            }
+            None => merged.push(coordinate.clone()),
+        }
+    }
+    merged
+}
+
+// REMOTE STORE
+
+// A project as pulled from the remote store, with when the remote received it
+pub struct RemoteChange {
+    pub project: Project,
+    pub pushed_at: i64, // The remote's clock (UNIX ms), not comparable with updated_at
+}
+
+// What the sync engine needs from the shared store, MongoDB in the app
+#[async_trait]
+pub trait SyncRemote: Send + Sync {
+    async fn get(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<Project>>;
+    async fn delete(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<Project>>;
+    // Writes a project exactly as given, inserting it if missing, and stamps when it was pushed
+    async fn replace(&self, project: &Project) -> HookedResult<()>;
+    // Projects of an account pushed after `since`, every project when None
+    async fn changed_since(&self, account_id: &ObjectId, since: Option<i64>) -> HookedResult<Vec<RemoteChange>>;
+    async fn ids_for_account(&self, account_id: &ObjectId) -> HookedResult<Vec<ObjectId>>;
+}
+
+#[async_trait]
+impl SyncRemote for MongoProjectStore {
+    async fn get(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<Project>> {
+        ProjectStore::get(self, account_id, id).await
+    }
+
+    async fn delete(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<Project>> {
+        ProjectStore::delete(self, account_id, id).await
+    }
+
+    async fn replace(&self, project: &Project) -> HookedResult<()> {
+        MongoProjectStore::replace(self, project).await
+    }
+
+    async fn changed_since(&self, account_id: &ObjectId, since: Option<i64>) -> HookedResult<Vec<RemoteChange>> {
+        MongoProjectStore::changed_since(self, account_id, since).await
+    }
+
+    async fn ids_for_account(&self, account_id: &ObjectId) -> HookedResult<Vec<ObjectId>> {
+        MongoProjectStore::ids_for_account(self, account_id).await
+    }
+}
+
+// SYNC ENGINE
+
+// Totals for one sync round, logged after each run
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub pushed: usize,
+    pub pulled: usize,
+    pub deleted_remote: usize,
+    pub deleted_local: usize,
+}
+
+pub struct SyncEngine {
+    local: Arc<SqliteStore>,
+    remote: Arc<dyn SyncRemote>,
+}
+
+impl SyncEngine {
+    pub fn new(local: Arc<SqliteStore>, remote: Arc<dyn SyncRemote>) -> Self {
+        SyncEngine { local, remote }
+    }
+
+    // Syncs every account that has data in the local database
+    pub async fn sync_all(&self) -> HookedResult<SyncReport> {
+        let mut report = SyncReport::default();
+
+        for account_id in self.local.known_account_ids().await? {
+            self.sync_account(&account_id, &mut report).await?;
+        }
+        Ok(report)
+    }
+
+    // Push local changes first, then pull remote ones, then drop records deleted remotely
+    pub async fn sync_account(&self, account_id: &ObjectId, report: &mut SyncReport) -> HookedResult<()> {
+        self.push(account_id, report).await?;
+        self.pull(account_id, report).await?;
+        self.reconcile_deletions(account_id, report).await
+    }
+
+    async fn push(&self, account_id: &ObjectId, report: &mut SyncReport) -> HookedResult<()> {
+        for change in self.local.pending_changes(account_id).await? {
+            let remote = self.remote.get(account_id, &change.project_id).await?;
+
+            match change.operation {
+                PendingOperation::Delete => {
+                    // A remote edit made after the local delete wins, the next pull brings the project back
+                    let edited_later = remote.as_ref()
+                        .map(|project| project.updated_at.unwrap_or(0) > change.queued_at)
+                        .unwrap_or(false);
+
+                    if remote.is_some() && !edited_later {
+                        self.remote.delete(account_id, &change.project_id).await?;
+                        report.deleted_remote += 1;
+                    }
+                }
+                PendingOperation::Upsert => {
+                    // Deleted locally after being queued, the delete entry handles it
+                    let Some(local) = self.local.get(account_id, &change.project_id).await? else {
+                        self.local.clear_changes(&change.project_id, change.queue_id).await?;
+                        continue;
+                    };
+
+                    let synced = match remote {
+                        Some(remote) => {
+                            let last_synced = self.local.synced_at(&change.project_id).await?;
+                            merge(&local, &remote, last_synced)?
+                        }
+                        None => local,
+                    };
+
+                    self.remote.replace(&synced).await?;
+                    let synced = self.drop_missing_places(account_id, synced).await?;
+                    self.local.write_synced(&synced, now_millis()).await?;
+                    report.pushed += 1;
+                }
+            }
+
+            self.local.clear_changes(&change.project_id, change.queue_id).await?;
+        }
+        Ok(())
+    }
+
+    async fn pull(&self, account_id: &ObjectId, report: &mut SyncReport) -> HookedResult<()> {
+        let since = self.local.last_pulled_at(account_id).await?;
+        let mut newest = since;
+
+        for RemoteChange { project: remote, pushed_at } in self.remote.changed_since(account_id, since).await? {
+            let Some(project_id) = remote._id else { continue };
+            newest = newest.max(Some(pushed_at));
+
+            // Local edits that happened during this round are picked up next time
+            if self.local.has_pending_changes(&project_id).await? {
+                continue;
+            }
+
+            let synced = match self.local.get(account_id, &project_id).await? {
+                Some(local) => {
+                    let last_synced = self.local.synced_at(&project_id).await?;
+                    let merged = merge(&local, &remote, last_synced)?;
+                    // Annotation merges produce something neither side has yet
+                    if merged.coordinates != remote.coordinates {
+                        self.remote.replace(&merged).await?;
+                    }
+                    merged
+                }
+                None => remote,
+            };
+
+            let synced = self.drop_missing_places(account_id, synced).await?;
+            self.local.write_synced(&synced, now_millis()).await?;
+            report.pulled += 1;
+        }
+
+        // The remote's clock, so late pushes of old edits and clock differences between devices don't skip changes
+        if let Some(newest) = newest {
+            self.local.set_last_pulled_at(account_id, newest).await?;
+        }
+        Ok(())
+    }
+
+    // Locations and walls aren't synced, references to ones made on another device are left out of the local copy.
+    // Their versions are kept, so the next merge doesn't take the missing value as a local clear.
+    async fn drop_missing_places(&self, account_id: &ObjectId, mut project: Project) -> HookedResult<Project> {
+        if let Some(wall_id) = project.wall_id {
+            if self.local.find_wall(account_id, &wall_id).await?.is_none() {
+                project.wall_id = None;
+            }
+        }
+        if let Some(location_id) = project.location_id {
+            if self.local.find_location(account_id, &location_id).await?.is_none() {
+                project.location_id = None;
+                project.wall_id = None;
+            }
+        }
+        Ok(project)
+    }
+
+    // Projects that were synced before but no longer exist remotely were deleted on another device
+    async fn reconcile_deletions(&self, account_id: &ObjectId, report: &mut SyncReport) -> HookedResult<()> {
+        let remote_ids: HashSet<ObjectId> = self.remote.ids_for_account(account_id).await?.into_iter().collect();
+
+        for project_id in self.local.synced_ids(account_id).await? {
+            if !remote_ids.contains(&project_id) && !self.local.has_pending_changes(&project_id).await? {
+                self.local.delete_synced(&project_id).await?;
+                report.deleted_local += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Runs sync rounds in the background for as long as the app is open.
+// Connecting is retried every round, so changes go up as soon as there is signal again.
+pub fn spawn(local: Arc<SqliteStore>) {
+    let interval = std::env::var("SYNC_INTERVAL_SECS").ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_SYNC_INTERVAL_SECS);
+
+    tokio::spawn(async move {
+        let mut engine: Option<SyncEngine> = None;
+
+        loop {
+            if engine.is_none() {
+                match storage::mongo_client().await {
+                    Ok(client) => engine = Some(SyncEngine::new(local.clone(), Arc::new(MongoProjectStore::new(&client)))),
+                    Err(e) => println!("Sync skipped, MongoDB unreachable: {}", e),
+                }
+            }
+
+            if let Some(sync_engine) = &engine {
+                if let Err(e) = sync_engine.sync_all().await {
+                    eprintln!("Sync failed: {}", e);
+                    engine = None; // Reconnect on the next round
+                }
+            }
+
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use crate::database_helper::{tests, InactiveReason};
+    use crate::sqlite_store::tests::temp_store;
+
+    fn project(versions: &[(&str, i64)]) -> Project {
+        Project {
+            updated_at: versions.iter().map(|(_, version)| *version).max(),
+            field_versions: versions.iter().map(|(field, version)| (field.to_string(), *version)).collect(),
+            ..tests::project()
+        }
+    }
+
+    fn marker(lat: f64, lng: f64, note: &str) -> Coordinate {
+        Coordinate { lat, lng, note: vec![note.to_string()] }
+    }
+
+    #[test]
+    fn tie_goes_to_remote() {
+        let mut local = project(&[("grade", 100)]);
+        local.grade = "V4".to_string();
+        let mut remote = project(&[("grade", 100)]);
+        remote._id = local._id;
+        remote.grade = "V5".to_string();
+
+        let merged = merge(&local, &remote, None).unwrap();
+        assert_eq!(merged.grade, "V5");
+        assert_eq!(merged.field_versions["grade"], 100);
+    }
+
+    #[test]
+    fn newer_local_field_wins() {
+        let mut local = project(&[("grade", 200), ("attempts", 50)]);
+        local.grade = "V4".to_string();
+        local.attempts = 3;
+        let mut remote = project(&[("grade", 100), ("attempts", 150)]);
+        remote._id = local._id;
+        remote.grade = "V5".to_string();
+        remote.attempts = 7;
+
+        let merged = merge(&local, &remote, None).unwrap();
+        assert_eq!(merged.grade, "V4");
+        assert_eq!(merged.attempts, 7);
+        assert_eq!(merged.field_versions["grade"], 200);
+        assert_eq!(merged.field_versions["attempts"], 150);
+        assert_eq!(merged.updated_at, Some(200));
+    }
+
+    #[test]
+    fn field_cleared_locally_is_cleared_in_merge() {
+        let local = project(&[("inactive_reason", 200), ("location_id", 200)]);
+        let mut remote = project(&[("inactive_reason", 100), ("location_id", 100)]);
+        remote._id = local._id;
+        remote.inactive_reason = Some(InactiveReason::WallReset);
+        remote.location_id = Some(ObjectId::new());
+
+        let merged = merge(&local, &remote, None).unwrap();
+        assert_eq!(merged.inactive_reason, None);
+        assert_eq!(merged.location_id, None);
+        assert_eq!(merged.field_versions["location_id"], 200);
+    }
+
+    #[test]
+    fn coordinates_changed_on_both_sides_are_merged() {
+        let mut local = project(&[("coordinates", 300)]);
+        local.coordinates = vec![marker(1.0, 1.0, "crimp"), marker(2.0, 2.0, "local only")];
+        let mut remote = project(&[("coordinates", 250)]);
+        remote._id = local._id;
+        remote.coordinates = vec![marker(1.0, 1.0, "heel hook"), marker(3.0, 3.0, "remote only")];
+
+        let merged = merge(&local, &remote, Some(200)).unwrap();
+        assert_eq!(merged.coordinates, vec![
+            Coordinate { lat: 1.0, lng: 1.0, note: vec!["crimp".to_string(), "heel hook".to_string()] },
+            marker(2.0, 2.0, "local only"),
+            marker(3.0, 3.0, "remote only"),
+        ]);
+    }
+
+    #[test]
+    fn coordinates_changed_on_one_side_are_not_merged() {
+        let mut local = project(&[("coordinates", 150)]);
+        local.coordinates = vec![marker(1.0, 1.0, "old")];
+        let mut remote = project(&[("coordinates", 250)]);
+        remote._id = local._id;
+        remote.coordinates = vec![marker(3.0, 3.0, "new")];
+
+        let merged = merge(&local, &remote, Some(200)).unwrap();
+        assert_eq!(merged.coordinates, remote.coordinates);
+    }
+
+    #[test]
+    fn stamps_only_changed_fields() {
+        let previous = project(&[("grade", 100), ("attempts", 100)]);
+        let mut updated = previous.clone();
+        updated.attempts = 4;
+
+        stamp_changes(Some(&previous), &mut updated, 500).unwrap();
+        assert_eq!(updated.field_versions["grade"], 100);
+        assert_eq!(updated.field_versions["attempts"], 500);
+        assert_eq!(updated.updated_at, Some(500));
+    }
+
+    #[test]
+    fn stamps_every_field_of_a_new_project() {
+        let mut created = project(&[]);
+        stamp_changes(None, &mut created, 500).unwrap();
+        assert!(SYNCED_FIELDS.iter().all(|field| created.field_versions.get(*field) == Some(&500)));
+        assert_eq!(created.updated_at, Some(500));
+    }
+
+    // The shared store, in memory. pushed_at comes from a counter, a clock unrelated to the devices' ones.
+    #[derive(Default)]
+    struct MemoryRemote {
+        projects: Mutex<HashMap<ObjectId, RemoteChange>>,
+        clock: Mutex<i64>,
+    }
+
+    #[async_trait]
+    impl SyncRemote for MemoryRemote {
+        async fn get(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<Project>> {
+            let projects = self.projects.lock().unwrap();
+            Ok(projects.get(id).map(|change| change.project.clone()).filter(|project| project.account_id == *account_id))
+        }
+
+        async fn delete(&self, account_id: &ObjectId, id: &ObjectId) -> HookedResult<Option<Project>> {
+            let mut projects = self.projects.lock().unwrap();
+            if projects.get(id).is_some_and(|change| change.project.account_id == *account_id) {
+                return Ok(projects.remove(id).map(|change| change.project));
+            }
+            Ok(None)
+        }
+
+        async fn replace(&self, project: &Project) -> HookedResult<()> {
+            let mut clock = self.clock.lock().unwrap();
+            *clock += 1;
+            let change = RemoteChange { project: project.clone(), pushed_at: *clock };
+            self.projects.lock().unwrap().insert(project._id.unwrap(), change);
+            Ok(())
+        }
+
+        async fn changed_since(&self, account_id: &ObjectId, since: Option<i64>) -> HookedResult<Vec<RemoteChange>> {
+            let projects = self.projects.lock().unwrap();
+            Ok(projects.values()
+                .filter(|change| change.project.account_id == *account_id && since.is_none_or(|since| change.pushed_at > since))
+                .map(|change| RemoteChange { project: change.project.clone(), pushed_at: change.pushed_at })
+                .collect())
+        }
+
+        async fn ids_for_account(&self, account_id: &ObjectId) -> HookedResult<Vec<ObjectId>> {
+            let projects = self.projects.lock().unwrap();
+            Ok(projects.iter().filter(|(_, change)| change.project.account_id == *account_id).map(|(id, _)| *id).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn pulls_an_old_edit_pushed_late() {
+        let remote = Arc::new(MemoryRemote::default());
+        let (device_a, device_b) = (temp_store().await, temp_store().await);
+        let engine_a = SyncEngine::new(Arc::new(SqliteStore::open(&device_a.path).await.unwrap()), remote.clone());
+        let engine_b = SyncEngine::new(Arc::new(SqliteStore::open(&device_b.path).await.unwrap()), remote.clone());
+        let account_id = ObjectId::new();
+
+        // Logged on A without signal
+        let offline_id = device_a.store.create(Project { account_id, ..tests::project() }).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        // B syncs a later edit first, its last pull is now past A's edit
+        let online_id = device_b.store.create(Project { account_id, ..tests::project() }).await.unwrap();
+        engine_b.sync_account(&account_id, &mut SyncReport::default()).await.unwrap();
+
+        // A gets signal again and pushes its old edit
+        engine_a.sync_account(&account_id, &mut SyncReport::default()).await.unwrap();
+        let mut report = SyncReport::default();
+        engine_b.sync_account(&account_id, &mut report).await.unwrap();
+
+        assert_eq!(report.pulled, 1);
+        assert!(device_b.store.get(&account_id, &offline_id).await.unwrap().is_some());
+        assert!(device_a.store.get(&account_id, &online_id).await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,83 @@
+// src-tauri/src/upload_store.rs
+
+// Storage abstraction for the images each account uploaded (see ImageUpload in database_helper.rs).
+// upload_image records every variant it stores, and a project can only be saved with an image its account uploaded,
+// so copying another account's key or URL onto a project doesn't give access to that image.
+
+
+// IMPORTS
+use async_trait::async_trait;
+use std::sync::Arc;
+
+// MongoDB & BSON
+use mongodb::{Client, Collection, bson::{doc, oid::ObjectId}};
+use futures_util::stream::TryStreamExt;
+
+use crate::database_helper::ImageUpload;
+use crate::project_store::DATABASE_NAME;
+use crate::error::{HookedError, HookedResult};
+
+pub const IMAGE_UPLOADS_COLLECTION: &str = "image_uploads";
+
+// The store shared through Tauri's state
+pub type SharedUploadStore = Arc<dyn UploadStore>;
+
+// STORE TRAIT
+#[async_trait]
+pub trait UploadStore: Send + Sync {
+    // Records an uploaded image
+    async fn insert(&self, upload: ImageUpload) -> HookedResult<()>;
+    // The account's uploads with one of the given keys
+    async fn find(&self, account_id: &ObjectId, public_ids: &[String]) -> HookedResult<Vec<ImageUpload>>;
+    // Forgets the account's uploads with one of the given keys, once the images are deleted
+    async fn delete(&self, account_id: &ObjectId, public_ids: &[String]) -> HookedResult<u64>;
+    // Forgets every upload of an account
+    async fn delete_for_account(&self, account_id: &ObjectId) -> HookedResult<u64>;
+}
+
+// MONGODB IMPLEMENTATION
+pub struct MongoUploadStore {
+    collection: Collection<ImageUpload>,
+}
+
+impl MongoUploadStore {
+    pub fn new(client: &Client) -> Self {
+        let collection = client.database(DATABASE_NAME).collection::<ImageUpload>(IMAGE_UPLOADS_COLLECTION);
+        MongoUploadStore { collection }
+    }
+}
+
+#[async_trait]
+impl UploadStore for MongoUploadStore {
+    async fn insert(&self, upload: ImageUpload) -> HookedResult<()> {
+        self.collection.insert_one(&upload, None).await.map_err(HookedError::storage)?;
+        Ok(())
+    }
+
+    async fn find(&self, account_id: &ObjectId, public_ids: &[String]) -> HookedResult<Vec<ImageUpload>> {
+        if public_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let cursor = self.collection
+            .find(doc! { "account_id": account_id, "public_id": { "$in": public_ids } }, None)
+            .await
+            .map_err(HookedError::storage)?;
+        cursor.try_collect().await.map_err(HookedError::storage)
+    }
+
+    async fn delete(&self, account_id: &ObjectId, public_ids: &[String]) -> HookedResult<u64> {
+        if public_ids.is_empty() {
+            return Ok(0);
+        }
+        let result = self.collection
+            .delete_many(doc! { "account_id": account_id, "public_id": { "$in": public_ids } }, None)
+            .await
+            .map_err(HookedError::storage)?;
+        Ok(result.deleted_count)
+    }
+
+    async fn delete_for_account(&self, account_id: &ObjectId) -> HookedResult<u64> {
+        let result = self.collection.delete_many(doc! { "account_id": account_id }, None).await.map_err(HookedError::storage)?;
+        Ok(result.deleted_count)
+    }
+}
@@ -20,23 +20,166 @@ export async function registerAccount(email: string, password: string): Promise<
   }
 }
 
+// Error returned by every backend command (HookedError in src-tauri/src/error.rs)
+export interface HookedError {
+  code: 'NotFound' | 'Unauthorized' | 'Validation' | 'EmailAlreadyRegistered' | 'Storage' | 'ImageService' | 'Internal';
+  message: string;
+}
+
+export function isHookedError(error: unknown): error is HookedError {
+  return typeof error === 'object' && error !== null && 'code' in error && 'message' in error;
+}
+
+// A message that can be shown to the user for any thrown error
+export function errorMessage(error: unknown): string {
+  return isHookedError(error) ? error.message : String(error);
+}
+
+// Tokens returned by 'login' and 'refresh_session' (SessionTokens in database_helper.rs)
+export interface SessionTokens {
+  token: string;          // Short-lived JWT sent with every command
+  expires_at: number;     // UNIX seconds
+  refresh_token: string;  // Single use, trades for a new pair
+  account_id: string;
+}
+
+// Keeps the current tokens in localStorage
+function storeSession(session: SessionTokens) {
+  localStorage.setItem('token', session.token);
+  localStorage.setItem('refresh_token', session.refresh_token);
+  localStorage.setItem('account_id', session.account_id);
+}
+
+function clearSession() {
+  localStorage.removeItem('token');
+  localStorage.removeItem('refresh_token');
+  localStorage.removeItem('account_id');
+}
+
 /**
  * Logs in an account by invoking the Rust backend command.
  * @param email - The user's email.
  * @param password - The user's password.
- * @returns A promise that resolves with a JWT token as a string.
+ * @returns A promise that resolves with the session tokens (already saved to localStorage).
  */
-export async function loginAccount(email: string, password: string): Promise<[string, string]> {
+export async function loginAccount(email: string, password: string): Promise<SessionTokens> {
   try {
     // Invoke the Tauri command 'login' (registered in main.rs)
-    const token = await invoke<[string, string]>('login', { email, password });
-    return token;
+    const session = await invoke<SessionTokens>('login', { email, password, device: navigator.userAgent });
+    storeSession(session);
+    return session;
   } catch (error) {
     console.error("Error logging in:", error);
     throw error;
   }
 }
 
+/**
+ * Trades the stored refresh token for a new token pair.
+ * Refresh tokens only work once, so the new pair replaces the old one right away.
+ * @returns true if the session was renewed, false if the user has to log in again.
+ */
+export async function refreshSession(): Promise<boolean> {
+  const refreshToken = localStorage.getItem('refresh_token');
+  if (!refreshToken) return false;
+
+  try {
+    const session = await invoke<SessionTokens>('refresh_session', { refreshToken });
+    storeSession(session);
+    return true;
+  } catch (error) {
+    console.error("Error refreshing session:", error);
+    clearSession();
+    return false;
+  }
+}
+
+/**
+ * Invokes a command that takes a `token`, renewing the session once if the token has expired.
+ * Drop-in replacement for `invoke` in the stores.
+ */
+export async function invokeWithSession<T>(command: string, args: Record<string, unknown> = {}): Promise<T> {
+  try {
+    return await invoke<T>(command, args);
+  } catch (error) {
+    const expired = isHookedError(error) && error.code === 'Unauthorized' && error.message.includes('expired');
+    if (!expired || !('token' in args) || !(await refreshSession())) throw error;
+
+    // Retry with the renewed token
+    return await invoke<T>(command, { ...args, token: localStorage.getItem('token') });
+  }
+}
+
+/**
+ * Logs out every device of this account, including this one.
+ */
+export async function logoutAllDevices() {
+  try {
+    await invoke<number>('logout_all_devices', { token: localStorage.getItem('token') });
+  } catch (error) {
+    console.error("Error logging out all devices:", error);
+  }
+  clearSession();
+  goto('/login');
+}
+
+/**
+ * Asks the backend to email a password reset code.
+ * Resolves even if the email isn't registered.
+ * @param email - The account's email.
+ */
+export async function requestPasswordReset(email: string): Promise<void> {
+  try {
+    await invoke('request_password_reset', { email });
+  } catch (error) {
+    console.error("Error requesting password reset:", error);
+    throw error;
+  }
+}
+
+/**
+ * Sets a new password with the emailed reset code. Every device is logged out afterwards.
+ * @param token - The code from the reset email.
+ * @param newPassword - The new password.
+ */
+export async function confirmPasswordReset(token: string, newPassword: string): Promise<void> {
+  try {
+    await invoke('confirm_password_reset', { token, newPassword });
+  } catch (error) {
+    console.error("Error resetting password:", error);
+    throw error;
+  }
+}
+
+/**
+ * Changes the logged-in user's password.
+ * @param oldPassword - The current password, checked by the backend.
+ * @param newPassword - The new password.
+ */
+export async function changePassword(oldPassword: string, newPassword: string): Promise<void> {
+  try {
+    await invokeWithSession('change_password', { token: localStorage.getItem('token'), oldPassword, newPassword });
+  } catch (error) {
+    console.error("Error changing password:", error);
+    throw error;
+  }
+}
+
+/**
+ * Deletes the logged-in user's account with all its projects and images, then goes back to login.
+ * @param password - The current password, checked by the backend.
+ */
+export async function deleteAccount(password: string): Promise<void> {
+  try {
+    await invokeWithSession('delete_account', { token: localStorage.getItem('token'), password });
+  } catch (error) {
+    console.error("Error deleting account:", error);
+    throw error;
+  }
+  clearSession();
+  goto('/login');
+}
+
 // Check login status once
 export const checkLoginStatus = (): boolean => {
   const token = localStorage.getItem('token'); // Retrieve JWT token
@@ -50,7 +193,15 @@ export const checkLoginStatus = (): boolean => {
 /**
  * Logs out the user by removing the token and redirecting to login.
  */
-export function logoutAccount() {
-  localStorage.removeItem('token'); // Remove token from localStorage
+export async function logoutAccount() {
+  const refreshToken = localStorage.getItem('refresh_token');
+  if (refreshToken) {
+    try {
+      await invoke('logout', { refreshToken }); // Ends this device's session on the backend
+    } catch (error) {
+      console.error("Error logging out:", error);
+    }
+  }
+  clearSession(); // Remove tokens from localStorage
   goto('/login'); // Redirect to login page
 }
\ No newline at end of file
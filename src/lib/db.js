@@ -3,14 +3,14 @@
 import { invoke } from '@tauri-apps/api/core';
 
 /**
- * Fetch all projects from the MongoDB backend using Tauri.
- * This function calls the 'get_all_projects' command in the Rust backend.
+ * Fetch the first page of the user's projects using Tauri.
+ * This function calls the 'query_projects' command in the Rust backend.
  * It returns an array of project objects.
  */
 export async function getAllProjects() {
   try {
-    const result = await invoke('get_all_projects');
-    return result; // The result should be the list of projects
+    const result = await invoke('query_projects', { query: {}, token: localStorage.getItem('token') });
+    return result.projects; // The first page of projects
   } catch (error) {
     console.error('Error fetching projects:', error);
     throw error; // Rethrow for handling at the calling side
@@ -18,14 +18,14 @@ export async function getAllProjects() {
 }
 
 /**
- * Fetch sends count by grade from the Rust backend.
- * This function calls the 'fetch_sends_count' command to get the number of sends for a specific grade.
+ * Fetch the user's sends count by grade from the Rust backend.
+ * This function calls the 'get_sends_count' command to get the number of sends for a specific grade.
  * @param {string} grade - The grade to count sends for.
  * @returns {Promise<number>} - The number of sends for the given grade.
  */
 export async function getSendsCount(grade) {
   try {
-    const result = await invoke('get_sends_count', { grade });
+    const result = await invoke('get_sends_count', { grade, token: localStorage.getItem('token') });
     return result; // The result should be the sends count for the given grade
   } catch (error) {
     console.error('Error fetching sends count:', error);
@@ -2,16 +2,55 @@
 
 import { format } from 'date-fns'; // library to format JS dates
 
+// Mirrors Discipline and SendStyle in src-tauri/src/database_helper.rs
+export type Discipline = 'boulder' | 'sport' | 'trad' | 'top_rope';
+export type SendStyle = 'lead' | 'top_rope' | 'flash' | 'onsight';
+export type InactiveReason = 'wall_reset'; // missing when archived by hand
+
+// Mirrors ImageVariant and StoredVariant in src-tauri/src/database_helper.rs
+export type ImageVariant = 'thumbnail' | 'medium' | 'original';
+
+export interface StoredVariant {
+  variant: ImageVariant;
+  url: string;
+  public_id: string;
+  width: number;
+  height: number;
+}
+
+// Mirrors Attempt in src-tauri/src/database_helper.rs
+export type AttemptOutcome = 'fall' | 'send' | 'flash';
+export type HighPoint = { annotation: number } | { percent: number }; // annotation: index into coordinates, percent: 0 to 100
+
+export interface Attempt {
+  _id?: string;
+  project_id: string;
+  session_id?: string; // the running climbing session when left out
+  timestamp?: number; // UNIX seconds, the backend uses the current time when left out
+  high_point?: HighPoint;
+  outcome: AttemptOutcome;
+  notes?: string;
+}
+
 // Definition of the Project class
 export class Project {
   _id?: string; // Optional, since MongoDB generates _id if not provided
   date_time: Date; // when the project was created / logged
   image_path: string; // path or URL to the project image
+  image_public_id?: string; // key of the image in the image store, returned by uploadImage
+  image_variants: StoredVariant[]; // sizes made at upload, empty for older images
   is_sent: boolean; // true if the climb was completed
   sent_date: Date; // when it was sent (if ever)
   attempts: number; // how many tries
-  grade: string; // e.g. V5
+  discipline: Discipline; // boulder or rope route
+  send_style?: SendStyle; // how it was sent (only when sent)
+  grade: string; // e.g. V5, 6b+, 5.11a
+  location_id?: string; // gym or crag, see src/stores/locationsStore.ts
+  wall_id?: string; // wall of that location
+  grade_system?: string; // e.g. 'v_scale', 'french' or a custom scale id. Worked out by the backend when missing.
   is_active: boolean; // for filtering
+  inactive_reason?: InactiveReason; // why it was archived, e.g. its wall was reset
+  inactive_since?: number; // UNIX seconds
   coordinates: { lat: number; lng: number; note?: string[] }[]; // annotation points on image
   style: string[]; // styles tags (e.g. "slab", "overhang")
   holds: string[]; // holds tags (e.g. "pinch", "jug")
@@ -23,11 +62,20 @@ export class Project {
     _id,
     date_time = new Date(), 
     image_path = 'No Image', 
+    image_public_id,
+    image_variants = [],
     is_sent = false, 
     sent_date = new Date(0),
     attempts = 0, 
+    discipline = 'boulder',
+    send_style,
     grade = 'Unknown', 
+    location_id,
+    wall_id,
+    grade_system,
     is_active = true, 
+    inactive_reason,
+    inactive_since,
     coordinates = [],
     style = [],
     holds = [],
@@ -35,11 +83,20 @@ export class Project {
     _id?: string;
     date_time?: Date | string | number;
     image_path?: string;
+    image_public_id?: string;
+    image_variants?: StoredVariant[];
     is_sent?: boolean;
     sent_date?: Date | string | number;
     attempts?: number;
+    discipline?: Discipline;
+    send_style?: SendStyle;
     grade?: string;
+    location_id?: string;
+    wall_id?: string;
+    grade_system?: string;
     is_active?: boolean;
+    inactive_reason?: InactiveReason;
+    inactive_since?: number;
     coordinates?: { lat: number; lng: number }[];
     style?: string[];
     holds?: string[];
@@ -52,6 +109,8 @@ export class Project {
       : date_time;
 
     this.image_path = image_path;
+    this.image_public_id = image_public_id;
+    this.image_variants = image_variants;
     this.is_sent = is_sent;
 
     // Support sent_date as timestamp (seconds or ms)
@@ -60,8 +119,15 @@ export class Project {
       : sent_date;
 
     this.attempts = attempts;
+    this.discipline = discipline;
+    this.send_style = send_style;
     this.grade = grade;
+    this.location_id = location_id;
+    this.wall_id = wall_id;
+    this.grade_system = grade_system;
     this.is_active = is_active;
+    this.inactive_reason = inactive_reason;
+    this.inactive_since = inactive_since;
     this.coordinates = coordinates;
     this.style = style;
     this.holds = holds; 
@@ -73,6 +139,19 @@ export class Project {
     return this.date_time ? format(this.date_time, 'dd-MM-yyyy HH:mm:ss') : 'Invalid Date';
   }
 
+  // URL of the smallest variant at least `width` pixels wide (the widest one when none is),
+  // image_path for images uploaded before variants. Same choice as get_image_variant without Cloudinary.
+  imageForWidth(width: number): string {
+    const variants = [...this.image_variants].sort((a, b) => a.width - b.width);
+    const best = variants.find((variant) => variant.width >= width) ?? variants[variants.length - 1];
+    return best ? best.url : this.image_path;
+  }
+
+  // Getter: true when the project was archived because the gym reset its wall
+  get expired_by_reset(): boolean {
+    return !this.is_active && this.inactive_reason === 'wall_reset';
+  }
+
   // Getter: returns formatted sent date
   // or "Not Sent" if no valid sent_date
   get formatted_sent_date(): string {
@@ -92,11 +171,20 @@ export class Project {
 
       date_time: this.date_time.getTime(),  // Store as milliseconds (or use ISODate in MongoDB)
       image_path: this.image_path,
+      ...(this.image_public_id ? { image_public_id: this.image_public_id } : {}),
+      ...(this.image_variants.length ? { image_variants: this.image_variants } : {}),
       is_sent: this.is_sent ? 1 : 0,
       sent_date: Math.floor(this.sent_date.getTime() / 1000),
       attempts: this.attempts,
+      discipline: this.discipline,
+      ...(this.is_sent && this.send_style ? { send_style: this.send_style } : {}),
       grade: this.grade,
+      ...(this.location_id ? { location_id: this.location_id } : {}),
+      ...(this.wall_id ? { wall_id: this.wall_id } : {}),
+      ...(this.grade_system ? { grade_system: this.grade_system } : {}),
       is_active: this.is_active ? 1 : 0,
+      ...(this.inactive_reason ? { inactive_reason: this.inactive_reason } : {}),
+      ...(this.inactive_since ? { inactive_since: this.inactive_since } : {}),
       coordinates: this.coordinates,
       style: this.style,
       holds: this.holds,
@@ -110,11 +198,20 @@ export class Project {
       _id: map._id ? String(map._id) : undefined,
       date_time: new Date(map.date_time),
       image_path: map.image_path,
+      image_public_id: map.image_public_id ?? undefined,
+      image_variants: map.image_variants || [],
       is_sent: map.is_sent === 1,
       sent_date: map.sent_date,
       attempts: map.attempts,
+      discipline: map.discipline ?? 'boulder',
+      send_style: map.send_style ?? undefined,
+      location_id: map.location_id ? String(map.location_id) : undefined,
+      wall_id: map.wall_id ? String(map.wall_id) : undefined,
       grade: map.grade,
+      grade_system: map.grade_system ?? undefined,
       is_active: map.is_active === 1,
+      inactive_reason: map.inactive_reason ?? undefined,
+      inactive_since: map.inactive_since ?? undefined,
       coordinates: map.coordinates || [],
       style: map.style || [],
       holds: map.holds || [],
@@ -0,0 +1,80 @@
+// src/stores/locationsStore.ts
+
+import { writable } from 'svelte/store';
+import { invokeWithSession as invoke } from '../controllers/accountsController';
+
+// Mirrors Location and Wall in src-tauri/src/location.rs
+export type Environment = 'indoor' | 'outdoor';
+
+export interface Location {
+  _id?: string;
+  name: string;
+  coordinates?: { lat: number; lng: number };
+  environment: Environment;
+}
+
+export interface Wall {
+  _id?: string;
+  location_id: string;
+  name: string;
+  angle?: number; // degrees from vertical, negative for slabs
+  reset_date?: number; // UNIX seconds of the next reset, its projects are archived then
+  reset_interval_days?: number; // resets repeat this often, missing for a one-off reset
+  last_reset_at?: number;
+}
+
+// The user's locations and walls, by name
+export const locations = writable<Location[]>([]);
+export const walls = writable<Wall[]>([]);
+
+type StoredId = string | { $oid: string };
+const hex = (id: StoredId) => (typeof id === 'object' ? id.$oid : id);
+
+function toLocation(data: Omit<Location, '_id'> & { _id: StoredId }): Location {
+  return { ...data, _id: hex(data._id) };
+}
+
+function toWall(data: Omit<Wall, '_id' | 'location_id'> & { _id: StoredId; location_id: StoredId }): Wall {
+  return { ...data, _id: hex(data._id), location_id: hex(data.location_id) };
+}
+
+// Loads every location and wall into the stores
+export async function fetchLocations(): Promise<void> {
+  try {
+    const token = localStorage.getItem('token');
+    const [locationList, wallList] = await Promise.all([
+      invoke<Parameters<typeof toLocation>[0][]>('list_locations', { token }),
+      invoke<Parameters<typeof toWall>[0][]>('list_walls', { token }),
+    ]);
+    locations.set(locationList.map(toLocation));
+    walls.set(wallList.map(toWall));
+  } catch (error) {
+    console.error('Error fetching locations:', error);
+  }
+}
+
+// Creates a location, or updates it when it has an _id
+export async function saveLocation(location: Location): Promise<Location> {
+  const saved = await invoke<Parameters<typeof toLocation>[0]>('save_location', { token: localStorage.getItem('token'), location });
+  await fetchLocations();
+  return toLocation(saved);
+}
+
+// Deletes a location with its walls. The backend refuses while projects are still at it.
+export async function deleteLocation(id: string): Promise<void> {
+  await invoke('delete_location', { token: localStorage.getItem('token'), id });
+  await fetchLocations();
+}
+
+// Creates a wall, or updates it when it has an _id
+export async function saveWall(wall: Wall): Promise<Wall> {
+  const saved = await invoke<Parameters<typeof toWall>[0]>('save_wall', { token: localStorage.getItem('token'), wall });
+  await fetchLocations();
+  return toWall(saved);
+}
+
+// Deletes a wall. The backend refuses while projects are still on it.
+export async function deleteWall(id: string): Promise<void> {
+  await invoke('delete_wall', { token: localStorage.getItem('token'), id });
+  await fetchLocations();
+}
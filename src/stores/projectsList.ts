@@ -1,8 +1,9 @@
 // src/stores/projectsList.ts
 
 import { writable, type Writable } from 'svelte/store';
-import { invoke } from '@tauri-apps/api/core';
-import { Project } from '../models/Project';
+import { convertFileSrc } from '@tauri-apps/api/core';
+import { invokeWithSession as invoke } from '../controllers/accountsController'; // Renews expired sessions before giving up
+import { Project, type Attempt, type Discipline, type InactiveReason, type SendStyle, type StoredVariant } from '../models/Project';
 
 // INTERFACE TO REPRESENT RAW MONGODB PROJECT
 // (What the backend returns, before converting
@@ -11,10 +12,20 @@ export interface MongoDBProject {
   _id: string | { $oid: string };
   date_time: number;
   image_path: string;
+  image_public_id?: string;
+  image_variants?: StoredVariant[];
   grade: string;
+  location_id?: string | { $oid: string };
+  wall_id?: string | { $oid: string };
+  grade_system?: string; // Set by the backend, see src-tauri/src/grade.rs
+  difficulty?: number;
   is_sent: boolean;
   attempts: number;
+  discipline?: Discipline; // Missing on projects saved before disciplines, which are boulders
+  send_style?: SendStyle;
   is_active: boolean;
+  inactive_reason?: InactiveReason;
+  inactive_since?: number;
   coordinates?: { lat: number; lng: number; note?: string[] }[];
   style?: string[];
   holds?: string[];
@@ -28,47 +39,129 @@ export const projectsList: Writable<Project[]> = writable([]);
 // Annotation overlays (image markers)
 export const annotations = writable<{ [key: string]: { x: string; y: string }[] }>({});
 // Summary data
-export const sendsSummary = writable<{ total: number; byGrade: Record<string, number> }>({
+export const sendsSummary = writable<{
+  total: number;
+  byGrade: Record<string, number>;
+  periods: PeriodSends[];
+  byDiscipline: Partial<Record<Discipline, number>>;
+  bySendStyle: Partial<Record<SendStyle, number>>;
+}>({
   total: 0,
   byGrade: {},
+  periods: [],
+  byDiscipline: {},
+  bySendStyle: {},
 });
 
-export const stylesSummary = writable<{ style: string; done: number; practicing: number }[]>([]);
-export const holdsSummary = writable<{ holds: string; done: number; practicing: number }[]>([]);
+// Per-tag stats as returned by get_styles_summary / get_holds_summary (TagSummary in project_stats.rs)
+export interface TagSummary {
+  tag: string;
+  sent: number;
+  open: number;
+  send_rate: number; // 0 to 1
+  avg_attempts_to_send: number | null;
+  hardest_grade_sent: string | null;
+}
 
-// FUNCTION: Load all projects from backend
+// done/practicing are the sent/open counts, kept under the names the radar graphs use
+export const stylesSummary = writable<({ style: string; done: number; practicing: number } & TagSummary)[]>([]);
+export const holdsSummary = writable<({ holds: string; done: number; practicing: number } & TagSummary)[]>([]);
+
+// QUERYING PROJECTS
+// Mirrors ProjectQuery in src-tauri/src/project_query.rs. Every field is optional.
+export type SortField = 'date_time' | 'sent_date' | 'grade' | 'attempts';
+export type TagMatch = 'any' | 'all'; // Match any (default) or all of the listed tags
+
+export interface ProjectQuery {
+  active?: boolean;
+  sent?: boolean;
+  discipline?: Discipline;
+  location_id?: string;
+  wall_id?: string;
+  grades?: string[];
+  styles?: string[];
+  styles_match?: TagMatch;
+  exclude_styles?: string[]; // Projects with any of these styles are left out
+  holds?: string[];
+  holds_match?: TagMatch;
+  exclude_holds?: string[];
+  date_from?: number; // UNIX seconds, inclusive
+  date_to?: number;
+  sent_from?: number;
+  sent_to?: number;
+  min_attempts?: number;
+  max_attempts?: number;
+  sort?: { field: SortField; direction?: 'asc' | 'desc' }[]; // Defaults to newest first. 'grade' sorts by difficulty.
+  cursor?: string; // next_cursor of the previous page
+  limit?: number;
+}
+
+export interface ProjectPage {
+  projects: MongoDBProject[];
+  total: number; // Matching projects across all pages
+  next_cursor: string | null; // null on the last page
+}
+
+// Hex string of an id the backend sent as { $oid }
+function hexId(id?: string | { $oid: string }): string | undefined {
+  return typeof id === 'object' && id !== null && '$oid' in id ? id.$oid : id;
+}
+
+// Converts a raw backend project into a Project instance
+function toProject(data: MongoDBProject): Project {
+  return new Project({
+    ...data,
+    _id: hexId(data._id) || '',
+    location_id: hexId(data.location_id),
+    wall_id: hexId(data.wall_id),
+    coordinates: Array.isArray(data.coordinates)
+      ? data.coordinates.map((coord) =>
+          typeof coord.lat === 'number' && typeof coord.lng === 'number'
+            ? coord
+            : { lat: 0, lng: 0 } // Default invalid coordinates
+        )
+      : [],
+  });
+}
+
+// Fetches a single page of projects
+export async function queryProjects(query: ProjectQuery = {}): Promise<ProjectPage> {
+  return await invoke<ProjectPage>('query_projects', { query, token: localStorage.getItem('token') });
+}
+
+// Follows next_cursor until every matching project is loaded
+export async function fetchAllProjects(query: ProjectQuery = {}): Promise<Project[]> {
+  const projects: Project[] = [];
+  let cursor: string | undefined = undefined;
+
+  do {
+    const page: ProjectPage = await queryProjects({ ...query, cursor });
+    projects.push(...page.projects.map(toProject));
+    cursor = page.next_cursor ?? undefined;
+  } while (cursor);
+
+  return projects;
+}
+
+// Maps the 'true'/'false'/'' sent status of the filter UI to the query's sent flag
+function sentFilter(sentStatus: string): boolean | undefined {
+  if (sentStatus === 'true') return true;
+  if (sentStatus === 'false') return false;
+  return undefined;
+}
+
+// FUNCTION: Load all active projects from backend
 // and fill the store
 export async function initializeProjectsList(): Promise<void> {
   try {
-    // Invoke Rust command to get all projects. 'result' is typed as 'unknown' because the function does not initially know what type the backend will return.
-    // const result: unknown = await invoke('get_all_projects');
-    const accountId = localStorage.getItem("account_id");
-    if (!accountId) {
-      console.error("No account ID found in localStorage.");
+    const token = localStorage.getItem("token");
+    if (!token) {
+      console.error("No session token found in localStorage.");
       return;
     }
 
-    const result: unknown = await invoke('get_all_projects', { accountId });
-
-    // Ensure the response is an array (if not an array, throw error). 
-    if (!Array.isArray(result)) {
-      throw new Error('Unexpected response format');
-    }
-
-    // Convert MongoDB-style objects into Project instances.
-    const projects: Project[] = result.map((projectMap: MongoDBProject) =>
-      Project.fromMap({
-        ...projectMap,
-        _id:
-          typeof projectMap._id === 'object' && projectMap._id !== null && '$oid' in projectMap._id
-            ? projectMap._id.$oid
-            : String(projectMap._id || ''),
-        coordinates: projectMap.coordinates || [],
-      })
-    );
-
     // Update the projectsList store with the fetched projects.
-    projectsList.set(projects);
+    projectsList.set(await fetchAllProjects({ active: true }));
   } catch (error) {
     console.error('Error initializing projects list:', error);
   }
@@ -77,7 +170,7 @@ export async function initializeProjectsList(): Promise<void> {
 // Delete a project by its ID (calls Rust, then refreshes list)
 export async function deleteProject(_id: string): Promise<void> {
   try {
-    await invoke('delete_project', { id: _id });
+    await invoke('delete_project', { _id, token: localStorage.getItem('token') });
     // Refresh the projectsList store by re-fetching all projects.
     await initializeProjectsList();
   } catch (error) {
@@ -87,48 +180,62 @@ export async function deleteProject(_id: string): Promise<void> {
 
 // FETCH SUMMARY DATA (for stats graphs)
 // e.g. sends count by grade
-export async function fetchSendsSummary(): Promise<void> {
+export type SendPeriod = 'week' | 'month' | 'year';
+
+// Sends within one week/month/year (PeriodSends in project_stats.rs)
+export interface PeriodSends {
+  period: string; // "2025-W07", "2025-02" or "2025"
+  start: number; // UNIX seconds
+  total: number;
+  by_grade: { grade: string; count: number }[]; // Easiest grade first
+}
+
+// discipline: only count sends of one discipline (boulder and route grades don't mix in one pyramid)
+export async function fetchSendsSummary(period: SendPeriod = 'month', discipline?: Discipline): Promise<void> {
   try {
-    const accountId = localStorage.getItem("account_id");
-    if (!accountId) {
-      console.error("No account ID found in localStorage.");
+    const token = localStorage.getItem("token");
+    if (!token) {
+      console.error("No session token found in localStorage.");
       return;
     }
 
-    const [total, gradeCounts] = await invoke<[number, [string, number][]]>(
-      'get_sends_summary', 
-      { accountId }
-    );
-
-    console.log('Backend returned data:', { total, gradeCounts }); // Debugging log
-
-    // Ensure gradeCounts is defined and an array
-    if (Array.isArray(gradeCounts)) {
-      const byGrade: Record<string, number> = {};
-      gradeCounts.forEach(([grade, count]) => {
-        byGrade[grade] = count;
-      });
+    const summary = await invoke<{
+      total: number;
+      by_grade: { grade: string; count: number }[];
+      periods: PeriodSends[];
+      by_discipline: [Discipline, number][];
+      by_send_style: [SendStyle, number][];
+    }>('get_sends_summary', { token, period, discipline });
+
+    // Grades arrive in scale order, so byGrade keeps that order
+    const byGrade: Record<string, number> = {};
+    summary.by_grade.forEach(({ grade, count }) => {
+      byGrade[grade] = count;
+    });
 
-      sendsSummary.set({ total, byGrade });
-    } else {
-      console.error('Invalid data format for gradeCounts:', gradeCounts);
-    }
+    sendsSummary.set({
+      total: summary.total,
+      byGrade,
+      periods: summary.periods,
+      byDiscipline: Object.fromEntries(summary.by_discipline),
+      bySendStyle: Object.fromEntries(summary.by_send_style),
+    });
   } catch (error) {
     console.error('Error fetching sends summary:', error);
   }
 }
 
 // Same pattern for style & holds
-export async function fetchStylesSummary() {
+export async function fetchStylesSummary(discipline?: Discipline) {
   try {
-    const accountId = localStorage.getItem("account_id");
-    if (!accountId) {
-      console.error("No account ID found in localStorage.");
+    const token = localStorage.getItem("token");
+    if (!token) {
+      console.error("No session token found in localStorage.");
       return;
     }
 
-    const result: [string, number, number][] = await invoke('get_styles_summary', { accountId });
-    const summary = result.map(([style, done, practicing]) => ({ style, done, practicing }));
+    const result: TagSummary[] = await invoke('get_styles_summary', { token, discipline });
+    const summary = result.map((row) => ({ ...row, style: row.tag, done: row.sent, practicing: row.open }));
     stylesSummary.set(summary);
     console.log('Fetched styles summary:', summary);
   } catch (err) {
@@ -136,16 +243,16 @@ export async function fetchStylesSummary() {
   }
 }
 
-export async function fetchHoldsSummary() {
+export async function fetchHoldsSummary(discipline?: Discipline) {
   try {
-    const accountId = localStorage.getItem("account_id");
-    if (!accountId) {
-      console.error("No account ID found in localStorage.");
+    const token = localStorage.getItem("token");
+    if (!token) {
+      console.error("No session token found in localStorage.");
       return;
     }
 
-    const result: [string, number, number][] = await invoke('get_holds_summary', { accountId });
-    const summary = result.map(([holds, done, practicing]) => ({ holds, done, practicing }));
+    const result: TagSummary[] = await invoke('get_holds_summary', { token, discipline });
+    const summary = result.map((row) => ({ ...row, holds: row.tag, done: row.sent, practicing: row.open }));
     holdsSummary.set(summary);
     console.log('Fetched holds summary:', summary);
   } catch (err) {
@@ -156,141 +263,29 @@ export async function fetchHoldsSummary() {
 // FETCH ACTIVE OR INACTIVE PROJECTS
 // Used in /+page.svelte or /inactiveProjects
 export async function fetchActiveProjects(): Promise<Project[]> {
-  try {
-    const accountId = localStorage.getItem("account_id");
-    if (!accountId) {
-      console.error("No account ID found in localStorage.");
-      return [];
-    }
-
-    const projectsData: unknown = await invoke('get_active_projects', { accountId });
-
-    if (!Array.isArray(projectsData)) {
-      console.error('Unexpected response format:', projectsData);
-      return [];
-    }
-
-    const typedProjectsData: MongoDBProject[] = projectsData;
-
-    const projectInstances: Project[] = typedProjectsData.map((data) => {
-      return new Project({
-        ...data,
-        _id:
-          typeof data._id === 'object' && data._id !== null && '$oid' in data._id
-            ? data._id.$oid
-            : String(data._id || ''),
-        coordinates: Array.isArray(data.coordinates)
-        ? data.coordinates.map((coord) =>
-            typeof coord.lat === 'number' && typeof coord.lng === 'number'
-              ? coord
-              : { lat: 0, lng: 0 } // Default invalid coordinates
-          )
-        : [],
-      });
-    });
-
-    console.log('Processed Project IDs:', projectInstances.map((p) => p._id));
-    return projectInstances;
-  } catch (error) {
-    console.error('Error fetching active projects:', error);
-    return [];
-  }
+  return fetchFilteredProjects(true);
 }
 
 // Function to fetch inactive projects
 export async function fetchInactiveProjects(): Promise<Project[]> {
-  try {
-    const accountId = localStorage.getItem("account_id");
-    if (!accountId) {
-      console.error("No account ID found in localStorage.");
-      return [];
-    }
-
-    const projectsData: unknown = await invoke('get_inactive_projects', { accountId });
-
-    if (!Array.isArray(projectsData)) {
-      console.error('Unexpected response format:', projectsData);
-      return [];
-    }
-
-    const typedProjectsData: MongoDBProject[] = projectsData;
-
-    const projectInstances: Project[] = typedProjectsData.map((data) => {
-      return new Project({
-        ...data,
-        _id:
-          typeof data._id === 'object' && data._id !== null && '$oid' in data._id
-            ? data._id.$oid
-            : String(data._id || ''),
-        coordinates: data.coordinates || [],
-      });
-    });
-
-    console.log('Processed Project IDs:', projectInstances.map((p) => p._id));
-    return projectInstances;
-  } catch (error) {
-    console.error('Error fetching active projects:', error);
-    return [];
-  }
+  return fetchFilteredProjects(false);
 }
 
-// FILTERED FETCHING (by grade, sent, style, holds)
+// FILTERED FETCHING (by grade, sent, style, holds, location and wall)
 // Used by the filters in your UI
+export interface PlaceFilter {
+  location_id?: string;
+  wall_id?: string;
+}
+
 export async function fetchActiveFilteredProjects(
   grades: string[] = [],
   sentStatus: string = '',
   styles: string[] = [],
   holds: string[] = [],
+  place: PlaceFilter = {},
 ): Promise<Project[]> {
-  try {
-    const accountId = localStorage.getItem("account_id");
-    if (!accountId) {
-      console.error("No account ID found in localStorage.");
-      return [];
-    }
-
-    console.log('Sending to Rust:', { grades, sentStatus, styles, holds }); // Log filters
-
-    const projectsData: unknown = await invoke('get_active_filtered_projects', {
-      accountId,
-      grades,
-      sentStatus,
-      styles,
-      holds,
-    });
-
-    console.log('Received from Rust:', projectsData); // Log what comes back
-
-    if (!Array.isArray(projectsData)) {
-      console.error('Unexpected response format:', projectsData);
-      return [];
-    }
-
-    const typedProjectsData: MongoDBProject[] = projectsData;
-
-    const projectInstances: Project[] = typedProjectsData.map((data) => {
-      return new Project({
-        ...data,
-        _id:
-          typeof data._id === 'object' && data._id !== null && '$oid' in data._id
-            ? data._id.$oid
-            : String(data._id || ''),
-        coordinates: Array.isArray(data.coordinates)
-          ? data.coordinates.map((coord) =>
-              typeof coord.lat === 'number' && typeof coord.lng === 'number'
-                ? coord
-                : { lat: 0, lng: 0 } // Default invalid coordinates
-            )
-          : [],
-      });
-    });
-
-    console.log('Processed Project IDs:', projectInstances.map((p) => p._id));
-    return projectInstances;
-  } catch (error) {
-    console.error('Error fetching active projects:', error);
-    return [];
-  }
+  return fetchFilteredProjects(true, grades, sentStatus, styles, holds, place);
 }
 
 // Function to fetch inactive projects with filters
@@ -299,60 +294,47 @@ export async function fetchInactiveFilteredProjects(
   sentStatus: string = '',
   styles: string[] = [],
   holds: string[] = [],
+  place: PlaceFilter = {},
 ): Promise<Project[]> {
-  try {
-    const accountId = localStorage.getItem("account_id");
-    if (!accountId) {
-      console.error("No account ID found in localStorage.");
-      return [];
-    }
-
-    console.log('Sending to Rust:', { grades, sentStatus, styles, holds }); // Log filters
-
-    const projectsData: unknown = await invoke('get_inactive_filtered_projects', {
-      accountId,
-      grades,
-      sentStatus,
-      styles,
-      holds,
-    });
-
-    console.log('Received from Rust:', projectsData); // Log what comes back
+  return fetchFilteredProjects(false, grades, sentStatus, styles, holds, place);
+}
 
-    if (!Array.isArray(projectsData)) {
-      console.error('Unexpected response format:', projectsData);
+async function fetchFilteredProjects(
+  active: boolean,
+  grades: string[] = [],
+  sentStatus: string = '',
+  styles: string[] = [],
+  holds: string[] = [],
+  place: PlaceFilter = {},
+): Promise<Project[]> {
+  try {
+    const token = localStorage.getItem("token");
+    if (!token) {
+      console.error("No session token found in localStorage.");
       return [];
     }
 
-    const typedProjectsData: MongoDBProject[] = projectsData;
-
-    const projectInstances: Project[] = typedProjectsData.map((data) => {
-      return new Project({
-        ...data,
-        _id:
-          typeof data._id === 'object' && data._id !== null && '$oid' in data._id
-            ? data._id.$oid
-            : String(data._id || ''),
-        coordinates: Array.isArray(data.coordinates)
-          ? data.coordinates.map((coord) =>
-              typeof coord.lat === 'number' && typeof coord.lng === 'number'
-                ? coord
-                : { lat: 0, lng: 0 } // Default invalid coordinates
-            )
-          : [],
-      });
-    });
+    const query: ProjectQuery = {
+      active,
+      sent: sentFilter(sentStatus),
+      grades: grades.length ? grades : undefined,
+      styles: styles.length ? styles : undefined,
+      holds: holds.length ? holds : undefined,
+      location_id: place.location_id || undefined,
+      wall_id: place.wall_id || undefined,
+    };
 
+    const projectInstances = await fetchAllProjects(query);
     console.log('Processed Project IDs:', projectInstances.map((p) => p._id));
     return projectInstances;
   } catch (error) {
-    console.error('Error fetching inactive projects:', error);
+    console.error(`Error fetching ${active ? 'active' : 'inactive'} projects:`, error);
     return [];
   }
 }
 
 // IMAGE HELPERS
-// Handles safe filenames + Cloudinary upload
+// Handles safe filenames + image upload
 export const sanitizeFileName = (image: any): string => {
   const timestamp = Date.now();
   const extension = image.format || 'jpg';
@@ -363,56 +345,99 @@ export const sanitizeFileName = (image: any): string => {
   return `${timestamp}_${sanitizedName}.${extension}`;
 };
 
-export async function uploadImageToCloudinary(imageFile: File): Promise<string | null> {
-  try {
-    const sanitizedFileName = imageFile.name;
-    console.log("Uploading image with sanitized name:", sanitizedFileName);
+// What upload_image returns: save url, public_id and variants as the project's image_path, image_public_id and image_variants
+export interface UploadedImage {
+  url: string;
+  public_id: string;
+  variants: StoredVariant[];
+  sizes: {
+    original_bytes: number; // what was sent, before resizing and re-encoding
+    bytes: number;
+    width: number;
+    height: number;
+    medium_bytes: number;
+    medium_width: number;
+    medium_height: number;
+    thumbnail_bytes: number;
+    thumbnail_width: number;
+    thumbnail_height: number;
+  };
+}
 
+// projectId: set when the image is for an existing project, so the stored image can be traced back to it
+export async function uploadImage(imageFile: File, projectId?: string): Promise<UploadedImage | null> {
+  try {
     // Convert the image to binary (Uint8Array)
     const imageData = new Uint8Array(await imageFile.arrayBuffer());
 
-    // Invoke the Rust function to upload the image
-    const imagePath = await invoke<string>('upload_image', {
+    // The backend stores it in the configured image store (Cloudinary, S3/MinIO or the app data folder)
+    const uploaded = await invoke<UploadedImage>('upload_image', {
       imageData,
-      imageName: sanitizedFileName,
+      imageName: imageFile.name,
+      projectId,
+      token: localStorage.getItem('token'),
     });
 
-    console.log('Cloudinary response:', imagePath);
-    console.log('Image size (bytes):', imageFile.size);
-
-    // Ensure the upload was successful and return the Cloudinary URL
-    if (!imagePath || typeof imagePath !== 'string') {
+    // Ensure the upload was successful and return the image URL
+    if (!uploaded || typeof uploaded.url !== 'string') {
       throw new Error('Image upload failed or invalid response.');
     }
+    console.log(`Image stored: ${uploaded.sizes.original_bytes} bytes sent, ${uploaded.sizes.bytes} bytes kept`);
 
-    return imagePath; // Return Cloudinary image URL
+    return uploaded;
   } catch (error) {
     console.error('Error uploading image:', error);
     return null;
   }
 }
 
+/**
+ * URL of a project's image best suited to show it `width` pixels wide, null when it has no image.
+ * With Cloudinary the image is scaled to the exact width, otherwise it is the closest stored variant.
+ */
+export async function fetchImageVariant(projectId: string, width: number): Promise<string | null> {
+  try {
+    return await invoke<string | null>('get_image_variant', { projectId, width, token: localStorage.getItem('token') });
+  } catch (error) {
+    console.error('Error fetching image variant:', error);
+    return null;
+  }
+}
+
+/**
+ * What to put in <img src> for a project's image_path.
+ * Images in the local image store are file paths, which the webview loads through the asset protocol.
+ */
+export function imageSrc(imagePath: string): string {
+  if (!imagePath || /^(https?|data|blob|asset):/.test(imagePath) || imagePath.startsWith('/images/')) {
+    return imagePath;
+  }
+  return convertFileSrc(imagePath);
+}
+
 // CREATE OR EDIT PROJECTS
 // This talks to the Tauri backend
-// Function to add a new project with image upload to Cloudinary
+// Function to add a new project with its image upload
 export async function addProject(newProject: Project, imageFile: File): Promise<void> {
   try {
-    // Upload the image to Cloudinary
-    const imagePath = await uploadImageToCloudinary(imageFile);
-    if (!imagePath) throw new Error('Image upload failed.');
+    // Upload the image to the image store
+    const uploaded = await uploadImage(imageFile);
+    if (!uploaded) throw new Error('Image upload failed.');
 
-    // Create a new Project instance with the Cloudinary URL for the image
+    // Create a new Project instance with the URL of the uploaded image
     const projectWithImage = new Project({
       ...newProject,
-      image_path: imagePath, // Store the Cloudinary URL here
+      image_path: uploaded.url, // Store the image URL here
+      image_public_id: uploaded.public_id,
+      image_variants: uploaded.variants,
       coordinates: newProject.coordinates || [],
     });
 
-    const account_id = localStorage.getItem("account_id");
+    const token = localStorage.getItem("token");
 
     // Convert project to map and send to Rust backend to insert
     // await invoke('insert_project', { project: projectWithImage.toMap() });
-    await invoke('insert_project', { project: projectWithImage.toMap(), accountId: account_id });
+    await invoke('insert_project', { project: projectWithImage.toMap(), token });
 
     // Refresh the project list after adding the new project
     await initializeProjectsList();
@@ -425,7 +450,7 @@ export async function addProject(newProject: Project, imageFile: File): Promise<
 export async function fetchProjectById(projectId: string): Promise<Project | null> {
   try {
     // Call the Tauri backend to get the project details
-    const projectData: unknown = await invoke('get_project_by_id', { id: projectId });
+    const projectData: unknown = await invoke('get_project_by_id', { id: projectId, token: localStorage.getItem('token') });
 
     if (!projectData || typeof projectData !== 'object') {
       console.error('Unexpected response format:', projectData);
@@ -437,10 +462,9 @@ export async function fetchProjectById(projectId: string): Promise<Project | nul
     // Create a new Project instance and include all project data
     const project = new Project({
       ...data,
-      _id:
-        typeof data._id === 'object' && data._id !== null && '$oid' in data._id
-          ? data._id.$oid
-          : String(data._id || ''),
+      _id: hexId(data._id) || '',
+      location_id: hexId(data.location_id),
+      wall_id: hexId(data.wall_id),
     });
 
     // Return the full project data including annotations (coordinates)
@@ -457,38 +481,49 @@ export async function editProject(updatedProject: Project, imageFile?: File): Pr
     console.log("Editing project, current coordinates:", updatedProject.coordinates);
 
     let savedImagePath: string = updatedProject.image_path;
+    let uploaded: UploadedImage | null = null;
 
     // If a new image is selected, upload it and get the new image URL
     if (imageFile) {
-      console.log("Uploading image to Cloudinary...");
-      const uploadedImageUrl = await uploadImageToCloudinary(imageFile);
-      if (uploadedImageUrl) {
-        savedImagePath = uploadedImageUrl;
+      console.log("Uploading image...");
+      uploaded = await uploadImage(imageFile, updatedProject._id);
+      if (uploaded) {
+        savedImagePath = uploaded.url;
       }
     }
 
+    const token = localStorage.getItem("token");
+
      // 👇 Fetch the existing project from the backend to preserve coordinates
     const existingProject: Project = await invoke("get_project_by_id", {
       id: updatedProject._id,
+      token,
     });
 
     console.log("Fetched existing project for coordinates:", existingProject);
 
     console.log("Updating project:", updatedProject);
 
-    const account_id = localStorage.getItem("account_id");
     // Ensure correct formatting before sending to Rust
     const formattedProject = {
       _id: updatedProject._id,
-      account_id,
       date_time: typeof updatedProject.date_time === "number" 
         ? updatedProject.date_time 
         : new Date(updatedProject.date_time).getTime(), // Convert to timestamp
       image_path: savedImagePath,
+      image_public_id: uploaded ? uploaded.public_id : existingProject.image_public_id, // Kept with the old image
+      image_variants: uploaded ? uploaded.variants : existingProject.image_variants,
       is_sent: updatedProject.is_sent ? 1 : 0, // Convert boolean to integer
       attempts: updatedProject.attempts,
+      discipline: updatedProject.discipline,
+      send_style: updatedProject.is_sent ? updatedProject.send_style : undefined,
       grade: updatedProject.grade,
+      location_id: updatedProject.location_id,
+      wall_id: updatedProject.wall_id,
+      grade_system: updatedProject.grade_system,
       is_active: updatedProject.is_active ? 1 : 0, // Convert boolean to integer
+      inactive_reason: updatedProject.is_active ? undefined : updatedProject.inactive_reason, // Kept while still inactive
+      inactive_since: updatedProject.is_active ? undefined : updatedProject.inactive_since,
       coordinates: existingProject.coordinates,
       style: updatedProject.style,
       holds: updatedProject.holds,
@@ -496,7 +531,7 @@ export async function editProject(updatedProject: Project, imageFile?: File): Pr
 
     console.log("Project details being sent to backend:", formattedProject);
 
-    await invoke("update_project", { project: formattedProject });
+    await invoke("update_project", { project: formattedProject, token });
     console.log("Project updated successfully.");
 
     // Refresh project list
@@ -523,6 +558,7 @@ export async function updateAnnotations(projectId: string, annotationsData: { la
         project_id: projectId,
         annotations: annotationsDataAsNumbers,
       },
+      token: localStorage.getItem('token'),
     });
 
     console.log(`Annotations for project ${projectId} saved successfully.`);
@@ -531,16 +567,53 @@ export async function updateAnnotations(projectId: string, annotationsData: { la
   }
 }
 
+// ATTEMPT LOG
+// Attempts as the backend returns them, with ObjectIds as { $oid }
+type StoredId = string | { $oid: string };
+type StoredAttempt = Omit<Attempt, '_id' | 'project_id' | 'session_id'> & { _id: StoredId; project_id: StoredId; session_id?: StoredId };
+
+function toAttempt(data: StoredAttempt): Attempt {
+  const hex = (id: StoredId) => (typeof id === 'object' ? id.$oid : id);
+  return { ...data, _id: hex(data._id), project_id: hex(data.project_id), session_id: data.session_id && hex(data.session_id) };
+}
+
+// Logs an attempt. The backend then updates the project's attempts, sent state and sent date from its log.
+export async function logAttempt(attempt: Attempt): Promise<Attempt | null> {
+  try {
+    const saved = await invoke<StoredAttempt>('log_attempt', { attempt, token: localStorage.getItem('token') });
+    await initializeProjectsList();
+    return toAttempt(saved);
+  } catch (error) {
+    console.error('Error logging attempt:', error);
+    return null;
+  }
+}
+
+// Attempts on one project, oldest first
+export async function listAttempts(projectId: string): Promise<Attempt[]> {
+  try {
+    const attempts = await invoke<StoredAttempt[]>('list_attempts', { projectId, token: localStorage.getItem('token') });
+    return attempts.map(toAttempt);
+  } catch (error) {
+    console.error('Error fetching attempts:', error);
+    return [];
+  }
+}
+
 // Add or update a project (CURRENTLY NOT IN USE)
 export async function saveProject(project: Project, imageFile?: File): Promise<void> {
   try {
     let updatedImagePath = project.image_path;
+    let updatedPublicId = project.image_public_id;
+    let updatedVariants = project.image_variants;
 
     // If a new image is provided, upload it.
     if (imageFile) {
-      const uploadedUrl = await uploadImageToCloudinary(imageFile);
-      if (!uploadedUrl) throw new Error("Image upload failed");
-      updatedImagePath = uploadedUrl;
+      const uploaded = await uploadImage(imageFile, project._id);
+      if (!uploaded) throw new Error("Image upload failed");
+      updatedImagePath = uploaded.url;
+      updatedPublicId = uploaded.public_id;
+      updatedVariants = uploaded.variants;
     }
 
     if (project._id) {
@@ -549,19 +622,23 @@ export async function saveProject(project: Project, imageFile?: File): Promise<v
         _id: project._id,
         date_time: typeof project.date_time === "number" ? project.date_time : new Date(project.date_time).getTime(),
         image_path: updatedImagePath,
+        image_public_id: updatedPublicId,
+        image_variants: updatedVariants,
         is_sent: project.is_sent ? 1 : 0,
         attempts: project.attempts,
         grade: project.grade,
         is_active: project.is_active ? 1 : 0,
       };
-      await invoke("update_project", { project: formattedProject });
+      await invoke("update_project", { project: formattedProject, token: localStorage.getItem('token') });
     } else {
       // Create new project with image URL
       const projectWithImage = new Project({
         ...project,
         image_path: updatedImagePath,
+        image_public_id: updatedPublicId,
+        image_variants: updatedVariants,
       });
-      await invoke('insert_project', { project: projectWithImage.toMap() });
+      await invoke('insert_project', { project: projectWithImage.toMap(), token: localStorage.getItem('token') });
     }
 
     // Refresh the projects list if needed.
@@ -0,0 +1,60 @@
+// src/stores/sessionsStore.ts
+
+import { writable } from 'svelte/store';
+import { invokeWithSession as invoke } from '../controllers/accountsController';
+
+// Mirrors ClimbingSession and SessionSummary in src-tauri (database_helper.rs, project_stats.rs)
+export interface ClimbingSession {
+  _id: string;
+  started_at: number; // UNIX seconds
+  ended_at?: number; // missing while the session is running
+  location?: string;
+  effort?: number; // 1 to 10
+  notes?: string;
+}
+
+export interface SessionSummary extends ClimbingSession {
+  attempts: number;
+  sends: number;
+  projects: number;
+  duration_minutes?: number;
+  load?: number; // effort x minutes
+}
+
+// The running session, null when none is
+export const runningSession = writable<ClimbingSession | null>(null);
+// Sessions loaded by fetchSessions, newest first
+export const sessions = writable<SessionSummary[]>([]);
+
+type Stored<T> = Omit<T, '_id'> & { _id: string | { $oid: string } };
+
+function withHexId<T extends { _id: string }>(data: Stored<T>): T {
+  return { ...data, _id: typeof data._id === 'object' ? data._id.$oid : data._id } as T;
+}
+
+// Starts a session now. Attempts logged while it runs are linked to it.
+export async function startSession(location?: string, notes?: string): Promise<void> {
+  const started = await invoke<Stored<ClimbingSession>>('start_session', { token: localStorage.getItem('token'), location, notes });
+  runningSession.set(withHexId(started));
+}
+
+// Ends a session now, with the perceived effort (1 to 10)
+export async function endSession(id: string, effort?: number, notes?: string): Promise<void> {
+  await invoke('end_session', { token: localStorage.getItem('token'), id, effort, notes });
+  runningSession.set(null);
+  await fetchSessions();
+}
+
+// Loads the sessions started between from and to (UNIX seconds), all of them by default
+export async function fetchSessions(from?: number, to?: number): Promise<void> {
+  try {
+    const list = await invoke<Stored<SessionSummary>[]>('list_sessions', { token: localStorage.getItem('token'), from, to });
+    const summaries = list.map((summary) => withHexId<SessionSummary>(summary));
+    sessions.set(summaries);
+
+    const running = summaries.find((summary) => summary.ended_at === undefined);
+    runningSession.set(running ?? null);
+  } catch (error) {
+    console.error('Error fetching sessions:', error);
+  }
+}
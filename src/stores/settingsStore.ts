@@ -1,6 +1,7 @@
 // src/stores/settingsStore.ts
 
 import { writable } from 'svelte/store';
+import { invokeWithSession as invoke } from '../controllers/accountsController';
 
 // Check localStorage for the user's last selection
 const storedSystem = localStorage.getItem('gradeSystem') || 'V-Scale';
@@ -43,6 +44,55 @@ export const setGradeSystem = (system: string) => {
     localStorage.setItem('gradeSystem', system);
   };
 
+// GRADE SYSTEMS (see src-tauri/src/grade.rs)
+// Built-in scales have the ids 'v_scale' and 'font', custom scales use their database id.
+export interface GradeInfo {
+  name: string;
+  difficulty: number; // 10 per V grade, V0 = 0
+  colour?: string;
+}
+
+export interface GradeSystemInfo {
+  id: string;
+  name: string;
+  custom: boolean;
+  grades: GradeInfo[]; // Easiest first
+}
+
+// A gym colour-circuit scale. Each circuit covers a range of V grades.
+export interface CustomScale {
+  _id?: string | { $oid: string }; // Missing for a new scale
+  name: string;
+  circuits: { name: string; colour?: string; lowest: string; highest: string }[];
+}
+
+export const gradeSystems = writable<GradeSystemInfo[]>([]);
+
+export async function fetchGradeSystems(): Promise<void> {
+  try {
+    gradeSystems.set(await invoke<GradeSystemInfo[]>('list_grade_systems', { token: localStorage.getItem('token') }));
+  } catch (error) {
+    console.error('Error fetching grade systems:', error);
+  }
+}
+
+// Creates or replaces a custom scale, then refreshes the list
+export async function saveGradeScale(scale: CustomScale): Promise<CustomScale> {
+  const saved = await invoke<CustomScale>('save_grade_scale', { scale, token: localStorage.getItem('token') });
+  await fetchGradeSystems();
+  return saved;
+}
+
+export async function deleteGradeScale(id: string): Promise<void> {
+  await invoke('delete_grade_scale', { id, token: localStorage.getItem('token') });
+  await fetchGradeSystems();
+}
+
+// Closest grade of `to` for a grade of `from`
+export async function convertGrade(grade: string, from: string, to: string): Promise<string> {
+  return await invoke<string>('convert_grade', { grade, from, to, token: localStorage.getItem('token') });
+}
+
 // Export the style and hold tags so they can be reused
 export const allStyles = ['Topout', 'Traverse', 'Vert', 'Overhang', 'Slab', 'Roof', 'Static', 'Dyno', 'Technical', 'Reachy', 'Sustained', 'Power', 'Campusing'];
 export const allHolds = ['Slopers', 'Crimps', 'Jugs', 'Pinches', 'Pockets', 'Undercut', 'Side Pull', 'Hidden Hold/s', 'Volumes'];